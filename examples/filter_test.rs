@@ -1,3 +1,7 @@
+//! Compares the output of the available resampling filters on a sample image.
+
+#![allow(unused_crate_dependencies)]
+
 use anyhow::Result;
 use image::imageops::FilterType;
 use std::path::Path;
@@ -18,7 +22,7 @@ pub fn test_filter_quality(input_path: &Path, output_dir: &Path) -> Result<()> {
 
     println!("Testing filters on image: {}", input_path.display());
     println!("Original size: {}x{}", img.width(), img.height());
-    println!("Target size: {}x{}", target_width, target_height);
+    println!("Target size: {target_width}x{target_height}");
     println!();
 
     for (name, filter) in filters {
@@ -26,7 +30,7 @@ pub fn test_filter_quality(input_path: &Path, output_dir: &Path) -> Result<()> {
         let resized = img.resize_exact(target_width, target_height, filter);
         let duration = start.elapsed();
 
-        let output_path = output_dir.join(format!("test_{}.jpg", name));
+        let output_path = output_dir.join(format!("test_{name}.jpg"));
         resized.save(&output_path)?;
 
         println!(
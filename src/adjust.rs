@@ -0,0 +1,217 @@
+use anyhow::{ensure, Result};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::color;
+use crate::presets::GrayscaleMode;
+
+/// Most steps a stack holds, so presets carrying one stay `Copy`.
+pub const MAX_STEPS: usize = 8;
+
+/// One change to a resized image.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Adjustment {
+    Grayscale(GrayscaleMode),
+    /// Added to every color channel, -255 to 255.
+    Brightness(i32),
+    /// Contrast change in percent, negative values flatten the image.
+    Contrast(f32),
+    /// Unsharp mask blurring by `sigma` pixels, only differences above `threshold` levels
+    /// are sharpened.
+    Sharpen {
+        sigma: f32,
+        threshold: i32,
+    },
+}
+
+impl Adjustment {
+    /// Each kind of step with a moderate setting.
+    pub const ALL: [Self; 4] = [
+        Self::Grayscale(GrayscaleMode::Luminosity),
+        Self::Brightness(10),
+        Self::Contrast(10.0),
+        Self::Sharpen {
+            sigma: 1.0,
+            threshold: 2,
+        },
+    ];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Grayscale(_) => "Grayscale",
+            Self::Brightness(_) => "Brightness",
+            Self::Contrast(_) => "Contrast",
+            Self::Sharpen { .. } => "Sharpen",
+        }
+    }
+
+    pub fn validate(self) -> Result<()> {
+        match self {
+            Self::Grayscale(_) => {}
+            Self::Brightness(amount) => ensure!(
+                (-255..=255).contains(&amount),
+                "Brightness must be -255 to 255, got {amount}"
+            ),
+            Self::Contrast(percent) => ensure!(
+                percent.is_finite() && percent >= -100.0,
+                "Contrast must be at least -100%, got {percent}"
+            ),
+            Self::Sharpen { sigma, threshold } => ensure!(
+                sigma.is_finite() && sigma > 0.0 && threshold >= 0,
+                "Sharpening needs a positive radius and a threshold of at least 0"
+            ),
+        }
+        Ok(())
+    }
+
+    pub fn apply(self, img: &DynamicImage) -> DynamicImage {
+        match self {
+            Self::Grayscale(mode) => color::to_grayscale(img, mode),
+            Self::Brightness(amount) => img.brighten(amount),
+            Self::Contrast(percent) => img.adjust_contrast(percent),
+            Self::Sharpen { sigma, threshold } => img.unsharpen(sigma, threshold),
+        }
+    }
+}
+
+/// Adjustments applied one after another to every resized image, saved as a list.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(into = "Vec<Adjustment>", try_from = "Vec<Adjustment>")]
+pub struct AdjustmentStack {
+    // The first `len` are the steps, the rest are unused
+    steps: [Adjustment; MAX_STEPS],
+    len: usize,
+}
+
+impl AdjustmentStack {
+    pub const EMPTY: Self = Self {
+        steps: [Adjustment::Brightness(0); MAX_STEPS],
+        len: 0,
+    };
+
+    pub fn steps(&self) -> &[Adjustment] {
+        &self.steps[..self.len]
+    }
+
+    pub fn steps_mut(&mut self) -> &mut [Adjustment] {
+        &mut self.steps[..self.len]
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub const fn is_full(&self) -> bool {
+        self.len == MAX_STEPS
+    }
+
+    /// Adds `step` at the end, failing once the stack holds `MAX_STEPS`.
+    pub fn push(&mut self, step: Adjustment) -> Result<()> {
+        ensure!(
+            !self.is_full(),
+            "A look holds at most {MAX_STEPS} adjustments"
+        );
+        self.steps[self.len] = step;
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.steps[index..self.len].rotate_left(1);
+        self.len -= 1;
+    }
+
+    /// Swaps the step at `index` with the one before it.
+    pub const fn move_up(&mut self, index: usize) {
+        if index > 0 {
+            self.steps.swap(index - 1, index);
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.steps().iter().try_for_each(|step| step.validate())
+    }
+
+    /// `img` with every step applied in order.
+    pub fn apply(&self, img: &DynamicImage) -> DynamicImage {
+        self.steps()
+            .iter()
+            .fold(img.clone(), |adjusted, step| step.apply(&adjusted))
+    }
+}
+
+impl From<AdjustmentStack> for Vec<Adjustment> {
+    fn from(stack: AdjustmentStack) -> Self {
+        stack.steps().to_vec()
+    }
+}
+
+impl TryFrom<Vec<Adjustment>> for AdjustmentStack {
+    type Error = anyhow::Error;
+
+    fn try_from(steps: Vec<Adjustment>) -> Result<Self> {
+        let mut stack = Self::EMPTY;
+        for step in steps {
+            stack.push(step)?;
+        }
+        Ok(stack)
+    }
+}
+
+/// A named adjustment stack users can save and apply to later batches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Look {
+    pub name: String,
+    pub adjustments: AdjustmentStack,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preset_store;
+    use crate::test_util;
+
+    fn two_steps(first: Adjustment, second: Adjustment) -> AdjustmentStack {
+        AdjustmentStack::try_from(vec![first, second]).unwrap()
+    }
+
+    #[test]
+    fn steps_apply_in_order() {
+        let img = test_util::gradient(32, 24);
+        let brighten = Adjustment::Brightness(60);
+        let contrast = Adjustment::Contrast(50.0);
+        let stack = two_steps(brighten, contrast);
+
+        let adjusted = stack.apply(&img);
+        assert_eq!(adjusted, contrast.apply(&brighten.apply(&img)));
+        assert_eq!(adjusted, stack.apply(&img));
+        assert_ne!(adjusted, two_steps(contrast, brighten).apply(&img));
+    }
+
+    #[test]
+    fn looks_round_trip_through_the_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("looks.json");
+        let looks = vec![Look {
+            name: "Punchy mono".to_owned(),
+            adjustments: two_steps(
+                Adjustment::Grayscale(GrayscaleMode::Luminosity),
+                Adjustment::Sharpen {
+                    sigma: 1.5,
+                    threshold: 3,
+                },
+            ),
+        }];
+
+        preset_store::save_looks(&path, &looks).unwrap();
+        let loaded = preset_store::load_looks(&path).unwrap();
+
+        assert_eq!(loaded, looks);
+    }
+
+    #[test]
+    fn stacks_beyond_the_limit_are_rejected() {
+        let steps = vec![Adjustment::Brightness(1); MAX_STEPS + 1];
+        assert!(serde_json::from_value::<AdjustmentStack>(serde_json::json!(steps)).is_err());
+    }
+}
@@ -1,32 +1,334 @@
+use anyhow::Context as _;
 use eframe::egui;
 use rfd::FileDialog;
-use std::path::PathBuf;
-use std::sync::mpsc;
-use std::thread;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-use crate::presets::{OutputFormat, ResizePreset};
+use crate::adjust::{Adjustment, AdjustmentStack, Look};
+use crate::checksum::ChecksumSink;
+use crate::contact_sheet;
+use crate::discovery;
+use crate::encoder_params::{self, EncoderParams};
+#[cfg(feature = "event-server")]
+use crate::event_server::EventServer;
+use crate::gallery;
+use crate::jobs;
+use crate::kernel::{self, Kernel};
+use crate::manifest;
+use crate::naming::{self, Naming, OverwritePolicy, Subfolders};
+use crate::overlay;
+#[cfg(feature = "battery-pause")]
+use crate::power;
+use crate::preflight;
+use crate::preset_store;
+use crate::presets::{
+    AnimationPolicy, ByteBudget, Corner, GrayscaleMode, IncompatibleOutput, MaskShape,
+    MetadataPolicy, MultiImageSelect, NamedPreset, NormalizedCrop, OutputFormat, PixelBudget,
+    ResampleFilter, ResizeMode, ResizePreset,
+};
+use crate::probe::{self, SourceInfo};
 use crate::resizer::ImageResizer;
+#[cfg(feature = "s3")]
+use crate::s3::{S3Config, S3Sink};
+use crate::sink::{FileSystemSink, MemorySink, OutputSink};
+use crate::sorting::{self, SortOrder};
+#[cfg(feature = "net")]
+use crate::source::UrlSource;
+use crate::source::{FileSource, InputSource};
+use crate::summary::{self, BatchSummary};
+#[cfg(feature = "video")]
+use crate::video;
+use crate::xmp::{self, XmpSink};
 
-#[derive(Debug)]
+/// How long closing the window waits for the image being processed to finish.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Most images the parallelism limit allows at once, one per CPU core.
+fn max_workers() -> usize {
+    std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+}
+
+/// How far a batch is from 0 to 1, for the progress bar.
+fn progress_fraction(current: usize, total: usize) -> f32 {
+    // An empty batch reports 0 of 0, don't turn that into NaN
+    if total == 0 {
+        1.0
+    } else {
+        current as f32 / total as f32
+    }
+}
+
+/// The thread running a batch, with a channel that disconnects as soon as it has finished.
+struct BatchWorker {
+    handle: JoinHandle<()>,
+    done: mpsc::Receiver<()>,
+}
+
+impl BatchWorker {
+    fn spawn(work: impl FnOnce() + Send + 'static) -> Self {
+        let (done_sender, done) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            // Dropped when the work returns or panics, which wakes `shut_down`
+            let _done_sender: mpsc::Sender<()> = done_sender;
+            work();
+        });
+        Self { handle, done }
+    }
+
+    /// Sets `cancel` and waits up to `timeout` for the batch to stop, returning whether it
+    /// did. A worker still busy after that is left to finish on its own.
+    fn shut_down(self, cancel: &AtomicBool, timeout: Duration) -> bool {
+        cancel.store(true, Ordering::Relaxed);
+        match self.done.recv_timeout(timeout) {
+            Err(mpsc::RecvTimeoutError::Timeout) => false,
+            Ok(()) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+                let _ = self.handle.join();
+                true
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
 enum ProcessingStatus {
     Idle,
-    Processing { current: usize, total: usize },
-    Completed { successful: usize, failed: usize },
+    Processing {
+        current: usize,
+        total: usize,
+    },
+    Completed {
+        successful: usize,
+        skipped: usize,
+        failed: usize,
+        results: Vec<FileResult>,
+    },
+    /// Stopped early by the user, the inputs after the first `successful + skipped + failed`
+    /// were not processed.
+    Cancelled {
+        successful: usize,
+        skipped: usize,
+        failed: usize,
+        total: usize,
+        results: Vec<FileResult>,
+    },
     Error(String),
+    Message(String),
+}
+
+/// How one input of a batch went, a row of the results table.
+#[derive(Debug, Serialize)]
+struct FileResult {
+    input: PathBuf,
+    /// Why the input failed, `None` when it was resized or skipped.
+    error: Option<String>,
+    outputs: usize,
+    output_bytes: u64,
+}
+
+/// Choices restored at the next launch.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct PersistedSettings {
+    output_directory: Option<PathBuf>,
+    /// Presets are looked up by name, their fields may change between versions.
+    preset_name: String,
+    use_custom_size: bool,
+    custom_width: String,
+    custom_height: String,
+    mode: ResizeMode,
+    custom_output_format: OutputFormat,
+    limit_parallelism: bool,
+    max_parallel_images: usize,
+}
+
+impl Default for PersistedSettings {
+    fn default() -> Self {
+        Self::from(&ImageResizerApp::new())
+    }
+}
+
+impl From<&ImageResizerApp> for PersistedSettings {
+    fn from(app: &ImageResizerApp) -> Self {
+        Self {
+            output_directory: app.output_directory.clone(),
+            preset_name: app.presets[app.selected_preset].name.to_string(),
+            use_custom_size: app.use_custom_size,
+            custom_width: app.custom_width.clone(),
+            custom_height: app.custom_height.clone(),
+            mode: app.mode,
+            custom_output_format: app.custom_output_format,
+            limit_parallelism: app.limit_parallelism,
+            max_parallel_images: app.max_parallel_images,
+        }
+    }
+}
+
+/// The preview as rendered off the UI thread.
+struct PreviewImages {
+    output: egui::ColorImage,
+    source: egui::ColorImage,
+    layout: overlay::Layout,
+}
+
+/// The first selected image resized, next to its source with the crop and padding marked.
+struct Preview {
+    output: egui::TextureHandle,
+    source: egui::TextureHandle,
+    layout: overlay::Layout,
+}
+
+/// Progress of the header scan that summarizes the selection.
+enum ScanStatus {
+    Scanning { scanned: usize, total: usize },
+    Done(BatchSummary),
 }
 
+// One flag per checkbox in the settings panel
+#[allow(clippy::struct_excessive_bools)]
 pub struct ImageResizerApp {
     selected_files: Vec<PathBuf>,
+    summary: Option<BatchSummary>,
+    scan_receiver: Option<mpsc::Receiver<ScanStatus>>,
+    scan_progress: (usize, usize),
+    inspected: Option<(PathBuf, Result<SourceInfo, String>)>,
+    sort_order: SortOrder,
+    newest_only: bool,
+    newest_count: usize,
+    limit_depth: bool,
+    max_depth: usize,
+    include_hidden: bool,
+    /// Folder the selection was gathered from recursively, `None` for other selections.
+    folder_root: Option<PathBuf>,
     output_directory: Option<PathBuf>,
-    selected_preset: ResizePreset,
+    naming: Naming,
+    tag_batch_id: bool,
+    /// Built-in presets unless the user saved their own list, never empty.
+    presets: Vec<NamedPreset>,
+    /// Index into `presets`.
+    selected_preset: usize,
+    new_preset_name: String,
     custom_width: String,
     custom_height: String,
-    maintain_aspect_ratio: bool,
+    mode: ResizeMode,
+    prevent_upscale: bool,
+    pad_to_target: bool,
+    background: [u8; 4],
+    use_pixel_budget: bool,
+    pixel_budget_megapixels: f32,
+    pixel_budget_multiple: u32,
+    filter: ResampleFilter,
+    use_stretch_filter: bool,
+    stretch_filter: ResampleFilter,
+    use_custom_kernel: bool,
+    custom_kernel: Kernel,
+    aspect_tolerance_percent: f32,
     custom_output_format: OutputFormat,
+    jpeg_quality: u8,
+    webp_quality: f32,
+    webp_lossless: bool,
     use_custom_size: bool,
+    size_from_file_name: bool,
+    animation_policy: AnimationPolicy,
+    incompatible_output: IncompatibleOutput,
+    fast_filter_for_large: bool,
+    fast_filter_megapixels: u32,
+    use_timeout: bool,
+    timeout_seconds: u32,
+    limit_parallelism: bool,
+    max_parallel_images: usize,
+    separate_reads: bool,
+    max_parallel_reads: usize,
+    #[cfg(feature = "mmap")]
+    memory_map_inputs: bool,
+    area_average_integer_factors: bool,
+    date_caption: bool,
+    date_caption_corner: Corner,
+    grayscale: bool,
+    region_mask: bool,
+    grayscale_mode: GrayscaleMode,
+    adjustments: AdjustmentStack,
+    posterize: bool,
+    posterize_levels: u8,
+    dither: bool,
+    new_adjustment: Adjustment,
+    looks: Vec<Look>,
+    new_look_name: String,
+    /// The first selected image with the adjustments applied, while one is shown.
+    look_preview: Option<egui::TextureHandle>,
+    show_preview: bool,
+    /// The image and preset the preview shows or is being made for.
+    preview_key: Option<(PathBuf, ResizePreset)>,
+    /// The first selected image resized with the current preset, or why that failed.
+    preview: Option<Result<Preview, String>>,
+    preview_receiver: Option<mpsc::Receiver<Result<PreviewImages, String>>>,
+    use_mask: bool,
+    mask_shape: MaskShape,
+    mask_radius: u32,
+    crop_text: String,
+    encoder_params_text: String,
+    jpeg_restart_markers: bool,
+    jpeg_restart_interval: u16,
+    use_ssim_target: bool,
+    skip_blurry: bool,
+    min_sharpness: f32,
+    use_byte_budget: bool,
+    byte_budget_kb: u32,
+    quality_floor: u8,
+    ssim_target: f32,
+    generate_gallery: bool,
+    generate_contact_sheet: bool,
+    preserve_xmp: bool,
+    metadata: MetadataPolicy,
+    deskew: bool,
+    split_photos: bool,
+    respect_exif_orientation: bool,
+    multi_image: MultiImageSelect,
+    multi_image_index: u32,
+    xmp_copyright: String,
+    xmp_author: String,
+    average_color: bool,
+    dpr_variants: bool,
+    jpeg_fallback: bool,
+    lqip: bool,
+    write_manifest: bool,
+    write_image_manifest: bool,
+    write_checksums: bool,
+    check_disk_space: bool,
+    #[cfg(feature = "net")]
+    url_text: String,
+    #[cfg(feature = "s3")]
+    upload_to_s3: bool,
+    /// Where uploads go, the credentials are only read from the environment when starting
+    #[cfg(feature = "s3")]
+    s3: S3Config,
+    #[cfg(feature = "video")]
+    video_files: Vec<PathBuf>,
+    #[cfg(feature = "video")]
+    video_interval_secs: f32,
     processing_status: ProcessingStatus,
     processing_receiver: Option<mpsc::Receiver<ProcessingStatus>>,
+    worker: Option<BatchWorker>,
+    cancel: Arc<AtomicBool>,
     log_messages: Vec<String>,
+    #[cfg(feature = "event-server")]
+    event_server: Option<EventServer>,
+    #[cfg(feature = "event-server")]
+    event_server_port: u16,
+    #[cfg(feature = "seam-carving")]
+    seam_carving: bool,
+    #[cfg(feature = "seam-carving")]
+    seam_carving_megapixels: f32,
+    #[cfg(feature = "battery-pause")]
+    battery_pause: bool,
+    #[cfg(feature = "battery-pause")]
+    battery_pause_below: u8,
 }
 
 impl Default for ImageResizerApp {
@@ -39,16 +341,399 @@ impl ImageResizerApp {
     pub fn new() -> Self {
         Self {
             selected_files: Vec::new(),
+            summary: None,
+            scan_receiver: None,
+            scan_progress: (0, 0),
+            inspected: None,
+            sort_order: SortOrder::Selection,
+            newest_only: false,
+            newest_count: 50,
+            limit_depth: false,
+            max_depth: 3,
+            include_hidden: false,
+            folder_root: None,
             output_directory: None,
-            selected_preset: ResizePreset::default(),
+            naming: Naming::default(),
+            tag_batch_id: false,
+            presets: NamedPreset::PRESETS.to_vec(),
+            selected_preset: 0,
+            new_preset_name: String::new(),
             custom_width: "800".to_string(),
             custom_height: "600".to_string(),
-            maintain_aspect_ratio: true,
+            mode: ResizeMode::Fit,
+            prevent_upscale: false,
+            pad_to_target: false,
+            background: [255, 255, 255, 255],
+            use_pixel_budget: false,
+            pixel_budget_megapixels: 1.0,
+            pixel_budget_multiple: 16,
+            filter: ResampleFilter::Lanczos3,
+            use_stretch_filter: false,
+            stretch_filter: ResampleFilter::Triangle,
+            use_custom_kernel: false,
+            custom_kernel: Kernel::Lanczos { lobes: 3.0 },
+            aspect_tolerance_percent: 1.0,
             custom_output_format: OutputFormat::KeepOriginal,
+            jpeg_quality: 85,
+            webp_quality: 80.0,
+            webp_lossless: true,
             use_custom_size: false,
+            size_from_file_name: false,
+            animation_policy: AnimationPolicy::FirstFrame,
+            incompatible_output: IncompatibleOutput::Convert,
+            fast_filter_for_large: false,
+            fast_filter_megapixels: 24,
+            use_timeout: false,
+            timeout_seconds: 30,
+            limit_parallelism: false,
+            max_parallel_images: 2,
+            separate_reads: false,
+            max_parallel_reads: 4,
+            #[cfg(feature = "mmap")]
+            memory_map_inputs: false,
+            area_average_integer_factors: true,
+            date_caption: false,
+            date_caption_corner: Corner::BottomRight,
+            grayscale: false,
+            region_mask: false,
+            grayscale_mode: GrayscaleMode::Luminosity,
+            adjustments: AdjustmentStack::EMPTY,
+            posterize: false,
+            posterize_levels: 4,
+            dither: true,
+            new_adjustment: Adjustment::ALL[0],
+            looks: Vec::new(),
+            new_look_name: String::new(),
+            look_preview: None,
+            show_preview: true,
+            preview_key: None,
+            preview: None,
+            preview_receiver: None,
+            use_mask: false,
+            mask_shape: MaskShape::Circle,
+            mask_radius: 24,
+            crop_text: String::new(),
+            encoder_params_text: String::new(),
+            jpeg_restart_markers: false,
+            jpeg_restart_interval: 8,
+            use_ssim_target: false,
+            skip_blurry: false,
+            min_sharpness: 100.0,
+            use_byte_budget: false,
+            byte_budget_kb: 200,
+            quality_floor: 60,
+            ssim_target: 0.98,
+            generate_gallery: false,
+            generate_contact_sheet: false,
+            preserve_xmp: false,
+            metadata: MetadataPolicy::Strip,
+            deskew: false,
+            split_photos: false,
+            respect_exif_orientation: true,
+            multi_image: MultiImageSelect::Primary,
+            multi_image_index: 0,
+            xmp_copyright: String::new(),
+            xmp_author: String::new(),
+            average_color: false,
+            dpr_variants: false,
+            jpeg_fallback: false,
+            lqip: false,
+            write_manifest: false,
+            write_image_manifest: false,
+            write_checksums: false,
+            check_disk_space: false,
+            #[cfg(feature = "net")]
+            url_text: String::new(),
+            #[cfg(feature = "s3")]
+            upload_to_s3: false,
+            #[cfg(feature = "s3")]
+            s3: S3Config::default(),
+            #[cfg(feature = "video")]
+            video_files: Vec::new(),
+            #[cfg(feature = "video")]
+            video_interval_secs: 1.0,
             processing_status: ProcessingStatus::Idle,
             processing_receiver: None,
+            worker: None,
+            cancel: Arc::new(AtomicBool::new(false)),
             log_messages: Vec::new(),
+            #[cfg(feature = "event-server")]
+            event_server: None,
+            #[cfg(feature = "event-server")]
+            event_server_port: 7878,
+            #[cfg(feature = "seam-carving")]
+            seam_carving: false,
+            #[cfg(feature = "seam-carving")]
+            seam_carving_megapixels: 1.0,
+            #[cfg(feature = "battery-pause")]
+            battery_pause: false,
+            #[cfg(feature = "battery-pause")]
+            battery_pause_below: 50,
+        }
+    }
+
+    /// Starts with the choices saved by the last session, if there was one.
+    pub fn from_storage(storage: Option<&dyn eframe::Storage>) -> Self {
+        let mut app = Self::new();
+        if let Some(path) = preset_store::presets_path() {
+            match preset_store::load_presets(&path) {
+                Ok(presets) if !presets.is_empty() => app.presets = presets,
+                Ok(_) => {}
+                Err(e) => app.add_log_message(format!("Error: {e:#}")),
+            }
+        }
+        if let Some(path) = preset_store::looks_path() {
+            match preset_store::load_looks(&path) {
+                Ok(looks) => app.looks = looks,
+                Err(e) => app.add_log_message(format!("Error: {e:#}")),
+            }
+        }
+
+        let Some(settings) = storage
+            .and_then(|storage| eframe::get_value::<PersistedSettings>(storage, eframe::APP_KEY))
+        else {
+            return app;
+        };
+
+        // A folder removed since the last session would only fail the next batch
+        app.output_directory = settings.output_directory.filter(|dir| dir.is_dir());
+        if let Some(index) = app
+            .presets
+            .iter()
+            .position(|preset| preset.name == settings.preset_name)
+        {
+            app.selected_preset = index;
+        }
+        app.use_custom_size = settings.use_custom_size;
+        app.custom_width = settings.custom_width;
+        app.custom_height = settings.custom_height;
+        app.mode = settings.mode;
+        app.custom_output_format = settings.custom_output_format;
+        app.limit_parallelism = settings.limit_parallelism;
+        app.max_parallel_images = settings.max_parallel_images.clamp(1, max_workers());
+        app
+    }
+
+    /// Saves the current custom size and format as a preset, replacing one of the same name.
+    fn add_preset(&mut self, width: u32, height: u32) {
+        let preset = NamedPreset {
+            name: self.new_preset_name.trim().to_string().into(),
+            width,
+            height,
+            mode: self.mode,
+            output_format: self.custom_output_format,
+            scale_percent: None,
+        };
+        self.add_log_message(format!("Saved preset {}", preset.name));
+        match self
+            .presets
+            .iter()
+            .position(|existing| existing.name == preset.name)
+        {
+            Some(index) => self.presets[index] = preset,
+            None => self.presets.push(preset),
+        }
+        self.new_preset_name.clear();
+        self.save_presets();
+    }
+
+    fn delete_selected_preset(&mut self) {
+        let removed = self.presets.remove(self.selected_preset);
+        self.selected_preset = self.selected_preset.min(self.presets.len() - 1);
+        self.add_log_message(format!("Deleted preset {}", removed.name));
+        self.save_presets();
+    }
+
+    fn save_presets(&mut self) {
+        let Some(path) = preset_store::presets_path() else {
+            self.add_log_message(
+                "No config directory found, presets are kept until the app closes".to_string(),
+            );
+            return;
+        };
+        if let Err(e) = preset_store::save_presets(&path, &self.presets) {
+            self.add_log_message(format!("Error: {e:#}"));
+        }
+    }
+
+    /// Saves the current adjustments as a look, replacing one of the same name.
+    fn save_look(&mut self) {
+        let look = Look {
+            name: self.new_look_name.trim().to_string(),
+            adjustments: self.adjustments,
+        };
+        self.add_log_message(format!("Saved look {}", look.name));
+        match self
+            .looks
+            .iter()
+            .position(|existing| existing.name == look.name)
+        {
+            Some(index) => self.looks[index] = look,
+            None => self.looks.push(look),
+        }
+        self.new_look_name.clear();
+        self.save_looks();
+    }
+
+    fn delete_look(&mut self, index: usize) {
+        let removed = self.looks.remove(index);
+        self.add_log_message(format!("Deleted look {}", removed.name));
+        self.save_looks();
+    }
+
+    fn save_looks(&mut self) {
+        let Some(path) = preset_store::looks_path() else {
+            self.add_log_message(
+                "No config directory found, looks are kept until the app closes".to_string(),
+            );
+            return;
+        };
+        if let Err(e) = preset_store::save_looks(&path, &self.looks) {
+            self.add_log_message(format!("Error: {e:#}"));
+        }
+    }
+
+    /// Shows the first selected image, shrunk to a thumbnail, with the adjustments applied.
+    fn preview_look(&mut self, ctx: &egui::Context) {
+        let Some(path) = self.selected_files.first() else {
+            return;
+        };
+        let preview = image::open(path).map(|img| {
+            let img = self.adjustments.apply(&img.thumbnail(256, 256)).to_rgba8();
+            let size = [img.width() as usize, img.height() as usize];
+            egui::ColorImage::from_rgba_unmultiplied(size, img.as_raw())
+        });
+        match preview {
+            Ok(preview) => {
+                self.look_preview =
+                    Some(ctx.load_texture("look_preview", preview, egui::TextureOptions::LINEAR));
+            }
+            Err(e) => self.add_log_message(format!("Error: {e:#}")),
+        }
+    }
+
+    /// Picks up a finished preview and starts a new one when the first selected image or the
+    /// preset changed since the last.
+    fn update_preview(&mut self, ctx: &egui::Context) {
+        if let Some(result) = self
+            .preview_receiver
+            .as_ref()
+            .and_then(|receiver| receiver.try_recv().ok())
+        {
+            self.preview = Some(result.map(|images| Preview {
+                output: ctx.load_texture("preview", images.output, egui::TextureOptions::LINEAR),
+                source: ctx.load_texture(
+                    "preview_source",
+                    images.source,
+                    egui::TextureOptions::LINEAR,
+                ),
+                layout: images.layout,
+            }));
+            self.preview_receiver = None;
+        }
+
+        let key = self
+            .selected_files
+            .first()
+            .filter(|_| self.show_preview)
+            .map(|path| (path.clone(), self.current_preset()));
+        if key == self.preview_key {
+            return;
+        }
+        self.preview_key.clone_from(&key);
+        self.preview = None;
+        // A preview still being made for the old key is dropped with its receiver
+        self.preview_receiver = None;
+        let Some((path, preset)) = key else {
+            return;
+        };
+
+        let (tx, rx) = mpsc::channel();
+        self.preview_receiver = Some(rx);
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(Self::render_preview(&path, &preset).map_err(|e| format!("{e:#}")));
+            ctx.request_repaint();
+        });
+    }
+
+    /// `path` resized with `preset` by the same code a batch uses, kept in memory, and a
+    /// small upright copy of the source for showing what the preset crops and pads.
+    fn render_preview(path: &Path, preset: &ResizePreset) -> anyhow::Result<PreviewImages> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to open image: {}", path.display()))?;
+        let render = |preset: &ResizePreset| -> anyhow::Result<egui::ColorImage> {
+            let sink = MemorySink::default();
+            ImageResizer::resize_image(path, &bytes, path, preset, &sink, &|_| {})?;
+            let (_, output) = sink
+                .into_files()
+                .into_iter()
+                .next()
+                .context("The preset skips this image")?;
+            let image = image::load_from_memory(&output)
+                .context("The output can't be shown")?
+                .to_rgba8();
+            let size = [image.width() as usize, image.height() as usize];
+            Ok(egui::ColorImage::from_rgba_unmultiplied(
+                size,
+                image.as_raw(),
+            ))
+        };
+
+        let source_size =
+            probe::source_dimensions(&bytes, preset).context("Failed to read image header")?;
+        let source = render(&ResizePreset {
+            width: 640,
+            height: 640,
+            output_format: OutputFormat::Png,
+            respect_exif_orientation: preset.respect_exif_orientation,
+            ..ResizePreset::BASE
+        })?;
+        Ok(PreviewImages {
+            output: render(preset)?,
+            source,
+            layout: overlay::layout(source_size, preset),
+        })
+    }
+
+    /// The source scaled into `max_size`, with the parts the preset cuts off shaded and the
+    /// padding it adds tinted.
+    fn show_layout(ui: &mut egui::Ui, source: &egui::TextureHandle, layout: &overlay::Layout) {
+        const MAX_SIZE: egui::Vec2 = egui::vec2(320.0, 240.0);
+
+        let bounds = layout.bounds;
+        let scale = (MAX_SIZE.x / bounds.width).min(MAX_SIZE.y / bounds.height);
+        let (response, painter) = ui.allocate_painter(
+            egui::vec2(bounds.width * scale, bounds.height * scale),
+            egui::Sense::hover(),
+        );
+        let to_screen = |region: overlay::Region| {
+            egui::Rect::from_min_size(
+                response.rect.min
+                    + egui::vec2((region.x - bounds.x) * scale, (region.y - bounds.y) * scale),
+                egui::vec2(region.width * scale, region.height * scale),
+            )
+        };
+
+        painter.image(
+            source.id(),
+            to_screen(layout.source),
+            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+            egui::Color32::WHITE,
+        );
+        for region in &layout.discarded {
+            painter.rect_filled(
+                to_screen(*region),
+                0.0,
+                egui::Color32::from_black_alpha(160),
+            );
+        }
+        for region in &layout.padding {
+            painter.rect_filled(
+                to_screen(*region),
+                0.0,
+                egui::Color32::from_rgba_unmultiplied(90, 140, 255, 90),
+            );
         }
     }
 
@@ -66,7 +751,158 @@ impl ImageResizerApp {
             .pick_files()
         {
             self.selected_files = files;
+            self.folder_root = None;
             self.add_log_message(format!("Selected {} files", self.selected_files.len()));
+            self.start_scan();
+        }
+    }
+
+    #[cfg(feature = "video")]
+    fn select_videos(&mut self) {
+        if let Some(files) = FileDialog::new()
+            .add_filter("Videos", &video::VIDEO_EXTENSIONS)
+            .set_title("Select videos to take frames from")
+            .pick_files()
+        {
+            self.video_files = files;
+            self.add_log_message(format!("Selected {} videos", self.video_files.len()));
+        }
+    }
+
+    /// Summarizes the selected files' headers on a worker thread. A scan still running for
+    /// an earlier selection stops once its receiver is dropped here.
+    fn start_scan(&mut self) {
+        self.summary = None;
+        self.scan_progress = (0, self.selected_files.len());
+        let files = self.selected_files.clone();
+        let (tx, rx) = mpsc::channel();
+        self.scan_receiver = Some(rx);
+
+        thread::spawn(move || {
+            let summary = summary::scan(&files, |scanned, total| {
+                match tx.send(ScanStatus::Scanning { scanned, total }) {
+                    Ok(()) => ControlFlow::Continue(()),
+                    Err(_) => ControlFlow::Break(()),
+                }
+            });
+            if let Some(summary) = summary {
+                let _ = tx.send(ScanStatus::Done(summary));
+            }
+        });
+    }
+
+    fn update_scan_status(&mut self) {
+        let Some(receiver) = &self.scan_receiver else {
+            return;
+        };
+        let mut done = None;
+        while let Ok(status) = receiver.try_recv() {
+            match status {
+                ScanStatus::Scanning { scanned, total } => self.scan_progress = (scanned, total),
+                ScanStatus::Done(summary) => done = Some(summary),
+            }
+        }
+        if let Some(summary) = done {
+            self.add_log_message(format!("Selection: {}", summary.describe()));
+            self.summary = Some(summary);
+            self.scan_receiver = None;
+        }
+    }
+
+    /// With `recursive`, images in subfolders are selected too, down to the depth limit.
+    fn select_folder(&mut self, recursive: bool) {
+        let Some(dir) = FileDialog::new()
+            .set_title("Select a folder of images")
+            .pick_folder()
+        else {
+            return;
+        };
+
+        let skipped = RefCell::new(Vec::new());
+        let log = |message| skipped.borrow_mut().push(message);
+        let discovered = if recursive {
+            discovery::discover_images_recursive(
+                &dir,
+                self.limit_depth.then_some(self.max_depth),
+                self.include_hidden,
+                &log,
+            )
+        } else {
+            discovery::discover_images(&dir, self.include_hidden, &log)
+        };
+        for message in skipped.into_inner() {
+            self.add_log_message(message);
+        }
+        match discovered {
+            Ok(files) => {
+                let found = files.len();
+                self.selected_files = if self.newest_only {
+                    sorting::take_newest(files, self.newest_count)
+                } else {
+                    files
+                };
+                self.folder_root = recursive.then(|| dir.clone());
+                self.add_log_message(format!(
+                    "Selected {} of {found} images in {}",
+                    self.selected_files.len(),
+                    dir.display()
+                ));
+                self.start_scan();
+            }
+            Err(e) => self.add_log_message(format!("Error: {e:#}")),
+        }
+    }
+
+    /// Adds files dropped on the window to the selection, and the images directly inside
+    /// dropped folders. Paths already selected and unsupported files are skipped.
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        if dropped.is_empty() {
+            return;
+        }
+
+        let mut added = 0;
+        let mut ignored = 0;
+        for path in dropped.into_iter().filter_map(|file| file.path) {
+            let paths = if path.is_dir() {
+                let skipped = RefCell::new(Vec::new());
+                let discovered =
+                    discovery::discover_images(&path, self.include_hidden, &|message| {
+                        skipped.borrow_mut().push(message);
+                    });
+                for message in skipped.into_inner() {
+                    self.add_log_message(message);
+                }
+                match discovered {
+                    Ok(images) => images,
+                    Err(e) => {
+                        self.add_log_message(format!("Error: {e:#}"));
+                        continue;
+                    }
+                }
+            } else if discovery::has_supported_extension(&path) {
+                vec![path]
+            } else {
+                ignored += 1;
+                continue;
+            };
+            for path in paths {
+                if !self.selected_files.contains(&path) {
+                    self.selected_files.push(path);
+                    added += 1;
+                }
+            }
+        }
+
+        if ignored > 0 {
+            self.add_log_message(format!(
+                "Added {added} dropped files, ignored {ignored} unsupported"
+            ));
+        } else {
+            self.add_log_message(format!("Added {added} dropped files"));
+        }
+        if added > 0 {
+            self.start_scan();
         }
     }
 
@@ -80,8 +916,256 @@ impl ImageResizerApp {
         }
     }
 
+    /// One http(s) URL per non-empty line of the URL field.
+    #[cfg(feature = "net")]
+    fn urls(&self) -> impl Iterator<Item = &str> {
+        self.url_text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+    }
+
+    #[cfg_attr(not(feature = "net"), allow(clippy::missing_const_for_fn))]
+    fn has_inputs(&self) -> bool {
+        #[cfg(feature = "net")]
+        if self.urls().next().is_some() {
+            return true;
+        }
+        #[cfg(feature = "video")]
+        if !self.video_files.is_empty() {
+            return true;
+        }
+        !self.selected_files.is_empty()
+    }
+
+    fn input_sources(&self) -> Vec<Box<dyn InputSource>> {
+        let sources = self.selected_files.iter().cloned().map(|path| {
+            let source = FileSource::from(path);
+            #[cfg(feature = "mmap")]
+            let source = source.with_memory_map(self.memory_map_inputs);
+            Box::new(source) as Box<dyn InputSource>
+        });
+
+        #[cfg(feature = "net")]
+        let agent = UrlSource::agent();
+        #[cfg(feature = "net")]
+        let sources = sources.chain(
+            self.urls()
+                .map(|url| Box::new(UrlSource::new(url, agent.clone())) as Box<dyn InputSource>),
+        );
+
+        sources.collect()
+    }
+
+    fn parsed_crop(&self) -> anyhow::Result<Option<NormalizedCrop>> {
+        let text = self.crop_text.trim();
+        if text.is_empty() {
+            Ok(None)
+        } else {
+            text.parse().map(Some)
+        }
+    }
+
+    /// The custom width and height, an error naming the field when either isn't a size.
+    fn parsed_custom_size(&self) -> anyhow::Result<(u32, u32)> {
+        let parse = |name: &str, text: &str| {
+            text.trim()
+                .parse()
+                .ok()
+                .filter(|&size: &u32| size > 0)
+                .with_context(|| format!("{name} must be a whole number above 0, got {text:?}"))
+        };
+        Ok((
+            parse("Width", &self.custom_width)?,
+            parse("Height", &self.custom_height)?,
+        ))
+    }
+
+    fn parsed_encoder_params(&self, log: &dyn Fn(String)) -> anyhow::Result<EncoderParams> {
+        encoder_params::parse(
+            &encoder_params::parse_lines(&self.encoder_params_text)?,
+            log,
+        )
+    }
+
+    /// The naming settings with the chosen preset's name filled in for templates.
+    fn batch_naming(&self) -> Naming {
+        let preset_name = if self.use_custom_size {
+            "custom"
+        } else {
+            &self.presets[self.selected_preset].name
+        };
+        Naming {
+            preset_name: preset_name.to_string(),
+            input_root: self.folder_root.clone(),
+            ..self.naming.clone()
+        }
+    }
+
+    fn current_preset(&self) -> ResizePreset {
+        let preset = if self.use_custom_size {
+            // Only used for previews while the size is invalid, processing is blocked then
+            let (width, height) = self.parsed_custom_size().unwrap_or((800, 600));
+            ResizePreset {
+                width,
+                height,
+                mode: self.mode,
+                prevent_upscale: self.prevent_upscale,
+                pad_to_target: self.pad_to_target,
+                background: self.background,
+                aspect_tolerance: (self.aspect_tolerance_percent > 0.0)
+                    .then_some(self.aspect_tolerance_percent / 100.0),
+                output_format: self.custom_output_format,
+                jpeg_quality: self.jpeg_quality.clamp(1, 100),
+                webp_quality: self.webp_quality.clamp(0.0, 100.0),
+                webp_lossless: self.webp_lossless,
+                filter: self.filter,
+                stretch_filter: self.use_stretch_filter.then_some(self.stretch_filter),
+                custom_kernel: self.use_custom_kernel.then_some(self.custom_kernel),
+                #[cfg(feature = "seam-carving")]
+                seam_carving_max_pixels: self
+                    .seam_carving
+                    .then(|| (f64::from(self.seam_carving_megapixels) * 1_000_000.0) as u64),
+                pixel_budget: self.use_pixel_budget.then(|| PixelBudget {
+                    pixels: (f64::from(self.pixel_budget_megapixels) * 1_000_000.0) as u64,
+                    multiple: self.pixel_budget_multiple,
+                }),
+                ..ResizePreset::BASE
+            }
+        } else {
+            self.presets[self.selected_preset].preset()
+        };
+
+        // Settings that apply regardless of the chosen size
+        ResizePreset {
+            size_from_file_name: self.size_from_file_name,
+            average_color: self.average_color,
+            dpr_variants: self.dpr_variants,
+            jpeg_fallback: self.jpeg_fallback,
+            lqip: self.lqip,
+            preserve_xmp: self.preserve_xmp,
+            metadata: self.metadata,
+            deskew: self.deskew,
+            split_photos: self.split_photos,
+            respect_exif_orientation: self.respect_exif_orientation,
+            multi_image: match self.multi_image {
+                MultiImageSelect::Index(_) => MultiImageSelect::Index(self.multi_image_index),
+                select => select,
+            },
+            animation_policy: self.animation_policy,
+            incompatible_output: self.incompatible_output,
+            area_average_integer_factors: self.area_average_integer_factors,
+            timeout: self
+                .use_timeout
+                .then(|| Duration::from_secs(u64::from(self.timeout_seconds))),
+            max_parallel_images: self.limit_parallelism.then_some(self.max_parallel_images),
+            max_parallel_reads: self.separate_reads.then_some(self.max_parallel_reads),
+            fast_filter_above_pixels: self
+                .fast_filter_for_large
+                .then(|| u64::from(self.fast_filter_megapixels) * 1_000_000),
+            date_caption: self.date_caption.then_some(self.date_caption_corner),
+            grayscale: self.grayscale.then_some(self.grayscale_mode),
+            adjustments: self.adjustments,
+            posterize: self.posterize.then_some(self.posterize_levels),
+            dither: self.dither,
+            region_mask: self.region_mask,
+            mask: self.use_mask.then_some(match self.mask_shape {
+                MaskShape::Circle => MaskShape::Circle,
+                MaskShape::RoundedRect(_) => MaskShape::RoundedRect(self.mask_radius),
+            }),
+            crop: self.parsed_crop().ok().flatten(),
+            encoder_params: self
+                .parsed_encoder_params(&|_| {})
+                .unwrap_or(EncoderParams::NONE),
+            jpeg_restart_interval: self
+                .jpeg_restart_markers
+                .then_some(self.jpeg_restart_interval),
+            ssim_target: self.use_ssim_target.then_some(self.ssim_target),
+            min_sharpness: self.skip_blurry.then_some(self.min_sharpness),
+            byte_budget: self.use_byte_budget.then_some(ByteBudget {
+                max_bytes: u64::from(self.byte_budget_kb) * 1024,
+                min_quality: self.quality_floor,
+            }),
+            ..preset
+        }
+    }
+
+    fn export_plan(&mut self) {
+        let Some(output_dir) = self.output_directory.clone() else {
+            self.add_log_message("No output directory selected".to_string());
+            return;
+        };
+
+        let Some(plan_path) = FileDialog::new()
+            .add_filter("Job file", &["json"])
+            .set_file_name("resize-plan.json")
+            .set_title("Export resize plan")
+            .save_file()
+        else {
+            return;
+        };
+
+        sorting::sort_inputs(&mut self.selected_files, self.sort_order);
+        let preset = self.current_preset();
+        let result = ImageResizer::plan_batch(
+            &self.selected_files,
+            &output_dir,
+            &preset,
+            &self.batch_naming(),
+        )
+        .and_then(|jobs| jobs::write_job_file(&plan_path, &jobs).map(|()| jobs.len()));
+
+        match result {
+            Ok(count) => self.add_log_message(format!(
+                "Exported plan with {count} operations to {}",
+                plan_path.display()
+            )),
+            Err(e) => self.add_log_message(format!("Failed to export plan: {e}")),
+        }
+    }
+
+    /// Shows the output directory in the system file manager.
+    fn open_output_directory(&mut self) {
+        let Some(dir) = self.output_directory.clone() else {
+            return;
+        };
+        if !dir.is_dir() {
+            self.add_log_message(format!(
+                "Can't open {}, the folder no longer exists",
+                dir.display()
+            ));
+            return;
+        }
+
+        let opener = if cfg!(target_os = "windows") {
+            "explorer"
+        } else if cfg!(target_os = "macos") {
+            "open"
+        } else {
+            "xdg-open"
+        };
+        // Not waited for, explorer exits with an error code even when it opened the folder
+        if let Err(e) = std::process::Command::new(opener).arg(&dir).spawn() {
+            self.add_log_message(format!("Failed to open {}: {e}", dir.display()));
+        }
+    }
+
+    #[cfg(feature = "event-server")]
+    fn start_event_server(&mut self) {
+        match EventServer::start(self.event_server_port) {
+            Ok(server) => {
+                self.add_log_message(format!(
+                    "Broadcasting progress events on http://127.0.0.1:{}/",
+                    server.port()
+                ));
+                self.event_server = Some(server);
+            }
+            Err(e) => self.add_log_message(format!("Failed to start event server: {e:#}")),
+        }
+    }
+
     fn start_processing(&mut self) {
-        if self.selected_files.is_empty() {
+        if !self.has_inputs() {
             self.add_log_message("No files selected".to_string());
             return;
         }
@@ -93,70 +1177,357 @@ impl ImageResizerApp {
             return;
         };
 
-        let preset = if self.use_custom_size {
-            let width = self.custom_width.parse().unwrap_or(800);
-            let height = self.custom_height.parse().unwrap_or(600);
-            ResizePreset {
-                name: "Custom",
-                width,
-                height,
-                maintain_aspect_ratio: self.maintain_aspect_ratio,
-                output_format: self.custom_output_format,
+        if self.use_custom_size {
+            if let Err(e) = self.parsed_custom_size() {
+                self.add_log_message(format!("Not starting: {e}"));
+                return;
+            }
+        }
+
+        let preset = self.current_preset();
+
+        let warnings = RefCell::new(Vec::new());
+        let _ = self.parsed_encoder_params(&|warning| warnings.borrow_mut().push(warning));
+        for warning in warnings.into_inner() {
+            self.add_log_message(warning);
+        }
+
+        if self.check_disk_space {
+            // Downloads are not counted, their size is unknown until fetched
+            let required = preflight::estimate_output_bytes(&self.selected_files, &preset);
+            let checked = preflight::available_space(&output_dir)
+                .and_then(|available| preflight::ensure_space(required, available));
+            if let Err(e) = checked {
+                self.add_log_message(format!("Not starting: {e:#}"));
+                return;
+            }
+        }
+
+        #[cfg(feature = "s3")]
+        let s3_config = if self.upload_to_s3 {
+            match self.s3.clone().with_env_credentials() {
+                Ok(config) => Some(config),
+                Err(e) => {
+                    self.add_log_message(format!("Not starting: {e:#}"));
+                    return;
+                }
             }
         } else {
-            self.selected_preset
+            None
         };
 
-        let files = self.selected_files.clone();
+        // Sorted in place so the file list shows the order outputs are numbered in
+        sorting::sort_inputs(&mut self.selected_files, self.sort_order);
+        #[cfg_attr(not(feature = "video"), allow(unused_mut))]
+        let mut inputs = self.input_sources();
+        #[cfg(feature = "video")]
+        let videos = self.video_files.clone();
+        #[cfg(feature = "video")]
+        let video_interval = Duration::from_secs_f32(self.video_interval_secs);
+        let batch_id = naming::new_batch_id();
+        self.add_log_message(format!("Starting batch {batch_id}"));
+        let naming = Naming {
+            batch_id: self.tag_batch_id.then(|| batch_id.clone()),
+            ..self.batch_naming()
+        };
+        let rights_packet = (!self.xmp_copyright.is_empty() || !self.xmp_author.is_empty())
+            .then(|| xmp::rights_packet(&self.xmp_copyright, &self.xmp_author));
+        let generate_gallery = self.generate_gallery;
+        let generate_contact_sheet = self.generate_contact_sheet;
+        let write_manifest = self.write_manifest;
+        let write_image_manifest = self.write_image_manifest;
+        let write_checksums = self.write_checksums;
         let (tx, rx) = mpsc::channel();
         self.processing_receiver = Some(rx);
+        #[cfg(feature = "battery-pause")]
+        let battery_threshold = self.battery_pause.then_some(self.battery_pause_below);
 
-        thread::spawn(move || {
+        // Each batch gets its own flag so a late cancel can't reach the next one
+        self.cancel = Arc::new(AtomicBool::new(false));
+        let cancel = Arc::clone(&self.cancel);
+
+        self.worker = Some(BatchWorker::spawn(move || {
+            let log_callback = |message: String| {
+                let _ = tx.send(ProcessingStatus::Message(message));
+            };
+
+            // Frames are resized like any other image, their folders go once the batch is done
+            #[cfg(feature = "video")]
+            let _frames: Vec<_> =
+                videos
+                    .iter()
+                    .filter_map(|video| match video::extract_frames(video, video_interval) {
+                        Ok(frames) => {
+                            log_callback(format!(
+                                "{}: {} frames",
+                                video.file_name().unwrap_or_default().to_string_lossy(),
+                                frames.paths().len()
+                            ));
+                            inputs.extend(frames.paths().iter().cloned().map(|path| {
+                                Box::new(FileSource::from(path)) as Box<dyn InputSource>
+                            }));
+                            Some(frames)
+                        }
+                        Err(e) => {
+                            log_callback(format!("Error: {e:#}"));
+                            None
+                        }
+                    })
+                    .collect();
             let progress_callback = |current: usize, total: usize| {
                 let _ = tx.send(ProcessingStatus::Processing { current, total });
+                // Called before each image, so waiting here holds the batch between images
+                #[cfg(feature = "battery-pause")]
+                if let Some(threshold) = battery_threshold.filter(|_| current < total) {
+                    power::wait_for_power(threshold, &cancel, &log_callback);
+                }
+                if cancel.load(Ordering::Relaxed) {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
             };
 
-            match ImageResizer::batch_resize(&files, &output_dir, &preset, progress_callback) {
+            // Uploads are keyed by their path in the output folder, side files stay on disk
+            #[cfg(feature = "s3")]
+            let s3_sink = s3_config.map(|config| S3Sink::new(config, &output_dir));
+            #[cfg(feature = "s3")]
+            let storage = s3_sink
+                .as_ref()
+                .map_or(&FileSystemSink as &dyn OutputSink, |s3_sink| s3_sink);
+            #[cfg(not(feature = "s3"))]
+            let storage: &dyn OutputSink = &FileSystemSink;
+
+            // Hashed last, right before storage, so the checksums match the stored files
+            let checksum_sink = write_checksums.then(|| ChecksumSink::new(storage));
+            let sink = checksum_sink
+                .as_ref()
+                .map_or(storage, |checksum_sink| checksum_sink as &dyn OutputSink);
+
+            // Rights set here replace whatever XMP the sources had
+            let xmp_sink = rights_packet.map(|packet| XmpSink::new(sink, packet));
+            let sink = xmp_sink
+                .as_ref()
+                .map_or(sink, |xmp_sink| xmp_sink as &dyn OutputSink);
+
+            match ImageResizer::batch_resize(
+                &inputs,
+                &output_dir,
+                &preset,
+                &naming,
+                sink,
+                progress_callback,
+                log_callback,
+            ) {
                 Ok(results) => {
-                    let successful = results.iter().filter(|r| r.is_ok()).count();
-                    let failed = results.len() - successful;
-                    let _ = tx.send(ProcessingStatus::Completed { successful, failed });
+                    let skipped = results
+                        .iter()
+                        .filter(|r| r.as_ref().is_ok_and(Vec::is_empty))
+                        .count();
+                    let failed = results.iter().filter(|r| r.is_err()).count();
+                    let successful = results.len() - skipped - failed;
+                    let cancelled = results.len() < inputs.len();
+
+                    let file_results: Vec<_> = inputs
+                        .iter()
+                        .zip(&results)
+                        .map(|(input, result)| FileResult {
+                            input: input.identifier().to_path_buf(),
+                            error: result.as_ref().err().map(|e| format!("{e:#}")),
+                            outputs: result.as_ref().map_or(0, Vec::len),
+                            output_bytes: result
+                                .as_ref()
+                                .map_or(0, |files| files.iter().map(|file| file.bytes).sum()),
+                        })
+                        .collect();
+
+                    // Pair each input with what it produced, failed inputs produced nothing
+                    let entries: Vec<_> = inputs
+                        .iter()
+                        .zip(results)
+                        .filter_map(|(input, result)| {
+                            result.ok().map(|files| {
+                                let outputs = files.into_iter().map(|file| file.path).collect();
+                                (input.identifier().to_path_buf(), outputs)
+                            })
+                        })
+                        .collect();
+
+                    if write_manifest {
+                        let message =
+                            match manifest::write_manifest(&output_dir, &batch_id, &entries) {
+                                Ok(path) => format!("Manifest written to {}", path.display()),
+                                Err(e) => format!("Failed to write manifest: {e}"),
+                            };
+                        let _ = tx.send(ProcessingStatus::Message(message));
+                    }
+
+                    if write_image_manifest {
+                        let message = match manifest::write_image_manifest(&output_dir, &entries) {
+                            Ok(path) => format!("Image manifest written to {}", path.display()),
+                            Err(e) => format!("Failed to write image manifest: {e}"),
+                        };
+                        let _ = tx.send(ProcessingStatus::Message(message));
+                    }
+
+                    if let Some(checksum_sink) = &checksum_sink {
+                        let message = match checksum_sink.write_checksums(&output_dir) {
+                            Ok(path) => format!("Checksums written to {}", path.display()),
+                            Err(e) => format!("Failed to write checksums: {e}"),
+                        };
+                        let _ = tx.send(ProcessingStatus::Message(message));
+                    }
+
+                    let outputs: Vec<_> = entries
+                        .into_iter()
+                        .flat_map(|(_, outputs)| outputs)
+                        .collect();
+
+                    if generate_gallery {
+                        let message = match gallery::write_gallery(&output_dir, &outputs) {
+                            Ok(path) => format!("Gallery written to {}", path.display()),
+                            Err(e) => format!("Failed to write gallery: {e}"),
+                        };
+                        let _ = tx.send(ProcessingStatus::Message(message));
+                    }
+
+                    if generate_contact_sheet {
+                        let message =
+                            match contact_sheet::write_contact_sheet(&output_dir, &outputs) {
+                                Ok(path) => format!("Contact sheet written to {}", path.display()),
+                                Err(e) => format!("Failed to write contact sheet: {e}"),
+                            };
+                        let _ = tx.send(ProcessingStatus::Message(message));
+                    }
+
+                    let _ = tx.send(if cancelled {
+                        ProcessingStatus::Cancelled {
+                            successful,
+                            skipped,
+                            failed,
+                            total: inputs.len(),
+                            results: file_results,
+                        }
+                    } else {
+                        ProcessingStatus::Completed {
+                            successful,
+                            skipped,
+                            failed,
+                            results: file_results,
+                        }
+                    });
                 }
                 Err(e) => {
                     let _ = tx.send(ProcessingStatus::Error(e.to_string()));
                 }
             }
+        }));
+    }
+
+    /// Scrollable table of how each input went with failures in red, and the total output.
+    fn show_results(ui: &mut egui::Ui, results: &[FileResult]) {
+        ui.collapsing("Results", |ui| {
+            egui::ScrollArea::vertical()
+                .id_salt("results")
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("results")
+                        .num_columns(3)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for result in results {
+                                let name = result.input.file_name().unwrap_or_default();
+                                let name = name.to_string_lossy();
+                                if let Some(error) = &result.error {
+                                    ui.colored_label(egui::Color32::RED, name);
+                                    ui.colored_label(egui::Color32::RED, error);
+                                    ui.label("");
+                                } else if result.outputs == 0 {
+                                    ui.label(name);
+                                    ui.label("Skipped");
+                                    ui.label("");
+                                } else {
+                                    ui.label(name);
+                                    ui.label(match result.outputs {
+                                        1 => "1 file written".to_string(),
+                                        outputs => format!("{outputs} files written"),
+                                    });
+                                    ui.label(summary::format_bytes(result.output_bytes));
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+            let total: u64 = results.iter().map(|result| result.output_bytes).sum();
+            ui.label(format!(
+                "Total output size: {}",
+                summary::format_bytes(total)
+            ));
         });
     }
 
     fn update_processing_status(&mut self) {
         let mut new_status = None;
         let mut should_clear_receiver = false;
-        let mut log_message = None;
+        let mut log_messages = Vec::new();
 
         if let Some(ref receiver) = self.processing_receiver {
             while let Ok(status) = receiver.try_recv() {
+                #[cfg(feature = "event-server")]
+                if let Some(server) = &self.event_server {
+                    server.broadcast(&status);
+                }
+
                 match &status {
                     ProcessingStatus::Processing { current, total } => {
-                        log_message = Some(format!("Processing {} of {}", current + 1, total));
+                        log_messages.push(format!(
+                            "Processing {} of {}",
+                            (current + 1).min(*total),
+                            total
+                        ));
+                    }
+                    ProcessingStatus::Completed {
+                        successful,
+                        skipped,
+                        failed,
+                        ..
+                    } => {
+                        log_messages.push(format!(
+                            "Processing completed: {successful} successful, {skipped} skipped, \
+                             {failed} failed"
+                        ));
+                        should_clear_receiver = true;
                     }
-                    ProcessingStatus::Completed { successful, failed } => {
-                        log_message = Some(format!(
-                            "Processing completed: {successful} successful, {failed} failed"
+                    ProcessingStatus::Cancelled {
+                        successful,
+                        skipped,
+                        failed,
+                        total,
+                        ..
+                    } => {
+                        log_messages.push(format!(
+                            "Processing cancelled after {} of {total}: {successful} successful, \
+                             {skipped} skipped, {failed} failed",
+                            successful + skipped + failed
                         ));
                         should_clear_receiver = true;
                     }
                     ProcessingStatus::Error(err) => {
-                        log_message = Some(format!("Error: {err}"));
+                        log_messages.push(format!("Error: {err}"));
                         should_clear_receiver = true;
                     }
+                    ProcessingStatus::Message(message) => {
+                        // Informational only, the current status stays as it is
+                        log_messages.push(message.clone());
+                        continue;
+                    }
                     ProcessingStatus::Idle => {}
                 }
                 new_status = Some(status);
             }
         }
 
-        if let Some(message) = log_message {
+        for message in log_messages {
             self.add_log_message(message);
         }
 
@@ -171,8 +1542,26 @@ impl ImageResizerApp {
 }
 
 impl eframe::App for ImageResizerApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, &PersistedSettings::from(&*self));
+    }
+
+    /// Stops a running batch before the next image and waits briefly for the worker, so
+    /// no files are written after the window has closed.
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        match self.worker.take() {
+            Some(worker) => {
+                worker.shut_down(&self.cancel, SHUTDOWN_TIMEOUT);
+            }
+            None => self.cancel.store(true, Ordering::Relaxed),
+        }
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.update_processing_status();
+        self.update_scan_status();
+        self.update_preview(ctx);
+        self.handle_dropped_files(ctx);
 
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Image Resizer");
@@ -183,52 +1572,520 @@ impl eframe::App for ImageResizerApp {
                 if ui.button("Select Images").clicked() {
                     self.select_files();
                 }
+                if ui.button("Select Folder").clicked() {
+                    self.select_folder(false);
+                }
+                if ui.button("Select Folder (recursive)").clicked() {
+                    self.select_folder(true);
+                }
                 ui.label(format!("Selected: {} files", self.selected_files.len()));
             });
 
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.newest_only, "From folders, take only the newest")
+                    .on_hover_text("By modification time, e.g. the last shots imported from a card");
+                ui.add_enabled(
+                    self.newest_only,
+                    egui::DragValue::new(&mut self.newest_count).range(1..=100_000),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.limit_depth, "Recurse at most");
+                ui.add_enabled(
+                    self.limit_depth,
+                    egui::DragValue::new(&mut self.max_depth).range(1..=100),
+                );
+                ui.label("levels into subfolders");
+            });
+            ui.checkbox(&mut self.include_hidden, "Include hidden files and folders")
+                .on_hover_text(
+                    "Otherwise dotfiles, files marked hidden or system and junk like Thumbs.db \
+                     and .DS_Store are skipped",
+                );
+
+            if let Some(summary) = &self.summary {
+                ui.label(summary.describe());
+            } else if self.scan_receiver.is_some() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    let (scanned, total) = self.scan_progress;
+                    ui.label(format!("Scanning headers, {scanned} of {total}"));
+                });
+            }
+
             if !self.selected_files.is_empty() {
                 ui.collapsing("Selected Files", |ui| {
+                    let mut clicked = None;
+                    let mut removed = None;
+                    let clear_all = ui.button("Clear All").clicked();
                     egui::ScrollArea::vertical()
                         .max_height(100.0)
                         .show(ui, |ui| {
-                            for file in &self.selected_files {
-                                ui.label(file.file_name().unwrap().to_string_lossy());
+                            for (index, file) in self.selected_files.iter().enumerate() {
+                                let inspected = self
+                                    .inspected
+                                    .as_ref()
+                                    .is_some_and(|(path, _)| path == file);
+                                let name = file.file_name().unwrap().to_string_lossy();
+                                ui.horizontal(|ui| {
+                                    if ui
+                                        .small_button("✕")
+                                        .on_hover_text("Remove from the selection")
+                                        .clicked()
+                                    {
+                                        removed = Some(index);
+                                    }
+                                    if ui.selectable_label(inspected, name).clicked() {
+                                        clicked = Some(file.clone());
+                                    }
+                                });
                             }
                         });
+
+                    // Applied after the list is drawn, it can't change while being iterated
+                    if clear_all {
+                        self.selected_files.clear();
+                        self.folder_root = None;
+                        self.inspected = None;
+                        self.summary = None;
+                        self.scan_receiver = None;
+                        self.add_log_message("Selection cleared".to_string());
+                        return;
+                    }
+                    if let Some(index) = removed {
+                        let file = self.selected_files.remove(index);
+                        if self
+                            .inspected
+                            .as_ref()
+                            .is_some_and(|(path, _)| *path == file)
+                        {
+                            self.inspected = None;
+                        }
+                        self.start_scan();
+                        return;
+                    }
+                    if let Some(file) = clicked {
+                        let info = probe::probe(&file).map_err(|e| format!("{e:#}"));
+                        self.inspected = Some((file, info));
+                    }
+
+                    if let Some((_, info)) = &self.inspected {
+                        match info {
+                            Ok(info) => {
+                                egui::Grid::new("image_info").striped(true).show(ui, |ui| {
+                                    ui.strong("");
+                                    ui.strong("Source");
+                                    ui.strong("Output");
+                                    ui.end_row();
+                                    for (property, source, output) in
+                                        probe::describe(info, &self.current_preset())
+                                    {
+                                        ui.label(property);
+                                        ui.label(source);
+                                        ui.label(output);
+                                        ui.end_row();
+                                    }
+                                });
+                            }
+                            Err(e) => {
+                                ui.colored_label(egui::Color32::RED, e);
+                            }
+                        }
+                    }
                 });
             }
 
+            #[cfg(feature = "video")]
+            ui.horizontal(|ui| {
+                if ui.button("Select Videos").clicked() {
+                    self.select_videos();
+                }
+                ui.label(format!("{} videos", self.video_files.len()));
+                if !self.video_files.is_empty() && ui.button("Clear").clicked() {
+                    self.video_files.clear();
+                }
+                ui.label("One frame every");
+                ui.add(
+                    egui::DragValue::new(&mut self.video_interval_secs)
+                        .range(0.1..=3600.0)
+                        .speed(0.1)
+                        .suffix(" s"),
+                );
+            })
+            .response
+            .on_hover_text("Frames are taken with the ffmpeg command, which must be installed");
+
+            #[cfg(feature = "s3")]
+            ui.collapsing("S3 upload", |ui| {
+                ui.checkbox(&mut self.upload_to_s3, "Upload outputs instead of saving them")
+                    .on_hover_text(
+                        "Keys are the outputs' paths in the output directory. Credentials are \
+                         read from AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY",
+                    );
+                ui.add_enabled_ui(self.upload_to_s3, |ui| {
+                    egui::Grid::new("s3").num_columns(2).show(ui, |ui| {
+                        for (label, value, hint) in [
+                            ("Endpoint:", &mut self.s3.endpoint, "https://s3.amazonaws.com"),
+                            ("Region:", &mut self.s3.region, "us-east-1"),
+                            ("Bucket:", &mut self.s3.bucket, "bucket"),
+                            ("Key prefix:", &mut self.s3.prefix, "thumbnails/"),
+                        ] {
+                            ui.label(label);
+                            ui.add(egui::TextEdit::singleline(value).hint_text(hint));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+
+            #[cfg(feature = "net")]
+            ui.collapsing("Image URLs", |ui| {
+                ui.label("One http(s) URL per line");
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.url_text)
+                        .desired_rows(3)
+                        .desired_width(f32::INFINITY),
+                );
+            });
+
             ui.separator();
 
-            // Output directory selection
-            ui.horizontal(|ui| {
-                if ui.button("Select Output Directory").clicked() {
-                    self.select_output_directory();
-                }
-                if let Some(ref dir) = self.output_directory {
-                    ui.label(format!("Output: {}", dir.display()));
-                } else {
-                    ui.label("No output directory selected");
-                }
-            });
+            // Output directory selection
+            ui.horizontal(|ui| {
+                if ui.button("Select Output Directory").clicked() {
+                    self.select_output_directory();
+                }
+                if let Some(ref dir) = self.output_directory {
+                    ui.label(format!("Output: {}", dir.display()));
+                } else {
+                    ui.label("No output directory selected");
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("File name:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.naming.prefix)
+                        .hint_text("prefix")
+                        .desired_width(80.0),
+                );
+                ui.label("name");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.naming.suffix)
+                        .hint_text("suffix")
+                        .desired_width(80.0),
+                );
+                if let Err(e) = self.naming.validate_affixes() {
+                    ui.colored_label(egui::Color32::RED, e.to_string());
+                } else if self.naming.prefix.is_empty()
+                    && self.naming.suffix.is_empty()
+                    && self.naming.sequence_name.is_empty()
+                    && self.naming.template.is_empty()
+                {
+                    ui.weak("defaults to name_resized_WxH");
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Or template:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.naming.template)
+                        .hint_text("{stem}_thumb.{ext}")
+                        .desired_width(180.0),
+                )
+                .on_hover_text(format!(
+                    "Placeholders: {{{}}}",
+                    naming::TEMPLATE_PLACEHOLDERS.join("}, {")
+                ));
+                if !self.naming.template.is_empty() {
+                    match self.batch_naming().template_example() {
+                        Ok(example) => {
+                            ui.weak(format!("e.g. {example}"));
+                        }
+                        Err(e) => {
+                            ui.colored_label(egui::Color32::RED, format!("{e:#}"));
+                        }
+                    }
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Process in order:");
+                egui::ComboBox::from_id_salt("sort_order")
+                    .selected_text(self.sort_order.label())
+                    .show_ui(ui, |ui| {
+                        for order in SortOrder::ALL {
+                            ui.selectable_value(&mut self.sort_order, order, order.label());
+                        }
+                    });
+                ui.label("Number outputs as:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.naming.sequence_name)
+                        .hint_text("e.g. slide")
+                        .desired_width(100.0),
+                )
+                .on_hover_text("Names outputs slide_001, slide_002, ... instead of after the source");
+            });
+
+            ui.checkbox(&mut self.naming.transliterate, "ASCII-only file names")
+                .on_hover_text("Strips accents, e.g. café.jpg becomes cafe_resized_WxH.jpg");
+            ui.checkbox(&mut self.tag_batch_id, "Add the batch ID to file names")
+                .on_hover_text("Each run gets its own ID, also logged and written to the manifest");
+
+            ui.checkbox(
+                &mut self.naming.proxy_layout,
+                "Proxies and originals (proxies/ and originals/ subfolders)",
+            )
+            .on_hover_text("Keeps an untouched copy of every input next to its resized proxy");
+
+            ui.horizontal(|ui| {
+                ui.label("Subfolders:");
+                egui::ComboBox::from_id_salt("subfolders")
+                    .selected_text(self.naming.subfolders.label())
+                    .show_ui(ui, |ui| {
+                        for subfolders in Subfolders::ALL {
+                            // Mirroring needs the folder a recursive selection came from
+                            ui.add_enabled_ui(
+                                subfolders != Subfolders::MirrorInput || self.folder_root.is_some(),
+                                |ui| {
+                                    ui.selectable_value(
+                                        &mut self.naming.subfolders,
+                                        subfolders,
+                                        subfolders.label(),
+                                    )
+                                    .on_disabled_hover_text(
+                                        "Select a folder (recursive) to keep its subfolders",
+                                    );
+                                },
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Year/month uses the EXIF capture date, or the modified date without one",
+                    );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Existing outputs:");
+                egui::ComboBox::from_id_salt("overwrite_policy")
+                    .selected_text(self.naming.overwrite.label())
+                    .show_ui(ui, |ui| {
+                        for policy in OverwritePolicy::ALL {
+                            ui.selectable_value(&mut self.naming.overwrite, policy, policy.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Rename writes photo (1).jpg, photo (2).jpg, ... next to them");
+                let writes_over_inputs = self.output_directory.as_ref().is_some_and(|dir| {
+                    self.selected_files
+                        .iter()
+                        .any(|file| file.parent() == Some(dir.as_path()))
+                });
+                if writes_over_inputs && self.naming.overwrite == OverwritePolicy::Overwrite {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        "Some images are in the output folder, files there may be replaced",
+                    );
+                }
+            });
+
+            ui.checkbox(
+                &mut self.naming.allow_input_overwrite,
+                "Allow overwriting original images",
+            )
+            .on_hover_text("Otherwise outputs that would replace their own source are refused");
+
+            ui.separator();
+
+            // Size settings
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.use_custom_size, false, "Use Preset");
+                ui.radio_value(&mut self.use_custom_size, true, "Custom Size");
+            });
+
+            if self.use_custom_size {
+                ui.horizontal(|ui| {
+                    ui.label("Width:");
+                    ui.text_edit_singleline(&mut self.custom_width);
+                    ui.label("Height:");
+                    ui.text_edit_singleline(&mut self.custom_height);
+                });
+                if let Err(e) = self.parsed_custom_size() {
+                    ui.colored_label(egui::Color32::RED, e.to_string());
+                }
+
+                if let Ok((width, height)) = self.parsed_custom_size() {
+                    if let Some(preset) = NamedPreset::nearest(&self.presets, width, height) {
+                        if ui
+                            .button(format!("Snap to {}?", preset.name))
+                            .on_hover_text(format!("{}x{}", preset.width, preset.height))
+                            .clicked()
+                        {
+                            if let Some(index) = self.presets.iter().position(|p| p == preset) {
+                                self.selected_preset = index;
+                            }
+                            self.use_custom_size = false;
+                        }
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Save as preset:");
+                    ui.text_edit_singleline(&mut self.new_preset_name);
+                    let size = self.parsed_custom_size().ok();
+                    let can_add = size.is_some() && !self.new_preset_name.trim().is_empty();
+                    if ui
+                        .add_enabled(can_add, egui::Button::new("Add Preset"))
+                        .on_hover_text("Saves this size, mode and output format under the name")
+                        .clicked()
+                    {
+                        if let Some((width, height)) = size {
+                            self.add_preset(width, height);
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Mode:");
+                    egui::ComboBox::from_id_salt("resize_mode")
+                        .selected_text(self.mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in ResizeMode::ALL {
+                                ui.selectable_value(&mut self.mode, mode, mode.label());
+                            }
+                        });
+                    ui.checkbox(&mut self.prevent_upscale, "Don't enlarge smaller images")
+                        .on_hover_text("Images smaller than the target in both directions keep their size");
+                });
+
+                ui.add_enabled_ui(self.mode == ResizeMode::Fit, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.pad_to_target, "Pad to the exact size with")
+                            .on_hover_text("Centers each image on bars of this color, e.g. square thumbnails");
+                        ui.color_edit_button_srgba_unmultiplied(&mut self.background);
+                    });
+                });
+
+                ui.add_enabled_ui(self.mode == ResizeMode::Stretch, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Fit instead of stretching within");
+                        ui.add(
+                            egui::DragValue::new(&mut self.aspect_tolerance_percent)
+                                .range(0.0..=10.0)
+                                .speed(0.1)
+                                .suffix(" %"),
+                        );
+                    })
+                    .response
+                    .on_hover_text("Avoids imperceptible distortion when the ratios nearly match");
+
+                    #[cfg(feature = "seam-carving")]
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.seam_carving, "Seam carve instead, up to")
+                            .on_hover_text(
+                                "Removes low-detail paths so subjects keep their shape, slow on large images",
+                            );
+                        ui.add_enabled(
+                            self.seam_carving,
+                            egui::DragValue::new(&mut self.seam_carving_megapixels)
+                                .range(0.1..=20.0)
+                                .speed(0.05)
+                                .suffix(" MP"),
+                        );
+                    });
+                });
 
-            ui.separator();
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.use_pixel_budget, "Size by total pixels instead:")
+                        .on_hover_text("Keeps the source's ratio, width and height are ignored");
+                    ui.add_enabled_ui(self.use_pixel_budget, |ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut self.pixel_budget_megapixels)
+                                .range(0.01..=500.0)
+                                .speed(0.05)
+                                .suffix(" MP"),
+                        );
+                        ui.label("sides a multiple of");
+                        ui.add(egui::DragValue::new(&mut self.pixel_budget_multiple).range(1..=256));
+                    });
+                });
 
-            // Size settings
-            ui.horizontal(|ui| {
-                ui.radio_value(&mut self.use_custom_size, false, "Use Preset");
-                ui.radio_value(&mut self.use_custom_size, true, "Custom Size");
-            });
+                ui.horizontal(|ui| {
+                    ui.label("Filter:");
+                    egui::ComboBox::from_id_salt("filter")
+                        .selected_text(self.filter.label())
+                        .show_ui(ui, |ui| {
+                            for filter in ResampleFilter::ALL {
+                                ui.selectable_value(&mut self.filter, filter, filter.label());
+                            }
+                        })
+                        .response
+                        .on_hover_text("Nearest and Triangle are fastest, Lanczos3 looks best");
+                });
 
-            if self.use_custom_size {
                 ui.horizontal(|ui| {
-                    ui.label("Width:");
-                    ui.text_edit_singleline(&mut self.custom_width);
-                    ui.label("Height:");
-                    ui.text_edit_singleline(&mut self.custom_height);
+                    ui.checkbox(&mut self.use_stretch_filter, "Stretch with:")
+                        .on_hover_text("A softer filter hides some of the distortion of stretching");
+                    ui.add_enabled_ui(self.use_stretch_filter, |ui| {
+                        egui::ComboBox::from_id_salt("stretch_filter")
+                            .selected_text(self.stretch_filter.label())
+                            .show_ui(ui, |ui| {
+                                for filter in ResampleFilter::ALL {
+                                    ui.selectable_value(
+                                        &mut self.stretch_filter,
+                                        filter,
+                                        filter.label(),
+                                    );
+                                }
+                            });
+                    });
                 });
 
-                ui.checkbox(&mut self.maintain_aspect_ratio, "Maintain aspect ratio");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.use_custom_kernel, "Custom kernel:")
+                        .on_hover_text("Replaces the filter above");
+                    ui.add_enabled_ui(self.use_custom_kernel, |ui| {
+                        egui::ComboBox::from_id_salt("custom_kernel")
+                            .selected_text(self.custom_kernel.label())
+                            .show_ui(ui, |ui| {
+                                for kernel in Kernel::ALL {
+                                    let selected = self.custom_kernel.same_kind(kernel);
+                                    if ui.selectable_label(selected, kernel.label()).clicked()
+                                        && !selected
+                                    {
+                                        self.custom_kernel = kernel;
+                                    }
+                                }
+                            });
+                        let support = kernel::MIN_SUPPORT..=kernel::MAX_SUPPORT;
+                        match &mut self.custom_kernel {
+                            Kernel::Triangle { radius } => {
+                                ui.label("Radius");
+                                ui.add(egui::DragValue::new(radius).range(support).speed(0.05));
+                            }
+                            Kernel::Gaussian { sigma } => {
+                                ui.label("Sigma");
+                                ui.add(
+                                    egui::DragValue::new(sigma)
+                                        .range(support.start() / 3.0..=support.end() / 3.0)
+                                        .speed(0.01),
+                                );
+                            }
+                            Kernel::Lanczos { lobes } => {
+                                ui.label("Lobes");
+                                ui.add(egui::DragValue::new(lobes).range(support).speed(0.05));
+                            }
+                            Kernel::Cubic { b, c } => {
+                                ui.label("B");
+                                ui.add(egui::DragValue::new(b).range(-2.0..=2.0).speed(0.01));
+                                ui.label("C");
+                                ui.add(egui::DragValue::new(c).range(-2.0..=2.0).speed(0.01));
+                            }
+                        }
+                    });
+                });
 
                 ui.horizontal(|ui| {
                     ui.label("Output Format:");
@@ -240,6 +2097,7 @@ impl eframe::App for ImageResizerApp {
                             OutputFormat::Webp => "WebP",
                             OutputFormat::Bmp => "BMP",
                             OutputFormat::Tiff => "TIFF",
+                            OutputFormat::Avif => "AVIF",
                         })
                         .show_ui(ui, |ui| {
                             ui.selectable_value(
@@ -272,73 +2130,723 @@ impl eframe::App for ImageResizerApp {
                                 OutputFormat::Tiff,
                                 "TIFF",
                             );
+                            #[cfg(feature = "avif")]
+                            ui.selectable_value(
+                                &mut self.custom_output_format,
+                                OutputFormat::Avif,
+                                "AVIF",
+                            );
                         });
                 });
+
+                // Originals may be JPEGs too
+                if matches!(
+                    self.custom_output_format,
+                    OutputFormat::Jpeg | OutputFormat::KeepOriginal
+                ) {
+                    ui.horizontal(|ui| {
+                        ui.label("JPEG quality:");
+                        ui.add(egui::Slider::new(&mut self.jpeg_quality, 1..=100));
+                    });
+                }
+
+                if self.custom_output_format == OutputFormat::Webp {
+                    ui.horizontal(|ui| {
+                        ui.add_enabled(
+                            cfg!(feature = "webp-lossy"),
+                            egui::Checkbox::new(&mut self.webp_lossless, "Lossless WebP"),
+                        )
+                        .on_disabled_hover_text(
+                            "Lossy WebP needs resize-rs built with the webp-lossy feature",
+                        );
+                        ui.add_enabled(
+                            !self.webp_lossless,
+                            egui::Slider::new(&mut self.webp_quality, 0.0..=100.0)
+                                .text("quality"),
+                        );
+                    });
+                }
             } else {
                 ui.horizontal(|ui| {
                     ui.label("Preset:");
                     egui::ComboBox::from_label("")
-                        .selected_text(self.selected_preset.name)
+                        .selected_text(self.presets[self.selected_preset].name.as_ref())
                         .show_ui(ui, |ui| {
-                            for preset in ResizePreset::PRESETS {
+                            for (index, preset) in self.presets.iter().enumerate() {
                                 ui.selectable_value(
                                     &mut self.selected_preset,
-                                    *preset,
-                                    format!("{} ({}x{})", preset.name, preset.width, preset.height),
+                                    index,
+                                    format!("{} ({})", preset.name, preset.size_label()),
                                 );
                             }
                         });
+                    if ui
+                        .add_enabled(self.presets.len() > 1, egui::Button::new("Delete Preset"))
+                        .clicked()
+                    {
+                        self.delete_selected_preset();
+                    }
                 });
 
-                ui.label(format!(
-                    "Size: {}x{} (Aspect ratio: {})",
-                    self.selected_preset.width,
-                    self.selected_preset.height,
-                    if self.selected_preset.maintain_aspect_ratio {
-                        "maintained"
-                    } else {
-                        "ignored"
-                    }
-                ));
+                let preset = &self.presets[self.selected_preset];
+                if preset.scale_percent.is_some() {
+                    ui.label(format!("Size: {} of each image", preset.size_label()));
+                } else {
+                    ui.label(format!(
+                        "Size: {} ({})",
+                        preset.size_label(),
+                        preset.mode.label()
+                    ));
+                }
             }
 
+            ui.checkbox(
+                &mut self.size_from_file_name,
+                "Use the size in file names like logo@800x600.png",
+            )
+            .on_hover_text("Files without a size in their name use the size above");
+
             ui.separator();
 
+            ui.horizontal(|ui| {
+                ui.label("Animated images:");
+                egui::ComboBox::from_id_salt("animation_policy")
+                    .selected_text(self.animation_policy.label())
+                    .show_ui(ui, |ui| {
+                        for policy in AnimationPolicy::ALL {
+                            ui.selectable_value(&mut self.animation_policy, policy, policy.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Used when an animated GIF/WebP is saved as a still image");
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("When the output format can't hold the image:");
+                egui::ComboBox::from_id_salt("incompatible_output")
+                    .selected_text(self.incompatible_output.label())
+                    .show_ui(ui, |ui| {
+                        for policy in IncompatibleOutput::ALL {
+                            ui.selectable_value(
+                                &mut self.incompatible_output,
+                                policy,
+                                policy.label(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "e.g. 16-bit sources saved as JPEG/BMP/WebP, or transparency saved as JPEG",
+                    );
+            });
+
+            ui.horizontal(|ui| {
+                ui.checkbox(
+                    &mut self.fast_filter_for_large,
+                    "Use faster filter for images above",
+                );
+                ui.add_enabled(
+                    self.fast_filter_for_large,
+                    egui::DragValue::new(&mut self.fast_filter_megapixels)
+                        .range(1..=1000)
+                        .suffix(" MP"),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.use_timeout, "Skip images that take longer than")
+                    .on_hover_text("Keeps one pathological file from stalling the whole batch");
+                ui.add_enabled(
+                    self.use_timeout,
+                    egui::DragValue::new(&mut self.timeout_seconds)
+                        .range(1..=3600)
+                        .suffix(" s"),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.limit_parallelism, "Resize at most")
+                    .on_hover_text("Fewer images at once need less memory for very large sources");
+                ui.add_enabled(
+                    self.limit_parallelism,
+                    egui::DragValue::new(&mut self.max_parallel_images).range(1..=max_workers()),
+                );
+                ui.label("images at once");
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.separate_reads, "Read inputs on")
+                    .on_hover_text(
+                        "Reading ahead while images are resized helps on network storage, \
+                         where reads wait more than they compute",
+                    );
+                ui.add_enabled(
+                    self.separate_reads,
+                    egui::DragValue::new(&mut self.max_parallel_reads).range(1..=64),
+                );
+                ui.label("threads of their own");
+            });
+            #[cfg(feature = "mmap")]
+            ui.checkbox(&mut self.memory_map_inputs, "Memory-map large inputs")
+                .on_hover_text(
+                    "Files over 16 MB are decoded straight from the page cache instead of \
+                     being copied first. Don't change the files while a batch runs",
+                );
+            ui.checkbox(
+                &mut self.area_average_integer_factors,
+                "Average pixel blocks for whole-number shrink factors",
+            )
+            .on_hover_text("Exactly 1/2, 1/3, 1/4, ... of the source is averaged instead of filtered");
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.date_caption, "Caption with EXIF capture date");
+                ui.add_enabled_ui(self.date_caption, |ui| {
+                    egui::ComboBox::from_id_salt("date_caption_corner")
+                        .selected_text(self.date_caption_corner.label())
+                        .show_ui(ui, |ui| {
+                            for corner in Corner::ALL {
+                                ui.selectable_value(
+                                    &mut self.date_caption_corner,
+                                    corner,
+                                    corner.label(),
+                                );
+                            }
+                        });
+                });
+            });
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.grayscale, "Grayscale");
+                ui.add_enabled_ui(self.grayscale, |ui| {
+                    egui::ComboBox::from_id_salt("grayscale_mode")
+                        .selected_text(self.grayscale_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in GrayscaleMode::ALL {
+                                ui.selectable_value(&mut self.grayscale_mode, mode, mode.label());
+                            }
+                        })
+                        .response
+                        .on_hover_text("Luminosity keeps the brightness the eye sees");
+                });
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Look:");
+                let mut chosen = None;
+                let mut deleted = None;
+                egui::ComboBox::from_id_salt("look")
+                    .selected_text("Apply saved look")
+                    .show_ui(ui, |ui| {
+                        for (index, look) in self.looks.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                if ui.selectable_label(false, &look.name).clicked() {
+                                    chosen = Some(look.adjustments);
+                                }
+                                if ui.small_button("Delete").clicked() {
+                                    deleted = Some(index);
+                                }
+                            });
+                        }
+                        if self.looks.is_empty() {
+                            ui.weak("No saved looks");
+                        }
+                    });
+                if let Some(adjustments) = chosen {
+                    self.adjustments = adjustments;
+                }
+                if let Some(index) = deleted {
+                    self.delete_look(index);
+                }
+
+                egui::ComboBox::from_id_salt("new_adjustment")
+                    .selected_text(self.new_adjustment.label())
+                    .show_ui(ui, |ui| {
+                        for adjustment in Adjustment::ALL {
+                            ui.selectable_value(
+                                &mut self.new_adjustment,
+                                adjustment,
+                                adjustment.label(),
+                            );
+                        }
+                    });
+                if ui
+                    .add_enabled(!self.adjustments.is_full(), egui::Button::new("Add Step"))
+                    .clicked()
+                {
+                    // Can't fail, the button is disabled on a full stack
+                    let _ = self.adjustments.push(self.new_adjustment);
+                }
+                if ui
+                    .add_enabled(
+                        !self.adjustments.is_empty() && !self.selected_files.is_empty(),
+                        egui::Button::new("Preview"),
+                    )
+                    .on_hover_text("Applies the steps to the first selected image")
+                    .clicked()
+                {
+                    self.preview_look(ui.ctx());
+                }
+            });
+
+            let mut removed = None;
+            let mut moved_up = None;
+            for (index, step) in self.adjustments.steps_mut().iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{}. {}", index + 1, step.label()));
+                    match step {
+                        Adjustment::Grayscale(mode) => {
+                            egui::ComboBox::from_id_salt(("look_grayscale", index))
+                                .selected_text(mode.label())
+                                .show_ui(ui, |ui| {
+                                    for option in GrayscaleMode::ALL {
+                                        ui.selectable_value(mode, option, option.label());
+                                    }
+                                });
+                        }
+                        Adjustment::Brightness(amount) => {
+                            ui.add(egui::Slider::new(amount, -255..=255));
+                        }
+                        Adjustment::Contrast(percent) => {
+                            ui.add(egui::Slider::new(percent, -100.0..=100.0).suffix("%"));
+                        }
+                        Adjustment::Sharpen { sigma, threshold } => {
+                            ui.label("Radius");
+                            ui.add(egui::DragValue::new(sigma).range(0.1..=10.0).speed(0.05));
+                            ui.label("Threshold");
+                            ui.add(egui::DragValue::new(threshold).range(0..=255));
+                        }
+                    }
+                    if ui
+                        .add_enabled(index > 0, egui::Button::new("Up"))
+                        .clicked()
+                    {
+                        moved_up = Some(index);
+                    }
+                    if ui.button("Remove").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = moved_up {
+                self.adjustments.move_up(index);
+            }
+            if let Some(index) = removed {
+                self.adjustments.remove(index);
+            }
+
+            if !self.adjustments.is_empty() {
+                ui.horizontal(|ui| {
+                    ui.label("Look name:");
+                    ui.text_edit_singleline(&mut self.new_look_name);
+                    if ui
+                        .add_enabled(
+                            !self.new_look_name.trim().is_empty(),
+                            egui::Button::new("Save Look"),
+                        )
+                        .clicked()
+                    {
+                        self.save_look();
+                    }
+                });
+            }
+
+            if let Some(preview) = &self.look_preview {
+                let mut close = false;
+                ui.horizontal(|ui| {
+                    ui.image((preview.id(), preview.size_vec2()));
+                    close = ui.button("Close Preview").clicked();
+                });
+                if close {
+                    self.look_preview = None;
+                }
+            }
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.posterize, "Posterize to");
+                ui.add_enabled_ui(self.posterize, |ui| {
+                    ui.add(egui::DragValue::new(&mut self.posterize_levels).range(2..=64));
+                    ui.label("levels per channel");
+                    ui.checkbox(&mut self.dither, "Dither")
+                        .on_hover_text("Spreads the rounding error so gradients don't band");
+                });
+            });
+
+            ui.checkbox(
+                &mut self.region_mask,
+                "Only change the area marked white in a _mask.png next to each image",
+            )
+            .on_hover_text(
+                "photo.jpg uses photo_mask.png of the same size, the rest of the photo is kept \
+                 and the output has the photo's size",
+            );
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.use_mask, "Mask outputs")
+                    .on_hover_text("Makes everything outside the shape transparent");
+                ui.add_enabled_ui(self.use_mask, |ui| {
+                    egui::ComboBox::from_id_salt("mask_shape")
+                        .selected_text(self.mask_shape.label())
+                        .show_ui(ui, |ui| {
+                            for shape in [MaskShape::Circle, MaskShape::RoundedRect(self.mask_radius)]
+                            {
+                                let selected = std::mem::discriminant(&self.mask_shape)
+                                    == std::mem::discriminant(&shape);
+                                if ui.selectable_label(selected, shape.label()).clicked() {
+                                    self.mask_shape = shape;
+                                }
+                            }
+                        });
+                    if matches!(self.mask_shape, MaskShape::RoundedRect(_)) {
+                        ui.add(
+                            egui::DragValue::new(&mut self.mask_radius)
+                                .range(1..=1000)
+                                .suffix(" px"),
+                        );
+                    }
+                });
+            });
+
+            ui.checkbox(
+                &mut self.respect_exif_orientation,
+                "Rotate photos upright using their EXIF orientation",
+            );
+            ui.checkbox(&mut self.deskew, "Deskew scans")
+                .on_hover_text("Straightens pages scanned up to 5° crooked before resizing");
+            ui.checkbox(&mut self.split_photos, "Split scanned photos")
+                .on_hover_text(
+                    "Finds the prints on a scan with a plain background and writes each one \
+                     straightened as its own numbered output",
+                );
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.skip_blurry, "Skip images less sharp than")
+                    .on_hover_text("Variance of the Laplacian, sharp photos usually score a few hundred");
+                ui.add_enabled(
+                    self.skip_blurry,
+                    egui::DragValue::new(&mut self.min_sharpness).range(0.0..=10_000.0),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Multi-image TIFF/ICO:");
+                egui::ComboBox::from_id_salt("multi_image")
+                    .selected_text(self.multi_image.label())
+                    .show_ui(ui, |ui| {
+                        for select in [
+                            MultiImageSelect::Primary,
+                            MultiImageSelect::Largest,
+                            MultiImageSelect::Index(self.multi_image_index),
+                        ] {
+                            let selected = std::mem::discriminant(&self.multi_image)
+                                == std::mem::discriminant(&select);
+                            if ui.selectable_label(selected, select.label()).clicked() {
+                                self.multi_image = select;
+                            }
+                        }
+                    });
+                if matches!(self.multi_image, MultiImageSelect::Index(_)) {
+                    ui.add(egui::DragValue::new(&mut self.multi_image_index).prefix("#"));
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Crop before resize:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.crop_text)
+                        .hint_text("x,y,width,height e.g. 0.1,0.1,0.8,0.8")
+                        .desired_width(220.0),
+                );
+                if let Err(e) = self.parsed_crop() {
+                    ui.colored_label(egui::Color32::RED, e.to_string());
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("ICC profile and EXIF:");
+                egui::ComboBox::from_id_salt("metadata_policy")
+                    .selected_text(self.metadata.label())
+                    .show_ui(ui, |ui| {
+                        for policy in MetadataPolicy::ALL {
+                            ui.selectable_value(&mut self.metadata, policy, policy.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Kept for JPEG and PNG outputs. EXIF includes the camera and any GPS \
+                         position, strip it before sharing photos publicly",
+                    );
+            });
+
+            ui.collapsing("XMP metadata", |ui| {
+                ui.checkbox(&mut self.preserve_xmp, "Keep XMP from the originals")
+                    .on_hover_text("Ratings, keywords and rights, for JPEG, PNG and WebP outputs");
+                ui.horizontal(|ui| {
+                    ui.label("Copyright:");
+                    ui.text_edit_singleline(&mut self.xmp_copyright);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Author:");
+                    ui.text_edit_singleline(&mut self.xmp_author);
+                });
+                if !self.xmp_copyright.is_empty() || !self.xmp_author.is_empty() {
+                    ui.weak("Written to every output, replacing XMP kept from the originals");
+                }
+            });
+
+            ui.collapsing("Encoder parameters", |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.encoder_params_text)
+                        .hint_text("One key=value per line, e.g. jpeg.quality=85")
+                        .desired_rows(3),
+                )
+                .on_hover_text(format!("Known keys: {}", encoder_params::KNOWN_KEYS.join(", ")));
+                let warnings = RefCell::new(Vec::new());
+                match self.parsed_encoder_params(&|warning| warnings.borrow_mut().push(warning)) {
+                    Ok(_) => {
+                        for warning in warnings.into_inner() {
+                            ui.weak(warning);
+                        }
+                    }
+                    Err(e) => {
+                        ui.colored_label(egui::Color32::RED, e.to_string());
+                    }
+                }
+            });
+
+            // Restart markers only exist in JPEG streams, originals may be JPEGs too
+            if matches!(
+                self.current_preset().output_format,
+                OutputFormat::Jpeg | OutputFormat::KeepOriginal
+            ) {
+                ui.collapsing("Advanced", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.jpeg_restart_markers, "JPEG restart markers every")
+                            .on_hover_text("Limits how far corruption spreads in damaged files");
+                        ui.add_enabled(
+                            self.jpeg_restart_markers,
+                            egui::DragValue::new(&mut self.jpeg_restart_interval)
+                                .range(1..=u16::MAX)
+                                .suffix(" MCUs"),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.use_ssim_target, "Choose JPEG quality for SSIM")
+                            .on_hover_text(
+                                "Use the lowest quality that still looks this close to the resized image",
+                            );
+                        ui.add_enabled(
+                            self.use_ssim_target,
+                            egui::DragValue::new(&mut self.ssim_target)
+                                .range(0.8..=0.999)
+                                .speed(0.001)
+                                .fixed_decimals(3),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.use_byte_budget, "Keep JPEGs under")
+                            .on_hover_text(
+                                "Lowers quality down to the floor, then shrinks the image if it still doesn't fit",
+                            );
+                        ui.add_enabled_ui(self.use_byte_budget, |ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut self.byte_budget_kb)
+                                    .range(1..=100_000)
+                                    .suffix(" KB"),
+                            );
+                            ui.label("at quality no lower than");
+                            ui.add(egui::DragValue::new(&mut self.quality_floor).range(1..=100));
+                        });
+                    });
+                });
+            }
+
+            ui.checkbox(
+                &mut self.generate_gallery,
+                "Generate HTML gallery (index.html)",
+            );
+            ui.checkbox(
+                &mut self.generate_contact_sheet,
+                "Generate printable contact sheet (contact-sheet.pdf)",
+            )
+            .on_hover_text("Captioned thumbnails in a grid, each linking to its file");
+            ui.checkbox(&mut self.dpr_variants, "Also write @2x and @3x variants")
+                .on_hover_text("Retina sizes named like logo@2x.png, skipped when the source is too small");
+            ui.checkbox(
+                &mut self.jpeg_fallback,
+                "Also write a JPEG fallback next to WebP outputs",
+            )
+            .on_hover_text("photo.webp gets a photo.jpg of the same size for clients without WebP");
+            ui.checkbox(&mut self.lqip, "Also write tiny placeholder previews")
+                .on_hover_text("photo.webp gets a 32 px photo_lqip.webp to show blurred while it loads");
+            ui.checkbox(
+                &mut self.average_color,
+                "Report average colors and write swatches",
+            )
+            .on_hover_text("Logs each output's average color and saves a 16x16 _swatch.png of it");
+            ui.checkbox(
+                &mut self.write_manifest,
+                "Write dependency manifest (resize.d)",
+            )
+            .on_hover_text("Make/Ninja depfile listing which input each output was made from");
+            ui.checkbox(
+                &mut self.write_image_manifest,
+                "Write image manifest (images.json)",
+            )
+            .on_hover_text(
+                "Path, width, height and format of every variant per source, for web frameworks",
+            );
+            ui.checkbox(
+                &mut self.write_checksums,
+                "Write SHA-256 checksums (checksums.json)",
+            );
+            ui.checkbox(&mut self.check_disk_space, "Check free disk space first")
+                .on_hover_text("Estimate the output size and refuse to start if the volume is too full");
+
+            #[cfg(feature = "battery-pause")]
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.battery_pause, "Pause on battery below");
+                ui.add_enabled(
+                    self.battery_pause,
+                    egui::DragValue::new(&mut self.battery_pause_below)
+                        .range(1..=100)
+                        .suffix(" %"),
+                );
+            });
+
+            #[cfg(feature = "event-server")]
+            ui.horizontal(|ui| {
+                ui.label("Event server port:");
+                let running = self.event_server.is_some();
+                ui.add_enabled(
+                    !running,
+                    egui::DragValue::new(&mut self.event_server_port).range(1024..=65535),
+                );
+                if running {
+                    ui.label("Running");
+                } else if ui.button("Start").clicked() {
+                    self.start_event_server();
+                }
+            });
+
+            ui.checkbox(&mut self.show_preview, "Preview the first image");
+            if self.show_preview && !self.selected_files.is_empty() {
+                match &self.preview {
+                    Some(Ok(preview)) => {
+                        let [width, height] = preview.output.size();
+                        ui.label(format!("Preview: {width}x{height}"));
+                        ui.add(
+                            egui::Image::new((preview.output.id(), preview.output.size_vec2()))
+                                .max_width(320.0)
+                                .max_height(240.0),
+                        );
+                        let layout = &preview.layout;
+                        if !layout.discarded.is_empty() || !layout.padding.is_empty() {
+                            ui.label("Source: shaded parts are cut off, blue parts are padding");
+                            Self::show_layout(ui, &preview.source, layout);
+                        }
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::RED, format!("No preview: {e}"));
+                    }
+                    None => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Rendering preview");
+                        });
+                    }
+                }
+            }
+
             // Processing controls and status
-            let can_process = !self.selected_files.is_empty()
+            let can_process = self.has_inputs()
                 && self.output_directory.is_some()
+                && self.parsed_crop().is_ok()
+                && self.parsed_encoder_params(&|_| {}).is_ok()
+                && self.naming.validate().is_ok()
+                && (!self.use_custom_size || self.parsed_custom_size().is_ok())
                 && matches!(
                     self.processing_status,
-                    ProcessingStatus::Idle | ProcessingStatus::Completed { .. }
+                    ProcessingStatus::Idle
+                        | ProcessingStatus::Completed { .. }
+                        | ProcessingStatus::Cancelled { .. }
                 );
 
+            let mut open_output = false;
             ui.horizontal(|ui| {
-                if ui
+                if matches!(self.processing_status, ProcessingStatus::Processing { .. }) {
+                    let cancelling = self.cancel.load(Ordering::Relaxed);
+                    if ui
+                        .add_enabled(!cancelling, egui::Button::new("Cancel"))
+                        .on_hover_text("Images already being resized are finished first")
+                        .clicked()
+                    {
+                        self.cancel.store(true, Ordering::Relaxed);
+                        self.add_log_message("Cancelling...".to_string());
+                    }
+                } else if ui
                     .add_enabled(can_process, egui::Button::new("Start Processing"))
                     .clicked()
                 {
                     self.start_processing();
                 }
 
+                if ui
+                    .add_enabled(can_process, egui::Button::new("Export Plan"))
+                    .on_hover_text("Write the planned operations to a JSON job file")
+                    .clicked()
+                {
+                    self.export_plan();
+                }
+
                 match &self.processing_status {
                     ProcessingStatus::Idle => {
                         ui.label("Ready");
                     }
                     ProcessingStatus::Processing { current, total } => {
-                        ui.label(format!("Processing {} of {}", current + 1, total));
-                        let progress = *current as f32 / *total as f32;
-                        ui.add(egui::ProgressBar::new(progress).show_percentage());
+                        ui.label(format!("Processing {} of {}", (current + 1).min(*total), total));
+                        ui.add(
+                            egui::ProgressBar::new(progress_fraction(*current, *total))
+                                .show_percentage(),
+                        );
+                    }
+                    ProcessingStatus::Completed {
+                        successful,
+                        skipped,
+                        failed,
+                        ..
+                    } => {
+                        ui.label(format!(
+                            "Completed: {successful} successful, {skipped} skipped, {failed} failed"
+                        ));
+                        open_output = ui.button("Open Output Folder").clicked();
                     }
-                    ProcessingStatus::Completed { successful, failed } => {
+                    ProcessingStatus::Cancelled {
+                        successful,
+                        skipped,
+                        failed,
+                        total,
+                        ..
+                    } => {
                         ui.label(format!(
-                            "Completed: {successful} successful, {failed} failed"
+                            "Cancelled after {} of {total}: {successful} successful, {skipped} \
+                             skipped, {failed} failed",
+                            successful + skipped + failed
                         ));
                     }
                     ProcessingStatus::Error(err) => {
                         ui.colored_label(egui::Color32::RED, format!("Error: {err}"));
                     }
+                    ProcessingStatus::Message(_) => {}
                 }
             });
+            if open_output {
+                self.open_output_directory();
+            }
+
+            if let ProcessingStatus::Completed { results, .. }
+            | ProcessingStatus::Cancelled { results, .. } = &self.processing_status
+            {
+                if !results.is_empty() {
+                    Self::show_results(ui, results);
+                }
+            }
 
             ui.separator();
 
@@ -355,9 +2863,123 @@ impl eframe::App for ImageResizerApp {
             });
         });
 
+        if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("drop_overlay"),
+            ));
+            let screen = ctx.screen_rect();
+            painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(160));
+            painter.text(
+                screen.center(),
+                egui::Align2::CENTER_CENTER,
+                "Drop images here",
+                egui::FontId::proportional(24.0),
+                egui::Color32::WHITE,
+            );
+        }
+
         // Request repaint if processing
-        if matches!(self.processing_status, ProcessingStatus::Processing { .. }) {
+        if matches!(self.processing_status, ProcessingStatus::Processing { .. })
+            || self.scan_receiver.is_some()
+        {
             ctx.request_repaint();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::ReadAhead;
+
+    #[test]
+    fn empty_batch_progress_is_not_nan() {
+        assert!((progress_fraction(0, 0) - 1.0).abs() < f32::EPSILON);
+        assert!((progress_fraction(1, 4) - 0.25).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn shutdown_cancels_the_batch_before_more_files_are_written() {
+        const INPUTS: usize = 200;
+        let dir = tempfile::tempdir().unwrap();
+        let output_dir = dir.path().to_path_buf();
+        let bytes =
+            crate::test_util::encode(&crate::test_util::photo(160, 120), image::ImageFormat::Png);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (started_tx, started) = mpsc::channel();
+
+        let worker = BatchWorker::spawn({
+            let cancel = Arc::clone(&cancel);
+            let output_dir = output_dir.clone();
+            move || {
+                let names: Vec<_> = (0..INPUTS)
+                    .map(|i| PathBuf::from(format!("photo{i}.png")))
+                    .collect();
+                let inputs: Vec<_> = names
+                    .iter()
+                    .map(|name| ReadAhead::new(name, Ok(bytes.clone().into())))
+                    .collect();
+                let preset = ResizePreset {
+                    width: 80,
+                    height: 80,
+                    ..ResizePreset::BASE
+                };
+                let _ = ImageResizer::batch_resize(
+                    &inputs,
+                    &output_dir,
+                    &preset,
+                    &Naming::default(),
+                    &FileSystemSink,
+                    |current, _| {
+                        if current == 1 {
+                            let _ = started_tx.send(());
+                        }
+                        if cancel.load(Ordering::Relaxed) {
+                            ControlFlow::Break(())
+                        } else {
+                            ControlFlow::Continue(())
+                        }
+                    },
+                    |_| {},
+                );
+            }
+        });
+
+        started.recv().unwrap();
+        assert!(worker.shut_down(&cancel, SHUTDOWN_TIMEOUT));
+        assert!(cancel.load(Ordering::Relaxed));
+
+        let written = std::fs::read_dir(&output_dir).unwrap().count();
+        assert!(written < INPUTS, "{written} files written");
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(std::fs::read_dir(&output_dir).unwrap().count(), written);
+    }
+
+    #[test]
+    fn zero_or_empty_custom_sizes_are_rejected() {
+        let app = |width: &str, height: &str| ImageResizerApp {
+            custom_width: width.to_owned(),
+            custom_height: height.to_owned(),
+            ..ImageResizerApp::new()
+        };
+
+        assert_eq!(
+            app("640", " 480 ").parsed_custom_size().unwrap(),
+            (640, 480)
+        );
+        for (width, height) in [("0", "480"), ("640", "0"), ("", "480"), ("640", "abc")] {
+            assert!(
+                app(width, height).parsed_custom_size().is_err(),
+                "{width:?}x{height:?}"
+            );
+        }
+        assert_eq!(
+            app("0", "480")
+                .parsed_custom_size()
+                .unwrap_err()
+                .to_string(),
+            "Width must be a whole number above 0, got \"0\""
+        );
+    }
+}
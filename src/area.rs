@@ -0,0 +1,65 @@
+use image::{DynamicImage, Rgba32FImage};
+
+use crate::color;
+
+/// Downscales by averaging each `factor_x` by `factor_y` block into one pixel.
+///
+/// For whole-number factors this is the exact mean of the covered pixels, which Lanczos and
+/// friends only approximate. The result keeps the source's color type.
+pub fn downscale(img: &DynamicImage, factor_x: u32, factor_y: u32) -> DynamicImage {
+    let source = img.to_rgba32f();
+    let (width, height) = (img.width() / factor_x, img.height() / factor_y);
+    let block_pixels = (factor_x * factor_y) as f32;
+
+    let averaged = Rgba32FImage::from_fn(width, height, |x, y| {
+        let mut sum = [0.0_f32; 4];
+        for block_y in y * factor_y..(y + 1) * factor_y {
+            for block_x in x * factor_x..(x + 1) * factor_x {
+                let pixel = source.get_pixel(block_x, block_y);
+                for (total, value) in sum.iter_mut().zip(pixel.0) {
+                    *total += value;
+                }
+            }
+        }
+        image::Rgba(sum.map(|total| total / block_pixels))
+    });
+
+    color::convert(&DynamicImage::ImageRgba32F(averaged), img.color())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma, Rgb, RgbImage};
+
+    #[test]
+    fn four_by_four_block_becomes_its_mean() {
+        // 0, 10, ..., 150 averages to 75
+        let block = GrayImage::from_fn(4, 4, |x, y| Luma([((y * 4 + x) * 10) as u8]));
+        let averaged = downscale(&DynamicImage::ImageLuma8(block), 4, 4);
+
+        let DynamicImage::ImageLuma8(averaged) = averaged else {
+            panic!("color type changed to {:?}", averaged.color());
+        };
+        assert_eq!(averaged.dimensions(), (1, 1));
+        assert_eq!(averaged.get_pixel(0, 0).0, [75]);
+    }
+
+    #[test]
+    fn each_block_is_averaged_on_its_own() {
+        let img = RgbImage::from_fn(8, 4, |x, _| {
+            if x < 4 {
+                Rgb([200, 100, 0])
+            } else if x % 2 == 0 {
+                Rgb([0, 0, 0])
+            } else {
+                Rgb([0, 255, 100])
+            }
+        });
+        let averaged = downscale(&DynamicImage::ImageRgb8(img), 4, 4).to_rgb8();
+
+        assert_eq!(averaged.dimensions(), (2, 1));
+        assert_eq!(averaged.get_pixel(0, 0).0, [200, 100, 0]);
+        assert_eq!(averaged.get_pixel(1, 0).0, [0, 128, 50]);
+    }
+}
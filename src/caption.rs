@@ -0,0 +1,80 @@
+use image::{DynamicImage, GenericImage, Rgba};
+
+use crate::presets::Corner;
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+/// 5x7 bitmap glyphs, one byte per row with the leftmost pixel in bit 4.
+/// Only the characters needed for date captions are included.
+const fn glyph(c: char) -> [u8; 7] {
+    match c {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        _ => [0; 7],
+    }
+}
+
+/// Burns `text` into a corner of `img` as white glyphs with a dark drop shadow.
+pub fn draw_caption(img: &mut DynamicImage, text: &str, corner: Corner) {
+    // Scale glyphs with the image so the caption stays readable on large outputs
+    let scale = (img.width().min(img.height()) / 150).max(1);
+    let advance = (GLYPH_WIDTH + 1) * scale;
+    let text_width = advance * text.chars().count() as u32;
+    let text_height = GLYPH_HEIGHT * scale;
+    let margin = 4 * scale;
+
+    let (left, top) = match corner {
+        Corner::TopLeft => (margin, margin),
+        Corner::TopRight => (img.width().saturating_sub(text_width + margin), margin),
+        Corner::BottomLeft => (margin, img.height().saturating_sub(text_height + margin)),
+        Corner::BottomRight => (
+            img.width().saturating_sub(text_width + margin),
+            img.height().saturating_sub(text_height + margin),
+        ),
+    };
+
+    draw_text(
+        img,
+        text,
+        left + scale,
+        top + scale,
+        scale,
+        Rgba([0, 0, 0, 255]),
+    );
+    draw_text(img, text, left, top, scale, Rgba([255, 255, 255, 255]));
+}
+
+fn draw_text(img: &mut DynamicImage, text: &str, left: u32, top: u32, scale: u32, color: Rgba<u8>) {
+    let (width, height) = (img.width(), img.height());
+
+    for (index, c) in text.chars().enumerate() {
+        let glyph_left = left + index as u32 * (GLYPH_WIDTH + 1) * scale;
+        for (row, bits) in (0..).zip(glyph(c)) {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (0x10 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let x = glyph_left + column * scale + dx;
+                        let y = top + row * scale + dy;
+                        if x < width && y < height {
+                            img.put_pixel(x, y, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::sink::OutputSink;
+
+const CHECKSUMS_FILE_NAME: &str = "checksums.json";
+
+/// Hashes every file on its way through to the inner sink.
+///
+/// Wrap the sink that does the writing directly, so the hashed bytes are the ones stored.
+pub struct ChecksumSink<'a> {
+    inner: &'a dyn OutputSink,
+    checksums: Mutex<BTreeMap<PathBuf, String>>,
+}
+
+impl<'a> ChecksumSink<'a> {
+    pub fn new(inner: &'a dyn OutputSink) -> Self {
+        Self {
+            inner,
+            checksums: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Writes a `checksums.json` into `output_dir` mapping each output path, relative to
+    /// `output_dir`, to its SHA-256 in lowercase hex.
+    pub fn write_checksums(&self, output_dir: &Path) -> Result<PathBuf> {
+        let entries: BTreeMap<_, _> = self
+            .checksums
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Checksum list is poisoned"))?
+            .iter()
+            .map(|(path, checksum)| {
+                let relative = path.strip_prefix(output_dir).unwrap_or(path);
+                let key = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                (key, checksum.clone())
+            })
+            .collect();
+
+        let checksums_path = output_dir.join(CHECKSUMS_FILE_NAME);
+        let file = File::create(&checksums_path)
+            .with_context(|| format!("Failed to create checksums: {}", checksums_path.display()))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &entries)
+            .with_context(|| format!("Failed to write checksums: {}", checksums_path.display()))?;
+        Ok(checksums_path)
+    }
+}
+
+impl OutputSink for ChecksumSink<'_> {
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        self.inner.write(path, bytes)?;
+        if let Ok(mut checksums) = self.checksums.lock() {
+            checksums.insert(path.to_path_buf(), sha256_hex(bytes));
+        }
+        Ok(())
+    }
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::FileSystemSink;
+    use crate::test_util;
+    use image::ImageFormat;
+
+    #[test]
+    fn hex_matches_the_published_test_vector() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn checksums_match_the_written_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let sink = ChecksumSink::new(&FileSystemSink);
+        let outputs = [
+            dir.path().join("a.png"),
+            dir.path().join("nested").join("b.jpg"),
+        ];
+        for (path, format) in outputs.iter().zip([ImageFormat::Png, ImageFormat::Jpeg]) {
+            sink.write(path, &test_util::encode(&test_util::gradient(9, 7), format))
+                .unwrap();
+        }
+
+        let checksums_path = sink.write_checksums(dir.path()).unwrap();
+        let checksums: BTreeMap<String, String> =
+            serde_json::from_slice(&std::fs::read(checksums_path).unwrap()).unwrap();
+        assert_eq!(checksums.len(), 2);
+        for (key, path) in ["a.png", "nested/b.jpg"].into_iter().zip(&outputs) {
+            let on_disk = Sha256::digest(std::fs::read(path).unwrap());
+            assert_eq!(checksums[key], format!("{on_disk:x}"));
+        }
+    }
+}
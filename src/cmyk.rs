@@ -0,0 +1,154 @@
+use anyhow::{Context, Result};
+use image::{DynamicImage, RgbImage};
+use zune_core::colorspace::ColorSpace;
+use zune_core::options::DecoderOptions;
+use zune_jpeg::JpegDecoder;
+
+/// How the four channels of a CMYK JPEG are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmykEncoding {
+    /// Adobe APP14 files (Photoshop and most print tools) store 255 for no ink.
+    Inverted,
+    /// Files without the Adobe marker store 0 for no ink.
+    Plain,
+}
+
+/// Decodes a CMYK JPEG to RGB, `None` for any other JPEG.
+///
+/// The general decoder assumes the Adobe convention for every CMYK file, which turns
+/// files without the Adobe marker into a negative.
+pub fn decode_cmyk_jpeg(bytes: &[u8]) -> Result<Option<DynamicImage>> {
+    let Some(encoding) = cmyk_encoding(bytes) else {
+        return Ok(None);
+    };
+
+    let options = DecoderOptions::default().jpeg_set_out_colorspace(ColorSpace::CMYK);
+    let mut decoder = JpegDecoder::new_with_options(bytes, options);
+    let pixels = decoder
+        .decode()
+        .map_err(|e| anyhow::anyhow!("{e:?}"))
+        .context("Failed to decode CMYK JPEG")?;
+    let (width, height) = decoder
+        .dimensions()
+        .context("CMYK JPEG has no dimensions")?;
+
+    let rgb: Vec<u8> = pixels
+        .chunks_exact(4)
+        .flat_map(|cmyk| {
+            let [c, m, y, k] = [cmyk[0], cmyk[1], cmyk[2], cmyk[3]].map(|value| match encoding {
+                CmykEncoding::Inverted => u16::from(value),
+                CmykEncoding::Plain => 255 - u16::from(value),
+            });
+            // Each channel is what's left after its own ink and the black ink
+            [c, m, y].map(|channel| (channel * k / 255) as u8)
+        })
+        .collect();
+
+    let image = RgbImage::from_raw(u32::try_from(width)?, u32::try_from(height)?, rgb)
+        .context("CMYK JPEG has an unexpected size")?;
+    Ok(Some(DynamicImage::ImageRgb8(image)))
+}
+
+/// Scans the JPEG headers for a four component frame, and an Adobe APP14 segment saying
+/// how it is stored. YCCK files are left to the general decoder, which handles them.
+fn cmyk_encoding(bytes: &[u8]) -> Option<CmykEncoding> {
+    const APP14: u8 = 0xEE;
+    const SOS: u8 = 0xDA;
+
+    let mut four_components = false;
+    let mut adobe_transform = None;
+    let mut position = 2;
+    while position + 4 <= bytes.len() && bytes[position] == 0xFF {
+        let marker = bytes[position + 1];
+        let length = usize::from(u16::from_be_bytes([
+            bytes[position + 2],
+            bytes[position + 3],
+        ]));
+        let segment = bytes.get(position + 4..position + 2 + length)?;
+
+        match marker {
+            APP14 if segment.starts_with(b"Adobe") => adobe_transform = segment.get(11).copied(),
+            // Start of frame markers, except DHT (C4), JPG (C8) and DAC (CC)
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                four_components = segment.get(5) == Some(&4);
+            }
+            SOS => break,
+            _ => {}
+        }
+        position += 2 + length;
+    }
+
+    if !four_components {
+        return None;
+    }
+
+    match adobe_transform {
+        None => Some(CmykEncoding::Plain),
+        Some(0) => Some(CmykEncoding::Inverted),
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 32x16 CMYK JPEG, left half full magenta and yellow ink (red), right half no ink.
+    /// jpeg-encoder writes the Adobe convention, `plain` rewrites it without the marker.
+    fn red_and_white(plain: bool) -> Vec<u8> {
+        let ink: Vec<u8> = (0..16 * 32)
+            .flat_map(|index| {
+                let ink = if index % 32 < 16 {
+                    [0, 255, 255, 0]
+                } else {
+                    [0; 4]
+                };
+                // The encoder stores 255 minus its input, plain files store the ink itself
+                ink.map(|value| if plain { 255 - value } else { value })
+            })
+            .collect();
+        let mut bytes = Vec::new();
+        jpeg_encoder::Encoder::new(&mut bytes, 95)
+            .encode(&ink, 32, 16, jpeg_encoder::ColorType::Cmyk)
+            .unwrap();
+        if plain {
+            let start = bytes
+                .windows(9)
+                .position(|window| window == b"\xFF\xEE\x00\x0EAdobe")
+                .unwrap();
+            bytes.drain(start..start + 16);
+        }
+        bytes
+    }
+
+    #[test]
+    fn cmyk_jpegs_decode_to_the_right_colors() {
+        for plain in [false, true] {
+            let bytes = red_and_white(plain);
+            assert_eq!(
+                cmyk_encoding(&bytes),
+                Some(if plain {
+                    CmykEncoding::Plain
+                } else {
+                    CmykEncoding::Inverted
+                })
+            );
+
+            let rgb = decode_cmyk_jpeg(&bytes).unwrap().unwrap().to_rgb8();
+
+            let [r, g, b] = rgb.get_pixel(4, 8).0;
+            assert!(r > 240 && g < 15 && b < 15, "red came out {r} {g} {b}");
+            let [r, g, b] = rgb.get_pixel(28, 8).0;
+            assert!(r > 240 && g > 240 && b > 240, "white came out {r} {g} {b}");
+        }
+    }
+
+    #[test]
+    fn rgb_jpegs_are_left_to_the_general_decoder() {
+        let rgb = crate::test_util::encode(
+            &crate::test_util::gradient(16, 16),
+            image::ImageFormat::Jpeg,
+        );
+        assert!(decode_cmyk_jpeg(&rgb).unwrap().is_none());
+    }
+}
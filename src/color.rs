@@ -0,0 +1,148 @@
+use image::{ColorType, DynamicImage, Rgb, RgbImage};
+
+use crate::presets::GrayscaleMode;
+
+const SWATCH_SIZE: u32 = 16;
+
+/// Mean color of all pixels, transparent pixels count as much as opaque ones.
+pub fn average_color(img: &DynamicImage) -> Rgb<u8> {
+    let rgb = img.to_rgb8();
+    let count = u64::from(rgb.width()) * u64::from(rgb.height());
+    if count == 0 {
+        return Rgb([0, 0, 0]);
+    }
+
+    let mut sums = [0_u64; 3];
+    for pixel in rgb.pixels() {
+        for (sum, channel) in sums.iter_mut().zip(pixel.0) {
+            *sum += u64::from(channel);
+        }
+    }
+
+    Rgb(sums.map(|sum| (sum / count) as u8))
+}
+
+pub fn to_hex(color: Rgb<u8>) -> String {
+    let [r, g, b] = color.0;
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// `img` in `color`, for returning float working copies in the source's color type.
+pub fn convert(img: &DynamicImage, color: ColorType) -> DynamicImage {
+    match color {
+        ColorType::L8 => DynamicImage::ImageLuma8(img.to_luma8()),
+        ColorType::La8 => DynamicImage::ImageLumaA8(img.to_luma_alpha8()),
+        ColorType::Rgb8 => DynamicImage::ImageRgb8(img.to_rgb8()),
+        ColorType::Rgba8 => DynamicImage::ImageRgba8(img.to_rgba8()),
+        ColorType::L16 => DynamicImage::ImageLuma16(img.to_luma16()),
+        ColorType::La16 => DynamicImage::ImageLumaA16(img.to_luma_alpha16()),
+        ColorType::Rgb16 => DynamicImage::ImageRgb16(img.to_rgb16()),
+        ColorType::Rgba16 => DynamicImage::ImageRgba16(img.to_rgba16()),
+        ColorType::Rgb32F => DynamicImage::ImageRgb32F(img.to_rgb32f()),
+        _ => DynamicImage::ImageRgba32F(img.to_rgba32f()),
+    }
+}
+
+/// `img` reduced to gray with `mode`, keeping its alpha and bit depth.
+pub fn to_grayscale(img: &DynamicImage, mode: GrayscaleMode) -> DynamicImage {
+    let mut gray = img.to_rgba32f();
+    for pixel in gray.pixels_mut() {
+        let [r, g, b, a] = pixel.0;
+        let level = mode.gray([r, g, b]);
+        pixel.0 = [level, level, level, a];
+    }
+
+    let source = img.color();
+    let wide = source.bytes_per_pixel() > source.channel_count();
+    let color = match (source.has_alpha(), wide) {
+        (false, false) => ColorType::L8,
+        (true, false) => ColorType::La8,
+        (false, true) => ColorType::L16,
+        (true, true) => ColorType::La16,
+    };
+    // Equal channels stay the same level whatever weights the conversion uses
+    convert(&DynamicImage::ImageRgba32F(gray), color)
+}
+
+/// `img` with each color channel reduced to `levels` evenly spaced values, keeping its alpha
+/// and bit depth. With `dither` the rounding error is spread to the following pixels
+/// (Floyd–Steinberg), so gradients turn into a fine pattern instead of bands.
+pub fn posterize(img: &DynamicImage, levels: u8, dither: bool) -> DynamicImage {
+    let mut rgba = img.to_rgba32f();
+    let steps = f32::from(levels.max(2) - 1);
+    let width = rgba.width() as usize;
+
+    // Error owed to this row and the next, padded by a pixel on both sides
+    let mut row_errors = vec![[0.0_f32; 3]; width + 2];
+    let mut next_row_errors = vec![[0.0_f32; 3]; width + 2];
+    for row in rgba.rows_mut() {
+        for (x, pixel) in row.enumerate() {
+            for (channel, value) in pixel.0[..3].iter_mut().enumerate() {
+                let wanted = *value + row_errors[x + 1][channel];
+                *value = (wanted.clamp(0.0, 1.0) * steps).round() / steps;
+                if dither {
+                    let error = wanted - *value;
+                    row_errors[x + 2][channel] += error * 7.0 / 16.0;
+                    next_row_errors[x][channel] += error * 3.0 / 16.0;
+                    next_row_errors[x + 1][channel] += error * 5.0 / 16.0;
+                    next_row_errors[x + 2][channel] += error / 16.0;
+                }
+            }
+        }
+        std::mem::swap(&mut row_errors, &mut next_row_errors);
+        next_row_errors.fill([0.0; 3]);
+    }
+    convert(&DynamicImage::ImageRgba32F(rgba), img.color())
+}
+
+/// Small solid image of `color`, for use as a placeholder.
+pub fn swatch(color: Rgb<u8>) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_pixel(SWATCH_SIZE, SWATCH_SIZE, color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn luminosity_weights_green_by_its_rec_709_coefficient() {
+        const GREEN: [f32; 3] = [0.0, 1.0, 0.0];
+
+        let average = GrayscaleMode::Average.gray(GREEN);
+        let luminosity = GrayscaleMode::Luminosity.gray(GREEN);
+        assert!(luminosity > average);
+        assert!((luminosity - 0.7152).abs() < 1e-4);
+
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([0, 255, 0, 200])));
+        let gray = to_grayscale(&img, GrayscaleMode::Luminosity).to_luma_alpha8();
+        assert_eq!(gray.get_pixel(0, 0).0, [182, 200]);
+    }
+
+    #[test]
+    fn dithering_breaks_bands_into_varied_neighbors() {
+        let ramp = DynamicImage::ImageRgb8(RgbImage::from_fn(256, 16, |x, _| {
+            Rgb([x as u8, x as u8, x as u8])
+        }));
+        let changes = |img: &DynamicImage| {
+            let rgb = img.to_rgb8();
+            rgb.rows()
+                .map(|row| {
+                    let row: Vec<_> = row.collect();
+                    row.windows(2).filter(|pair| pair[0] != pair[1]).count()
+                })
+                .sum::<usize>()
+        };
+
+        let banded = posterize(&ramp, 4, false);
+        let dithered = posterize(&ramp, 4, true);
+
+        // Four flat bands per row, so three steps between them
+        assert_eq!(changes(&banded), 3 * 16);
+        assert!(
+            changes(&dithered) > 10 * changes(&banded),
+            "{} changes dithered",
+            changes(&dithered)
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use anyhow::{bail, Result};
+use image::{DynamicImage, ImageFormat};
+use std::borrow::Cow;
+
+use crate::mask;
+use crate::presets::IncompatibleOutput;
+
+/// Bits per channel and transparency an output format can store.
+struct OutputLimits {
+    bits_per_channel: u16,
+    alpha: bool,
+    gray_alpha: bool,
+}
+
+const fn limits(format: ImageFormat) -> OutputLimits {
+    match format {
+        ImageFormat::Png => OutputLimits {
+            bits_per_channel: 16,
+            alpha: true,
+            gray_alpha: true,
+        },
+        ImageFormat::Tiff => OutputLimits {
+            bits_per_channel: 16,
+            alpha: true,
+            gray_alpha: false,
+        },
+        ImageFormat::Jpeg => OutputLimits {
+            bits_per_channel: 8,
+            alpha: false,
+            gray_alpha: false,
+        },
+        _ => OutputLimits {
+            bits_per_channel: 8,
+            alpha: true,
+            gray_alpha: false,
+        },
+    }
+}
+
+/// Checks that `format` can store `img` and converts it when the policy allows.
+///
+/// Converting reduces deeper sources to the format's bit depth and flattens transparency onto
+/// white where the format has no alpha. Lossless conversions, such as gray with alpha to RGBA,
+/// always happen silently. Animated sources are decided earlier by the `AnimationPolicy`.
+pub fn fit_to_format<'a>(
+    img: &'a DynamicImage,
+    format: ImageFormat,
+    policy: IncompatibleOutput,
+    name: &str,
+    log: &dyn Fn(String),
+) -> Result<Cow<'a, DynamicImage>> {
+    let limits = limits(format);
+    let color = img.color();
+    let mut img = Cow::Borrowed(img);
+
+    let bits = color.bits_per_pixel() / u16::from(color.channel_count());
+    if bits > limits.bits_per_channel {
+        let problem = format!(
+            "{format:?} stores at most {} bits per channel but the image has {bits}",
+            limits.bits_per_channel
+        );
+        match policy {
+            IncompatibleOutput::Fail => bail!("{name}: {problem}"),
+            IncompatibleOutput::Convert => {
+                log(format!(
+                    "{name}: {problem}, reduced to {}",
+                    limits.bits_per_channel
+                ));
+                img = Cow::Owned(with_depth(&img, limits.bits_per_channel));
+            }
+        }
+    }
+
+    if !limits.alpha && color.has_alpha() {
+        if has_transparency(&img) {
+            let problem = format!("{format:?} can't store transparency");
+            match policy {
+                IncompatibleOutput::Fail => bail!("{name}: {problem}"),
+                IncompatibleOutput::Convert => {
+                    log(format!("{name}: {problem}, flattened onto white"));
+                }
+            }
+        }
+        mask::flatten_alpha(img.to_mut());
+    } else if !limits.gray_alpha && color.has_alpha() && !color.has_color() {
+        img = Cow::Owned(if bits > 8 {
+            DynamicImage::ImageRgba16(img.to_rgba16())
+        } else {
+            DynamicImage::ImageRgba8(img.to_rgba8())
+        });
+    }
+
+    Ok(img)
+}
+
+/// Converts to 8 or 16 bits per channel, keeping gray, color and alpha as they are.
+fn with_depth(img: &DynamicImage, bits_per_channel: u16) -> DynamicImage {
+    let color = img.color();
+    match (bits_per_channel > 8, color.has_color(), color.has_alpha()) {
+        (false, false, false) => DynamicImage::ImageLuma8(img.to_luma8()),
+        (false, false, true) => DynamicImage::ImageLumaA8(img.to_luma_alpha8()),
+        (false, true, false) => DynamicImage::ImageRgb8(img.to_rgb8()),
+        (false, true, true) => DynamicImage::ImageRgba8(img.to_rgba8()),
+        (true, false, false) => DynamicImage::ImageLuma16(img.to_luma16()),
+        (true, false, true) => DynamicImage::ImageLumaA16(img.to_luma_alpha16()),
+        (true, true, false) => DynamicImage::ImageRgb16(img.to_rgb16()),
+        (true, true, true) => DynamicImage::ImageRgba16(img.to_rgba16()),
+    }
+}
+
+fn has_transparency(img: &DynamicImage) -> bool {
+    img.to_rgba16().pixels().any(|pixel| pixel[3] < u16::MAX)
+}
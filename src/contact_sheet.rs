@@ -0,0 +1,284 @@
+use anyhow::{Context, Result};
+use image::codecs::jpeg::JpegEncoder;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+const CONTACT_SHEET_FILE_NAME: &str = "contact-sheet.pdf";
+
+/// A4 portrait in PDF points (1/72 inch).
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 36.0;
+const COLUMNS: usize = 4;
+const ROWS: usize = 5;
+const CAPTION_HEIGHT: f32 = 14.0;
+const FONT_SIZE: f32 = 7.0;
+/// Thumbnails are rendered at this many pixels per point, enough to print sharply.
+const PIXELS_PER_POINT: f32 = 2.0;
+const THUMBNAIL_QUALITY: u8 = 85;
+
+/// Pages a sheet of `images` thumbnails takes, an empty sheet still has one page.
+pub const fn page_count(images: usize) -> usize {
+    let per_page = COLUMNS * ROWS;
+    if images == 0 {
+        1
+    } else {
+        images.div_ceil(per_page)
+    }
+}
+
+/// Writes a printable `contact-sheet.pdf` into `output_dir`, a grid of thumbnails captioned
+/// with their file names, each linking to its file.
+pub fn write_contact_sheet(output_dir: &Path, outputs: &[PathBuf]) -> Result<PathBuf> {
+    let cell_width = 2.0_f32.mul_add(-MARGIN, PAGE_WIDTH) / COLUMNS as f32;
+    let cell_height = 2.0_f32.mul_add(-MARGIN, PAGE_HEIGHT) / ROWS as f32;
+    let (image_width, image_height) = (cell_width - 8.0, cell_height - CAPTION_HEIGHT - 8.0);
+
+    let mut pdf = PdfWriter::default();
+    let pages = pdf.add(Vec::new());
+    let catalog = pdf.add(format!("<< /Type /Catalog /Pages {pages} 0 R >>").into_bytes());
+    let font = pdf.add(b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_vec());
+
+    let mut page_ids = Vec::new();
+    let per_page = COLUMNS * ROWS;
+    for page_index in 0..page_count(outputs.len()) {
+        let page_outputs = outputs.iter().skip(page_index * per_page).take(per_page);
+
+        let mut content = String::new();
+        let mut xobjects = String::new();
+        let mut annotations = Vec::new();
+        for (slot, output) in page_outputs.enumerate() {
+            let left = ((slot % COLUMNS) as f32).mul_add(cell_width, MARGIN) + 4.0;
+            let top = ((slot / COLUMNS) as f32).mul_add(-cell_height, PAGE_HEIGHT - MARGIN) - 4.0;
+
+            let relative = output.strip_prefix(output_dir).unwrap_or(output);
+            let name = relative.to_string_lossy();
+            let _ = writeln!(
+                content,
+                "BT /F1 {FONT_SIZE} Tf {left:.2} {:.2} Td ({}) Tj ET",
+                top - cell_height + CAPTION_HEIGHT,
+                escape_text(&fit_caption(&name, image_width)),
+            );
+
+            // A missing or unreadable output still gets its caption
+            let Ok((jpeg, (width, height))) = thumbnail_jpeg(output, image_width, image_height)
+            else {
+                continue;
+            };
+            let scale = (image_width / width as f32).min(image_height / height as f32);
+            let (drawn_width, drawn_height) = (width as f32 * scale, height as f32 * scale);
+            let x = left + (image_width - drawn_width) / 2.0;
+            let y = top - drawn_height;
+
+            let mut object = format!(
+                "<< /Type /XObject /Subtype /Image /Width {width} /Height {height} \
+                 /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+                jpeg.len()
+            )
+            .into_bytes();
+            object.extend_from_slice(&jpeg);
+            object.extend_from_slice(b"\nendstream");
+            let image_id = pdf.add(object);
+
+            let _ = write!(xobjects, "/Im{slot} {image_id} 0 R ");
+            let _ = writeln!(
+                content,
+                "q {drawn_width:.2} 0 0 {drawn_height:.2} {x:.2} {y:.2} cm /Im{slot} Do Q"
+            );
+
+            let href = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let link = format!(
+                "<< /Type /Annot /Subtype /Link /Rect [{x:.2} {y:.2} {:.2} {:.2}] \
+                 /Border [0 0 0] /A << /S /URI /URI ({}) >> >>",
+                x + drawn_width,
+                y + drawn_height,
+                percent_encode(&href)
+            );
+            annotations.push(pdf.add(link.into_bytes()));
+        }
+
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend_from_slice(content.as_bytes());
+        stream.extend_from_slice(b"endstream");
+        let content_id = pdf.add(stream);
+
+        let annotations = annotations
+            .iter()
+            .map(|id| format!("{id} 0 R"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let resources = format!("<< /Font << /F1 {font} 0 R >> /XObject << {xobjects}>> >>");
+        let page = format!(
+            "<< /Type /Page /Parent {pages} 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+             /Resources {resources} /Contents {content_id} 0 R /Annots [{annotations}] >>"
+        );
+        page_ids.push(pdf.add(page.into_bytes()));
+    }
+
+    let kids = page_ids
+        .iter()
+        .map(|id| format!("{id} 0 R"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    pdf.set(
+        pages,
+        format!(
+            "<< /Type /Pages /Kids [{kids}] /Count {} >>",
+            page_ids.len()
+        )
+        .into_bytes(),
+    );
+
+    let sheet_path = output_dir.join(CONTACT_SHEET_FILE_NAME);
+    std::fs::write(&sheet_path, pdf.finish(catalog))
+        .with_context(|| format!("Failed to write contact sheet: {}", sheet_path.display()))?;
+    Ok(sheet_path)
+}
+
+/// Loads an image and encodes a thumbnail fitting `width` by `height` points as JPEG.
+fn thumbnail_jpeg(path: &Path, width: f32, height: f32) -> Result<(Vec<u8>, (u32, u32))> {
+    let thumbnail = image::open(path)?
+        .thumbnail(
+            (width * PIXELS_PER_POINT) as u32,
+            (height * PIXELS_PER_POINT) as u32,
+        )
+        .to_rgb8();
+
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, THUMBNAIL_QUALITY).encode_image(&thumbnail)?;
+    Ok((jpeg, thumbnail.dimensions()))
+}
+
+/// Shortens a caption with an ellipsis so it fits `width` points, assuming Helvetica's
+/// average glyph is about half the font size wide.
+fn fit_caption(caption: &str, width: f32) -> String {
+    let max_chars = (width / (FONT_SIZE * 0.5)) as usize;
+    if caption.chars().count() <= max_chars {
+        caption.to_string()
+    } else {
+        let kept: String = caption.chars().take(max_chars.saturating_sub(3)).collect();
+        format!("{kept}...")
+    }
+}
+
+/// Escapes a PDF literal string, the standard fonts only cover ASCII.
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+/// Encodes everything but unreserved URI characters and `/` as UTF-8 `%XX` escapes.
+fn percent_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(char::from(byte));
+            }
+            _ => {
+                let _ = write!(encoded, "%{byte:02X}");
+            }
+        }
+    }
+    encoded
+}
+
+/// Collects numbered objects and lays them out with a cross-reference table.
+#[derive(Default)]
+struct PdfWriter {
+    objects: Vec<Vec<u8>>,
+}
+
+impl PdfWriter {
+    /// Adds an object and returns its number.
+    fn add(&mut self, body: Vec<u8>) -> usize {
+        self.objects.push(body);
+        self.objects.len()
+    }
+
+    /// Replaces an object added earlier, for ones that refer to objects created after them.
+    fn set(&mut self, id: usize, body: Vec<u8>) {
+        self.objects[id - 1] = body;
+    }
+
+    fn finish(self, root: usize) -> Vec<u8> {
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::with_capacity(self.objects.len());
+        for (index, body) in self.objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            let _ = writeln!(pdf, "{} 0 obj", index + 1);
+            pdf.extend_from_slice(body);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref = pdf.len();
+        let _ = write!(
+            pdf,
+            "xref\n0 {}\n0000000000 65535 f \n",
+            self.objects.len() + 1
+        );
+        for offset in offsets {
+            let _ = writeln!(pdf, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            pdf,
+            "trailer\n<< /Size {} /Root {root} 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            self.objects.len() + 1
+        );
+        pdf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use image::ImageFormat;
+
+    #[test]
+    fn pages_follow_the_grid() {
+        assert_eq!(page_count(0), 1);
+        assert_eq!(page_count(1), 1);
+        assert_eq!(page_count(COLUMNS * ROWS), 1);
+        assert_eq!(page_count(COLUMNS * ROWS + 1), 2);
+        assert_eq!(page_count(2 * COLUMNS * ROWS + 1), 3);
+    }
+
+    #[test]
+    fn sheet_has_a_page_per_grid_of_thumbnails() {
+        let dir = tempfile::tempdir().unwrap();
+        let jpeg = test_util::encode(&test_util::gradient(60, 40), ImageFormat::Jpeg);
+        let outputs: Vec<_> = (0..COLUMNS * ROWS + 3)
+            .map(|i| {
+                let path = dir.path().join(format!("photo{i:02}.jpg"));
+                std::fs::write(&path, &jpeg).unwrap();
+                path
+            })
+            .collect();
+
+        let sheet = write_contact_sheet(dir.path(), &outputs).unwrap();
+
+        let pdf = String::from_utf8_lossy(&std::fs::read(sheet).unwrap()).into_owned();
+        assert!(pdf.starts_with("%PDF-"));
+        assert_eq!(pdf.matches("/Type /Page ").count(), 2);
+        assert!(pdf.contains("/Count 2 >>"));
+        assert_eq!(pdf.matches("/Subtype /Image").count(), outputs.len());
+        assert_eq!(pdf.matches("/URI (photo").count(), outputs.len());
+        assert!(pdf.contains("(photo22.jpg) Tj"));
+    }
+}
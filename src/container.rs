@@ -0,0 +1,203 @@
+use anyhow::{bail, Context, Result};
+use image::{DynamicImage, ImageBuffer, ImageFormat};
+use std::io::Cursor;
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::ColorType;
+
+use crate::presets::MultiImageSelect;
+
+/// Size of the ICO header and of each directory entry after it.
+const ICO_HEADER_LEN: usize = 6;
+const ICO_ENTRY_LEN: usize = 16;
+
+/// Decodes the image `select` picks from a TIFF or ICO holding several.
+/// Returns `None` for other formats and for containers the regular decoder already handles,
+/// such as single-image files and the first page of a TIFF.
+pub fn decode_selected(
+    bytes: &[u8],
+    format: ImageFormat,
+    select: MultiImageSelect,
+) -> Result<Option<DynamicImage>> {
+    match format {
+        ImageFormat::Tiff => decode_tiff_page(bytes, select),
+        ImageFormat::Ico => decode_ico_entry(bytes, select),
+        _ => Ok(None),
+    }
+}
+
+/// Picks an index from the `(width, height)` of every image in the container.
+fn pick(sizes: &[(u32, u32)], select: MultiImageSelect) -> Result<usize> {
+    match select {
+        MultiImageSelect::Primary => Ok(0),
+        MultiImageSelect::Largest => Ok(sizes
+            .iter()
+            .enumerate()
+            // The first of equally large images wins
+            .rev()
+            .max_by_key(|(_, &(width, height))| u64::from(width) * u64::from(height))
+            .map_or(0, |(index, _)| index)),
+        MultiImageSelect::Index(index) => {
+            let index = index as usize;
+            if index >= sizes.len() {
+                bail!(
+                    "Image index {index} requested but the file holds {} images",
+                    sizes.len()
+                );
+            }
+            Ok(index)
+        }
+    }
+}
+
+fn decode_tiff_page(bytes: &[u8], select: MultiImageSelect) -> Result<Option<DynamicImage>> {
+    let mut decoder = Decoder::new(Cursor::new(bytes))?;
+    let mut sizes = vec![decoder.dimensions()?];
+    while decoder.more_images() {
+        decoder.next_image()?;
+        sizes.push(decoder.dimensions()?);
+    }
+
+    let index = pick(&sizes, select)?;
+    if index == 0 {
+        return Ok(None);
+    }
+
+    decoder.seek_to_image(index)?;
+    let (width, height) = decoder.dimensions()?;
+    let color_type = decoder.colortype()?;
+    let image = match (color_type, decoder.read_image()?) {
+        (ColorType::Gray(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma8)
+        }
+        (ColorType::GrayA(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLumaA8)
+        }
+        (ColorType::RGB(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb8)
+        }
+        (ColorType::RGBA(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba8)
+        }
+        (ColorType::Gray(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma16)
+        }
+        (ColorType::GrayA(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLumaA16)
+        }
+        (ColorType::RGB(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb16)
+        }
+        (ColorType::RGBA(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba16)
+        }
+        (color_type, _) => bail!("Unsupported color type {color_type:?} in TIFF page {index}"),
+    };
+    image
+        .with_context(|| format!("TIFF page {index} is shorter than its dimensions"))
+        .map(Some)
+}
+
+/// The image crate decodes the largest icon of an ICO on its own, so the chosen entry is
+/// copied into an ICO of its own first.
+fn decode_ico_entry(bytes: &[u8], select: MultiImageSelect) -> Result<Option<DynamicImage>> {
+    let count = bytes
+        .get(4..ICO_HEADER_LEN)
+        .map(|count| usize::from(u16::from_le_bytes([count[0], count[1]])))
+        .context("ICO header is truncated")?;
+    let entries = (0..count)
+        .map(|index| {
+            let start = ICO_HEADER_LEN + index * ICO_ENTRY_LEN;
+            bytes
+                .get(start..start + ICO_ENTRY_LEN)
+                .context("ICO directory is truncated")
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if entries.len() < 2 {
+        return Ok(None);
+    }
+
+    // A stored size of 0 means 256 pixels
+    let side = |stored: u8| if stored == 0 { 256 } else { u32::from(stored) };
+    let sizes: Vec<_> = entries
+        .iter()
+        .map(|entry| (side(entry[0]), side(entry[1])))
+        .collect();
+    let entry = entries[pick(&sizes, select)?];
+
+    let le_u32 =
+        |at: usize| u32::from_le_bytes([entry[at], entry[at + 1], entry[at + 2], entry[at + 3]]);
+    let (length, offset) = (le_u32(8) as usize, le_u32(12) as usize);
+    let data = bytes
+        .get(offset..offset.saturating_add(length))
+        .context("ICO image data is truncated")?;
+
+    let mut single = Vec::with_capacity(ICO_HEADER_LEN + ICO_ENTRY_LEN + data.len());
+    single.extend_from_slice(&bytes[..4]);
+    single.extend_from_slice(&1_u16.to_le_bytes());
+    single.extend_from_slice(&entry[..12]);
+    single.extend_from_slice(&((ICO_HEADER_LEN + ICO_ENTRY_LEN) as u32).to_le_bytes());
+    single.extend_from_slice(data);
+
+    Ok(Some(image::load_from_memory_with_format(
+        &single,
+        ImageFormat::Ico,
+    )?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::ico::{IcoEncoder, IcoFrame};
+    use image::ExtendedColorType;
+    use tiff::encoder::{colortype, TiffEncoder};
+
+    /// Sizes of the images in each fixture, the largest is never the first.
+    const SIZES: [(u32, u32); 3] = [(8, 8), (32, 16), (16, 16)];
+
+    fn multi_page_tiff() -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        let mut encoder = TiffEncoder::new(&mut bytes).unwrap();
+        for (width, height) in SIZES {
+            let data = vec![128_u8; (width * height * 3) as usize];
+            encoder
+                .write_image::<colortype::RGB8>(width, height, &data)
+                .unwrap();
+        }
+        bytes.into_inner()
+    }
+
+    fn multi_icon_ico() -> Vec<u8> {
+        let frames: Vec<_> = SIZES
+            .iter()
+            .map(|&(width, height)| {
+                let data = vec![128_u8; (width * height * 4) as usize];
+                IcoFrame::as_png(&data, width, height, ExtendedColorType::Rgba8).unwrap()
+            })
+            .collect();
+        let mut bytes = Vec::new();
+        IcoEncoder::new(&mut bytes).encode_images(&frames).unwrap();
+        bytes
+    }
+
+    /// What the resizer ends up with: the selected image, or the regular decoder's.
+    fn selected_size(bytes: &[u8], format: ImageFormat, select: MultiImageSelect) -> (u32, u32) {
+        let img = decode_selected(bytes, format, select)
+            .unwrap()
+            .unwrap_or_else(|| image::load_from_memory_with_format(bytes, format).unwrap());
+        (img.width(), img.height())
+    }
+
+    #[test]
+    fn each_policy_picks_its_image() {
+        for (bytes, format) in [
+            (multi_page_tiff(), ImageFormat::Tiff),
+            (multi_icon_ico(), ImageFormat::Ico),
+        ] {
+            let size = |select| selected_size(&bytes, format, select);
+            assert_eq!(size(MultiImageSelect::Primary), SIZES[0], "{format:?}");
+            assert_eq!(size(MultiImageSelect::Largest), SIZES[1], "{format:?}");
+            assert_eq!(size(MultiImageSelect::Index(2)), SIZES[2], "{format:?}");
+            assert!(decode_selected(&bytes, format, MultiImageSelect::Index(3)).is_err());
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use image::{DynamicImage, GrayImage, Rgba, RgbaImage};
+
+/// Scans are searched for skew up to this many degrees either way.
+const MAX_SKEW_DEGREES: f32 = 5.0;
+/// Smaller corrections aren't visible and only soften the image.
+const MIN_CORRECTION_DEGREES: f32 = 0.05;
+/// Detection runs on a copy this large, the angle doesn't need full resolution.
+const ANALYSIS_SIZE: u32 = 800;
+
+/// Rotates a slightly crooked scan so its lines of text run horizontally.
+/// Returns the straightened image and the angle it was rotated by, or `None` if it was straight.
+pub fn deskew(img: &DynamicImage) -> Option<(DynamicImage, f32)> {
+    let angle = detect_skew(img);
+    if angle.abs() < MIN_CORRECTION_DEGREES {
+        return None;
+    }
+
+    let rotated = rotate(&img.to_rgba8(), angle);
+    let rotated = if img.color().has_alpha() {
+        DynamicImage::ImageRgba8(rotated)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rotated).to_rgb8())
+    };
+    Some((rotated, angle))
+}
+
+/// Angle in degrees of the dominant lines in the image, positive when they fall to the right.
+///
+/// Projects the dark pixels onto the vertical axis at each candidate angle; lines of text
+/// give the sharpest jumps between full and empty rows when the angle matches their skew.
+pub fn detect_skew(img: &DynamicImage) -> f32 {
+    let gray = img.thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE).to_luma8();
+    let points = dark_points(&gray);
+    if points.len() < 50 {
+        return 0.0;
+    }
+
+    let diagonal = f64::from(gray.width())
+        .hypot(f64::from(gray.height()))
+        .ceil() as usize;
+    let score = |degrees: f32| {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let mut rows = vec![0_u64; diagonal * 2 + 1];
+        for &(x, y) in &points {
+            // Offset by the diagonal so every projection lands on a non-negative row
+            let row = y.mul_add(cos, -x * sin) + diagonal as f32;
+            if let Some(count) = rows.get_mut(row as usize) {
+                *count += 1;
+            }
+        }
+        rows.iter().map(|count| count * count).sum::<u64>()
+    };
+
+    // Coarse sweep, then refine around the best coarse angle. Thick lines score the same over
+    // a small range of angles, ties go to the smallest rotation so straight pages stay put.
+    let search = |center: f32, steps: i32, step: f32| {
+        (-steps..=steps)
+            .map(|i| (i as f32).mul_add(step, center))
+            .map(|degrees| (score(degrees), degrees))
+            .max_by(|(a_score, a), (b_score, b)| {
+                a_score.cmp(b_score).then(b.abs().total_cmp(&a.abs()))
+            })
+            .map_or(0.0, |(_, degrees)| degrees)
+    };
+    let coarse = search(0.0, (MAX_SKEW_DEGREES / 0.25) as i32, 0.25);
+    search(coarse, 12, 0.02)
+}
+
+/// Pixels clearly darker than the page, relative to the image center.
+fn dark_points(gray: &GrayImage) -> Vec<(f32, f32)> {
+    let count = u64::from(gray.width()) * u64::from(gray.height());
+    let sum: u64 = gray.pixels().map(|pixel| u64::from(pixel[0])).sum();
+    let threshold = (sum / count.max(1)) as f32 * 0.75;
+
+    let (center_x, center_y) = (gray.width() as f32 / 2.0, gray.height() as f32 / 2.0);
+    gray.enumerate_pixels()
+        .filter(|(_, _, pixel)| f32::from(pixel[0]) < threshold)
+        .map(|(x, y, _)| (x as f32 - center_x, y as f32 - center_y))
+        .collect()
+}
+
+/// Rotates around the center by `degrees`, keeping the size and filling the corners with white.
+fn rotate(img: &RgbaImage, degrees: f32) -> RgbaImage {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (center_x, center_y) = (img.width() as f32 / 2.0, img.height() as f32 / 2.0);
+
+    RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+        let (dx, dy) = (x as f32 + 0.5 - center_x, y as f32 + 0.5 - center_y);
+        let source_x = dx.mul_add(cos, -dy * sin) + center_x - 0.5;
+        let source_y = dx.mul_add(sin, dy * cos) + center_y - 0.5;
+        sample_bilinear(img, source_x, source_y)
+    })
+}
+
+fn sample_bilinear(img: &RgbaImage, x: f32, y: f32) -> Rgba<u8> {
+    const BACKGROUND: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    let (left, top) = (x.floor(), y.floor());
+    let (fx, fy) = (x - left, y - top);
+    let pixel = |px: f32, py: f32| {
+        if px < 0.0 || py < 0.0 {
+            return BACKGROUND;
+        }
+        *img.get_pixel_checked(px as u32, py as u32)
+            .unwrap_or(&BACKGROUND)
+    };
+
+    let corners = [
+        (pixel(left, top), (1.0 - fx) * (1.0 - fy)),
+        (pixel(left + 1.0, top), fx * (1.0 - fy)),
+        (pixel(left, top + 1.0), (1.0 - fx) * fy),
+        (pixel(left + 1.0, top + 1.0), fx * fy),
+    ];
+
+    let mut result = [0.0_f32; 4];
+    for (corner, weight) in corners {
+        for (channel, value) in result.iter_mut().zip(corner.0) {
+            *channel += f32::from(value) * weight;
+        }
+    }
+    Rgba(result.map(|channel| channel.round() as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A white page with rows of dark dashes, like lines of text.
+    fn page() -> RgbaImage {
+        RgbaImage::from_fn(600, 400, |x, y| {
+            let in_line = y > 40 && y < 360 && y % 24 < 6;
+            let in_word = x > 40 && x < 560 && x % 50 < 38;
+            if in_line && in_word {
+                Rgba([20, 20, 20, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn three_degree_scan_is_straightened() {
+        let crooked = DynamicImage::ImageRgba8(rotate(&page(), 3.0));
+        let detected = detect_skew(&crooked);
+        assert!((detected.abs() - 3.0).abs() < 0.2, "detected {detected}°");
+
+        let (straightened, angle) = deskew(&crooked).unwrap();
+        assert!((angle - detected).abs() < f32::EPSILON);
+        let remaining = detect_skew(&straightened);
+        assert!(remaining.abs() < 0.2, "still skewed by {remaining}°");
+    }
+
+    #[test]
+    fn straight_page_is_left_alone() {
+        assert!(deskew(&DynamicImage::ImageRgba8(page())).is_none());
+    }
+}
@@ -0,0 +1,235 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::resizer::ImageResizer;
+
+/// Files operating systems leave in folders, never worth resizing.
+const JUNK_FILE_NAMES: [&str; 3] = ["Thumbs.db", "desktop.ini", ".DS_Store"];
+
+/// Supported images directly inside `dir`, in file name order.
+pub fn discover_images(
+    dir: &Path,
+    include_hidden: bool,
+    log: &dyn Fn(String),
+) -> Result<Vec<PathBuf>> {
+    discover_images_recursive(dir, Some(0), include_hidden, log)
+}
+
+/// Supported images inside `dir` and its subfolders, in path order. Folders more than
+/// `max_depth` levels below `dir` are skipped, and so are hidden files and folders and
+/// system junk unless `include_hidden` is set.
+///
+/// Linked folders are not followed, a link back up the tree would never end. Only `dir`
+/// itself has to be readable, subfolders and entries that can't be read are logged and
+/// skipped.
+pub fn discover_images_recursive(
+    dir: &Path,
+    max_depth: Option<usize>,
+    include_hidden: bool,
+    log: &dyn Fn(String),
+) -> Result<Vec<PathBuf>> {
+    let mut images = Vec::new();
+    let mut pending = vec![(dir.to_path_buf(), 0)];
+    while let Some((folder, depth)) = pending.pop() {
+        let entries = match std::fs::read_dir(&folder) {
+            Ok(entries) => entries,
+            Err(e) if folder != dir => {
+                log(format!(
+                    "Skipped unreadable folder {}: {e}",
+                    folder.display()
+                ));
+                continue;
+            }
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read folder: {}", folder.display()))
+            }
+        };
+
+        for entry in entries {
+            let (path, file_type) = match entry.and_then(|entry| {
+                let file_type = entry.file_type()?;
+                Ok((entry.path(), file_type))
+            }) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    log(format!(
+                        "Skipped unreadable entry in {}: {e}",
+                        folder.display()
+                    ));
+                    continue;
+                }
+            };
+            if !include_hidden && (is_hidden(&path) || is_junk(&path)) {
+                continue;
+            }
+            if file_type.is_dir() {
+                if max_depth.is_none_or(|max| depth < max) {
+                    pending.push((path, depth + 1));
+                }
+                continue;
+            }
+            // Linked files are fine, `is_file` follows the link, linked folders are not
+            if has_supported_extension(&path) && path.is_file() {
+                images.push(path);
+            } else if file_type.is_symlink() && path.is_dir() {
+                log(format!("Skipped linked folder {}", path.display()));
+            }
+        }
+    }
+
+    images.sort();
+    Ok(images)
+}
+
+/// Whether `path` ends in an extension the resizer reads, in any case.
+pub fn has_supported_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ImageResizer::get_supported_extensions().contains(&ext.to_lowercase().as_str())
+        })
+}
+
+/// Names starting with `.`, and on Windows also files with the hidden or system attribute.
+fn is_hidden(path: &Path) -> bool {
+    if path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+    {
+        return true;
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+        if std::fs::metadata(path).is_ok_and(|metadata| {
+            metadata.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0
+        }) {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn is_junk(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| {
+        JUNK_FILE_NAMES
+            .iter()
+            .any(|junk| name.eq_ignore_ascii_case(junk))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::Mutex;
+
+    #[cfg(unix)]
+    #[test]
+    fn linked_folders_are_not_followed() {
+        let root = tempfile::tempdir().unwrap();
+        let album = root.path().join("album");
+        fs::create_dir(&album).unwrap();
+        fs::write(album.join("a.jpg"), b"").unwrap();
+        // A loop back to the root, and a link to a folder elsewhere
+        std::os::unix::fs::symlink(root.path(), album.join("loop")).unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        fs::write(elsewhere.path().join("b.jpg"), b"").unwrap();
+        std::os::unix::fs::symlink(elsewhere.path(), root.path().join("linked")).unwrap();
+        std::os::unix::fs::symlink(album.join("a.jpg"), root.path().join("c.jpg")).unwrap();
+
+        let logged = Mutex::new(Vec::new());
+        let images = discover_images_recursive(root.path(), None, false, &|message| {
+            logged.lock().unwrap().push(message);
+        })
+        .unwrap();
+
+        assert_eq!(images, [album.join("a.jpg"), root.path().join("c.jpg")]);
+        assert_eq!(logged.into_inner().unwrap().len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_subfolders_are_logged_and_skipped() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let locked = root.path().join("locked");
+        fs::create_dir(&locked).unwrap();
+        fs::write(locked.join("hidden.jpg"), b"").unwrap();
+        fs::write(root.path().join("a.png"), b"").unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        // Permissions don't stop root, there is nothing unreadable to test then
+        let readable = fs::read_dir(&locked).is_ok();
+
+        let logged = Mutex::new(Vec::new());
+        let images = discover_images_recursive(root.path(), None, false, &|message| {
+            logged.lock().unwrap().push(message);
+        });
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let images = images.unwrap();
+        if readable {
+            assert_eq!(images.len(), 2);
+        } else {
+            assert_eq!(images, [root.path().join("a.png")]);
+            let logged = logged.into_inner().unwrap();
+            assert_eq!(logged.len(), 1);
+            assert!(
+                logged[0].starts_with("Skipped unreadable folder"),
+                "{logged:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn depth_limit_and_hidden_entries() {
+        let root = tempfile::tempdir().unwrap();
+        let deep = root.path().join("one").join("two");
+        fs::create_dir_all(&deep).unwrap();
+        fs::create_dir(root.path().join(".cache")).unwrap();
+        for file in ["top.jpg", "notes.txt", "one/mid.png", "one/two/deep.gif"] {
+            fs::write(root.path().join(file), b"").unwrap();
+        }
+        fs::write(root.path().join(".cache").join("thumb.jpg"), b"").unwrap();
+        let discover = |max_depth, include_hidden| {
+            discover_images_recursive(root.path(), max_depth, include_hidden, &|_| {}).unwrap()
+        };
+
+        assert_eq!(discover(Some(0), false), [root.path().join("top.jpg")]);
+        assert_eq!(discover(Some(1), false).len(), 2);
+        assert_eq!(discover(None, false).len(), 3);
+        assert_eq!(discover(None, true).len(), 4);
+    }
+
+    #[test]
+    fn system_junk_is_left_out() {
+        let root = tempfile::tempdir().unwrap();
+        for file in ["photo.jpg", ".DS_Store", "THUMBS.DB", "._photo.jpg"] {
+            fs::write(root.path().join(file), b"").unwrap();
+        }
+
+        assert_eq!(
+            discover_images(root.path(), false, &|_| {}).unwrap(),
+            [root.path().join("photo.jpg")]
+        );
+        // The resource fork copy has an image name, so it only comes with hidden files
+        assert_eq!(
+            discover_images(root.path(), true, &|_| {}).unwrap(),
+            [
+                root.path().join("._photo.jpg"),
+                root.path().join("photo.jpg")
+            ]
+        );
+        for junk in [".DS_Store", "thumbs.db", "Desktop.ini"] {
+            assert!(is_junk(Path::new(junk)), "{junk}");
+        }
+        assert!(!is_junk(Path::new("photo.jpg")));
+    }
+}
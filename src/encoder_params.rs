@@ -0,0 +1,185 @@
+use anyhow::{bail, Context, Result};
+use image::codecs::png;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Keys understood by `parse`, each prefixed with the format it applies to.
+pub const KNOWN_KEYS: [&str; 4] = [
+    "jpeg.quality",
+    "jpeg.restart_interval",
+    "png.compression",
+    "png.filter",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PngCompression {
+    Fast,
+    Default,
+    Best,
+}
+
+impl From<PngCompression> for png::CompressionType {
+    fn from(compression: PngCompression) -> Self {
+        match compression {
+            PngCompression::Fast => Self::Fast,
+            PngCompression::Default => Self::Default,
+            PngCompression::Best => Self::Best,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PngFilter {
+    None,
+    Sub,
+    Up,
+    Avg,
+    Paeth,
+    Adaptive,
+}
+
+impl From<PngFilter> for png::FilterType {
+    fn from(filter: PngFilter) -> Self {
+        match filter {
+            PngFilter::None => Self::NoFilter,
+            PngFilter::Sub => Self::Sub,
+            PngFilter::Up => Self::Up,
+            PngFilter::Avg => Self::Avg,
+            PngFilter::Paeth => Self::Paeth,
+            PngFilter::Adaptive => Self::Adaptive,
+        }
+    }
+}
+
+/// Encoder settings taken from raw `key=value` parameters, `None` keeps the usual behavior.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncoderParams {
+    /// Replaces the preset's JPEG quality, a size budget or SSIM target still wins.
+    pub jpeg_quality: Option<u8>,
+    /// Replaces the preset's restart interval.
+    pub jpeg_restart_interval: Option<u16>,
+    pub png_compression: Option<PngCompression>,
+    pub png_filter: Option<PngFilter>,
+}
+
+impl EncoderParams {
+    pub const NONE: Self = Self {
+        jpeg_quality: None,
+        jpeg_restart_interval: None,
+        png_compression: None,
+        png_filter: None,
+    };
+}
+
+/// Validates raw encoder parameters. Unknown keys are logged and ignored, a known key with a
+/// value out of range is an error.
+pub fn parse(params: &HashMap<String, String>, log: &dyn Fn(String)) -> Result<EncoderParams> {
+    let mut parsed = EncoderParams::NONE;
+
+    // Sorted so warnings come out in the same order every time
+    let mut entries: Vec<_> = params.iter().collect();
+    entries.sort();
+
+    for (key, value) in entries {
+        let value = value.trim();
+        match key.trim() {
+            "jpeg.quality" => {
+                let quality = value
+                    .parse()
+                    .ok()
+                    .filter(|quality| (1..=100).contains(quality))
+                    .with_context(|| format!("jpeg.quality must be 1 to 100, got {value:?}"))?;
+                parsed.jpeg_quality = Some(quality);
+            }
+            "jpeg.restart_interval" => {
+                let interval = value
+                    .parse()
+                    .ok()
+                    .filter(|&interval| interval > 0)
+                    .with_context(|| {
+                        format!("jpeg.restart_interval must be 1 to 65535, got {value:?}")
+                    })?;
+                parsed.jpeg_restart_interval = Some(interval);
+            }
+            "png.compression" => {
+                parsed.png_compression = Some(match value {
+                    "fast" => PngCompression::Fast,
+                    "default" => PngCompression::Default,
+                    "best" => PngCompression::Best,
+                    _ => bail!("png.compression must be fast, default or best, got {value:?}"),
+                });
+            }
+            "png.filter" => {
+                parsed.png_filter = Some(match value {
+                    "none" => PngFilter::None,
+                    "sub" => PngFilter::Sub,
+                    "up" => PngFilter::Up,
+                    "avg" => PngFilter::Avg,
+                    "paeth" => PngFilter::Paeth,
+                    "adaptive" => PngFilter::Adaptive,
+                    _ => bail!(
+                        "png.filter must be none, sub, up, avg, paeth or adaptive, got {value:?}"
+                    ),
+                });
+            }
+            unknown => log(format!(
+                "Unknown encoder parameter {unknown:?} ignored, known ones are {}",
+                KNOWN_KEYS.join(", ")
+            )),
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Reads one `key=value` per line, skipping blank lines and `#` comments.
+pub fn parse_lines(text: &str) -> Result<HashMap<String, String>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (key, value) = line
+                .split_once('=')
+                .with_context(|| format!("Expected key=value, got {line:?}"))?;
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn unknown_keys_only_warn() {
+        let params = parse_lines("# tuning\njpeg.quality = 70\n\njpeg.turbo=yes\n").unwrap();
+        let logged = Mutex::new(Vec::new());
+
+        let parsed = parse(&params, &|message| logged.lock().unwrap().push(message)).unwrap();
+
+        assert_eq!(
+            parsed,
+            EncoderParams {
+                jpeg_quality: Some(70),
+                ..EncoderParams::NONE
+            }
+        );
+        let logged = logged.into_inner().unwrap();
+        assert_eq!(logged.len(), 1);
+        assert!(
+            logged[0].starts_with("Unknown encoder parameter \"jpeg.turbo\" ignored"),
+            "{}",
+            logged[0]
+        );
+    }
+
+    #[test]
+    fn out_of_range_values_fail() {
+        for line in ["jpeg.quality=0", "jpeg.quality=101", "png.filter=fancy"] {
+            let params = parse_lines(line).unwrap();
+            assert!(parse(&params, &|_| {}).is_err(), "{line}");
+        }
+        assert!(parse_lines("jpeg.quality").is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const RESPONSE_HEAD: &str = "HTTP/1.1 200 OK\r\n\
+    Content-Type: text/event-stream\r\n\
+    Cache-Control: no-cache\r\n\
+    Connection: keep-alive\r\n\
+    Access-Control-Allow-Origin: *\r\n\r\n";
+
+/// Broadcasts processing events as Server-Sent Events to every connected client.
+pub struct EventServer {
+    port: u16,
+    clients: Arc<Mutex<Vec<TcpStream>>>,
+}
+
+impl EventServer {
+    /// Listens on `port` of the loopback interface, 0 picks a free port.
+    pub fn start(port: u16) -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .with_context(|| format!("Failed to bind event server to port {port}"))?;
+        let port = listener.local_addr()?.port();
+        let clients = Arc::new(Mutex::new(Vec::new()));
+
+        let accepted = Arc::clone(&clients);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Ok(stream) = Self::accept_client(stream) {
+                    if let Ok(mut clients) = accepted.lock() {
+                        clients.push(stream);
+                    }
+                }
+            }
+        });
+
+        Ok(Self { port, clients })
+    }
+
+    pub const fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Reads the request head (any path is accepted) and answers with an event stream.
+    fn accept_client(mut stream: TcpStream) -> std::io::Result<TcpStream> {
+        stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+                break;
+            }
+        }
+
+        stream.write_all(RESPONSE_HEAD.as_bytes())?;
+        stream.flush()?;
+        Ok(stream)
+    }
+
+    pub fn broadcast<T: Serialize>(&self, event: &T) {
+        let Ok(json) = serde_json::to_string(event) else {
+            return;
+        };
+        let payload = format!("data: {json}\n\n");
+
+        if let Ok(mut clients) = self.clients.lock() {
+            // Clients that hung up are dropped on the first failed write
+            clients.retain_mut(|client| {
+                client
+                    .write_all(payload.as_bytes())
+                    .and_then(|()| client.flush())
+                    .is_ok()
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::naming::Naming;
+    use crate::presets::ResizePreset;
+    use crate::resizer::ImageResizer;
+    use crate::sink::MemorySink;
+    use crate::test_util;
+    use image::ImageFormat;
+    use serde_json::{json, Value};
+    use std::io::Read;
+    use std::ops::ControlFlow;
+    use std::path::{Path, PathBuf};
+    use std::time::Instant;
+
+    struct Input(PathBuf, Vec<u8>);
+
+    impl crate::source::InputSource for Input {
+        fn identifier(&self) -> &Path {
+            &self.0
+        }
+
+        fn read_bytes(&self) -> Result<crate::source::InputBytes> {
+            Ok(self.1.clone().into())
+        }
+    }
+
+    #[test]
+    fn client_receives_progress_and_completion() {
+        let server = EventServer::start(0).unwrap();
+        let mut client = TcpStream::connect(("127.0.0.1", server.port())).unwrap();
+        client
+            .write_all(b"GET /events HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let started = Instant::now();
+        while server.clients.lock().unwrap().is_empty() {
+            assert!(
+                started.elapsed() < Duration::from_secs(5),
+                "client not accepted"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let bytes = test_util::encode(&test_util::gradient(16, 16), ImageFormat::Png);
+        let inputs = [
+            Input(PathBuf::from("a.png"), bytes.clone()),
+            Input(PathBuf::from("b.png"), bytes),
+        ];
+        let results = ImageResizer::batch_resize(
+            &inputs,
+            Path::new("out"),
+            &ResizePreset {
+                width: 8,
+                height: 8,
+                max_parallel_images: Some(1),
+                ..ResizePreset::BASE
+            },
+            &Naming::default(),
+            &MemorySink::default(),
+            |current, total| {
+                server.broadcast(&json!({
+                    "event": "processing",
+                    "data": { "current": current, "total": total },
+                }));
+                ControlFlow::Continue(())
+            },
+            |_| {},
+        )
+        .unwrap();
+        server.broadcast(&json!({
+            "event": "completed",
+            "data": { "successful": results.len(), "skipped": 0, "failed": 0 },
+        }));
+        drop(server);
+
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut response = String::new();
+        let mut buffer = [0; 4096];
+        while !response.contains("\"completed\"") {
+            let read = client.read(&mut buffer).unwrap();
+            assert!(read > 0, "stream ended early: {response}");
+            response.push_str(&String::from_utf8_lossy(&buffer[..read]));
+        }
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert!(head.contains("Content-Type: text/event-stream"));
+        let events: Vec<Value> = body
+            .split("\n\n")
+            .filter_map(|event| event.strip_prefix("data: "))
+            .map(|json| serde_json::from_str(json).unwrap())
+            .collect();
+        let progress: Vec<_> = events
+            .iter()
+            .filter(|event| event["event"] == "processing")
+            .map(|event| event["data"]["current"].as_u64().unwrap())
+            .collect();
+        assert_eq!(progress.first(), Some(&0));
+        assert_eq!(progress.last(), Some(&2));
+        assert!(progress.is_sorted());
+        assert_eq!(events.last().unwrap()["data"]["successful"], 2);
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+const GALLERY_FILE_NAME: &str = "index.html";
+
+const HEADER: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Resized images</title>
+<style>
+body { font-family: sans-serif; margin: 2em; }
+.gallery { display: flex; flex-wrap: wrap; gap: 1em; }
+figure { margin: 0; width: 220px; }
+figure img { max-width: 220px; max-height: 220px; }
+figcaption { font-size: 0.85em; word-break: break-all; }
+</style>
+</head>
+<body>
+<div class="gallery">
+"#;
+
+const FOOTER: &str = "</div>\n</body>\n</html>\n";
+
+/// Writes an `index.html` thumbnail gallery linking every output into `output_dir`.
+pub fn write_gallery(output_dir: &Path, outputs: &[PathBuf]) -> Result<PathBuf> {
+    let mut html = String::from(HEADER);
+
+    for output in outputs {
+        // Links are relative so the folder can be uploaded as-is
+        let relative = output.strip_prefix(output_dir).unwrap_or(output);
+        let href = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let name = relative.to_string_lossy();
+
+        let caption = match image::image_dimensions(output) {
+            Ok((width, height)) => format!("{name} ({width}x{height})"),
+            Err(_) => name.to_string(),
+        };
+
+        let href = escape_html(&href);
+        let _ = writeln!(
+            html,
+            "<figure><a href=\"{href}\"><img src=\"{href}\" alt=\"{caption}\" loading=\"lazy\"></a>\
+             <figcaption>{caption}</figcaption></figure>",
+            caption = escape_html(&caption),
+        );
+    }
+
+    html.push_str(FOOTER);
+
+    let gallery_path = output_dir.join(GALLERY_FILE_NAME);
+    std::fs::write(&gallery_path, html)
+        .with_context(|| format!("Failed to write gallery: {}", gallery_path.display()))?;
+    Ok(gallery_path)
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use image::ImageFormat;
+
+    /// Tag names in the order they open and close, with void elements left out.
+    fn unbalanced_tags(html: &str) -> Vec<String> {
+        let mut open: Vec<String> = Vec::new();
+        for tag in html.split('<').skip(1) {
+            let tag = &tag[..tag.find('>').expect("tag is closed")];
+            let name: String = tag
+                .trim_start_matches('/')
+                .chars()
+                .take_while(char::is_ascii_alphanumeric)
+                .collect();
+            if tag.starts_with('!') || matches!(name.as_str(), "meta" | "img") {
+                continue;
+            }
+            if tag.starts_with('/') {
+                assert_eq!(open.pop().as_deref(), Some(name.as_str()), "in {html}");
+            } else {
+                open.push(name);
+            }
+        }
+        open
+    }
+
+    #[test]
+    fn gallery_references_every_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let outputs: Vec<PathBuf> = ["a.png", "sub/b & c.png"]
+            .iter()
+            .map(|name| {
+                let path = dir.path().join(name);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                let img = test_util::gradient(40, 30);
+                std::fs::write(&path, test_util::encode(&img, ImageFormat::Png)).unwrap();
+                path
+            })
+            .collect();
+
+        let html = std::fs::read_to_string(write_gallery(dir.path(), &outputs).unwrap()).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(unbalanced_tags(&html).is_empty());
+        assert!(html.contains(r#"<a href="a.png">"#));
+        assert!(html.contains(r#"<a href="sub/b &amp; c.png">"#));
+        assert!(html.contains("a.png (40x30)"));
+        assert_eq!(html.matches("<figure>").count(), 2);
+    }
+}
@@ -0,0 +1,338 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use crate::presets::{NormalizedCrop, OutputFormat, ResizeMode, ResizePreset};
+use crate::resizer::ImageResizer;
+use crate::run_log::RunLog;
+use crate::sink::FileSystemSink;
+use crate::source::{FileSource, InputSource};
+
+/// A single planned resize operation, as written to a JSON job file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    /// Read by files without a `mode`, which were written before Fill existed.
+    pub maintain_aspect_ratio: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<ResizeMode>,
+    /// Scales the input by this percentage instead of to `width` by `height`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale_percent: Option<u32>,
+    pub output_format: OutputFormat,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crop: Option<NormalizedCrop>,
+    /// Every other setting of the preset the job was planned with, so running it writes
+    /// what the plan named. Hand-written jobs without it use the defaults.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<ResizePreset>,
+}
+
+impl Job {
+    /// The preset this job resizes with, the job's own fields take precedence over its
+    /// stored settings.
+    pub const fn preset(&self) -> ResizePreset {
+        let settings = match self.settings {
+            Some(settings) => settings,
+            None => ResizePreset::BASE,
+        };
+        ResizePreset {
+            width: self.width,
+            height: self.height,
+            mode: match self.mode {
+                Some(mode) => mode,
+                None if self.maintain_aspect_ratio => ResizeMode::Fit,
+                None => ResizeMode::Stretch,
+            },
+            scale_percent: self.scale_percent,
+            output_format: self.output_format,
+            crop: self.crop,
+            ..settings
+        }
+    }
+
+    /// Resizes this job's input into its own output path.
+    /// The output may only replace the input when `force` is set.
+    pub fn run(&self, force: bool, log: &dyn Fn(String)) -> Result<Vec<PathBuf>> {
+        ImageResizer::ensure_not_input(&self.input, &self.output, force)?;
+
+        let input = FileSource::from(self.input.clone());
+        let bytes = input.read_bytes()?;
+        ImageResizer::resize_image(
+            input.identifier(),
+            &bytes,
+            &self.output,
+            &self.preset(),
+            &FileSystemSink,
+            log,
+        )
+    }
+}
+
+pub fn load_job_file(path: &Path) -> Result<Vec<Job>> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open job file: {}", path.display()))?;
+    serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("Invalid job file: {}", path.display()))
+}
+
+/// Runs every job in the file, cropped to `crop` when given, recording one result line per job
+/// in `run_log`.
+/// Returns whether all of them succeeded.
+pub fn run_job_file(
+    path: &Path,
+    force: bool,
+    crop: Option<NormalizedCrop>,
+    run_log: &RunLog,
+) -> Result<bool> {
+    let mut jobs = load_job_file(path)?;
+    // Replaces whatever crop the jobs were planned with
+    if let Some(crop) = crop {
+        for job in &mut jobs {
+            job.crop = Some(crop);
+        }
+    }
+    let log = |message: String| run_log.message(&message);
+
+    let mut failed = 0;
+    for (index, job) in jobs.iter().enumerate() {
+        match job.run(force, &log) {
+            Ok(outputs) => {
+                for output in outputs {
+                    run_log.result(&format!(
+                        "[{}/{}] ok {} -> {}",
+                        index + 1,
+                        jobs.len(),
+                        job.input.display(),
+                        output.display()
+                    ));
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                run_log.result(&format!(
+                    "[{}/{}] failed {}: {e:#}",
+                    index + 1,
+                    jobs.len(),
+                    job.input.display()
+                ));
+            }
+        }
+    }
+
+    run_log.result(&format!(
+        "{} succeeded, {failed} failed",
+        jobs.len() - failed
+    ));
+    Ok(failed == 0)
+}
+
+pub fn write_job_file(path: &Path, jobs: &[Job]) -> Result<()> {
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create job file: {}", path.display()))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), jobs)
+        .with_context(|| format!("Failed to write job file: {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::naming::Naming;
+    use crate::presets::{GrayscaleMode, ResampleFilter};
+    use crate::test_util;
+    use image::ImageFormat;
+
+    #[test]
+    fn running_a_saved_plan_matches_the_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        // One source larger than the target and one smaller, which prevent_upscale keeps
+        let inputs: Vec<PathBuf> = [("a.png", (64, 48)), ("b.jpg", (30, 20))]
+            .iter()
+            .map(|&(name, (width, height))| {
+                let path = dir.path().join(name);
+                let format = ImageFormat::from_path(&path).unwrap();
+                std::fs::write(
+                    &path,
+                    test_util::encode(&test_util::photo(width, height), format),
+                )
+                .unwrap();
+                path
+            })
+            .collect();
+        let preset = ResizePreset {
+            width: 40,
+            height: 40,
+            output_format: OutputFormat::Png,
+            filter: ResampleFilter::Nearest,
+            prevent_upscale: true,
+            pad_to_target: true,
+            background: [20, 40, 60, 255],
+            grayscale: Some(GrayscaleMode::Average),
+            crop: Some("0.1,0.1,0.8,0.8".parse().unwrap()),
+            ..ResizePreset::BASE
+        };
+        let naming = Naming::default();
+
+        let plan =
+            ImageResizer::plan_batch(&inputs, &dir.path().join("plan"), &preset, &naming).unwrap();
+        let job_path = dir.path().join("plan.json");
+        write_job_file(&job_path, &plan).unwrap();
+        let loaded = load_job_file(&job_path).unwrap();
+        assert_eq!(loaded, plan);
+        for job in &loaded {
+            job.run(false, &|_| {}).unwrap();
+        }
+
+        let sources: Vec<FileSource> = inputs.iter().cloned().map(FileSource::from).collect();
+        let batch = ImageResizer::batch_resize(
+            &sources,
+            &dir.path().join("batch"),
+            &preset,
+            &naming,
+            &FileSystemSink,
+            |_, _| std::ops::ControlFlow::Continue(()),
+            |_| {},
+        )
+        .unwrap();
+
+        assert_eq!(batch.len(), loaded.len());
+        for (job, result) in loaded.iter().zip(batch) {
+            let written = result.unwrap();
+            assert_eq!(written.len(), 1);
+            assert_eq!(written[0].path.file_name(), job.output.file_name());
+            let planned = std::fs::read(&job.output).unwrap();
+            assert_eq!(planned, std::fs::read(&written[0].path).unwrap());
+            assert_eq!(test_util::dimensions(&planned), (40, 40));
+        }
+    }
+
+    #[test]
+    fn each_job_uses_its_own_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("photo.png");
+        std::fs::write(
+            &input,
+            test_util::encode(&test_util::gradient(200, 100), ImageFormat::Png),
+        )
+        .unwrap();
+        let spec = serde_json::json!([
+            {
+                "input": input,
+                "output": dir.path().join("small.jpg"),
+                "width": 50,
+                "height": 50,
+                "maintain_aspect_ratio": true,
+                "output_format": "Jpeg",
+            },
+            {
+                "input": input,
+                "output": dir.path().join("stretched.bmp"),
+                "width": 30,
+                "height": 40,
+                "maintain_aspect_ratio": false,
+                "output_format": "Bmp",
+            },
+        ]);
+        let spec_path = dir.path().join("jobs.json");
+        std::fs::write(&spec_path, spec.to_string()).unwrap();
+
+        let jobs = load_job_file(&spec_path).unwrap();
+        assert_eq!(jobs.len(), 2);
+        for job in &jobs {
+            let written = job.run(false, &|_| {}).unwrap();
+            assert_eq!(written, std::slice::from_ref(&job.output));
+        }
+
+        let small = std::fs::read(dir.path().join("small.jpg")).unwrap();
+        assert_eq!(image::guess_format(&small).unwrap(), ImageFormat::Jpeg);
+        assert_eq!(test_util::dimensions(&small), (50, 25));
+        let stretched = std::fs::read(dir.path().join("stretched.bmp")).unwrap();
+        assert_eq!(image::guess_format(&stretched).unwrap(), ImageFormat::Bmp);
+        assert_eq!(test_util::dimensions(&stretched), (30, 40));
+    }
+
+    #[test]
+    fn headless_run_logs_each_file_and_a_summary() {
+        let dir = tempfile::tempdir().unwrap();
+        let jobs: Vec<Job> = ["a", "b", "c"]
+            .iter()
+            .map(|name| {
+                let input = dir.path().join(format!("{name}.png"));
+                std::fs::write(
+                    &input,
+                    test_util::encode(&test_util::gradient(40, 30), ImageFormat::Png),
+                )
+                .unwrap();
+                Job {
+                    input,
+                    output: dir.path().join(format!("{name}_small.png")),
+                    width: 20,
+                    height: 20,
+                    maintain_aspect_ratio: true,
+                    mode: None,
+                    scale_percent: None,
+                    output_format: OutputFormat::Png,
+                    crop: None,
+                    settings: None,
+                }
+            })
+            .collect();
+        let job_path = dir.path().join("jobs.json");
+        write_job_file(&job_path, &jobs).unwrap();
+        let log_path = dir.path().join("run.log");
+
+        let run_log = RunLog::open(&log_path, false).unwrap();
+        assert!(run_job_file(&job_path, false, None, &run_log).unwrap());
+        drop(run_log);
+
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 4, "{log}");
+        for (line, name) in lines.iter().zip(["a", "b", "c"]) {
+            assert!(line.contains(" ok "), "{line}");
+            assert!(line.contains(&format!("{name}_small.png")), "{line}");
+        }
+        assert!(lines[3].ends_with("3 succeeded, 0 failed"), "{}", lines[3]);
+    }
+
+    #[test]
+    fn crop_override_applies_to_every_job() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = test_util::photo(100, 100);
+        let input = dir.path().join("photo.png");
+        std::fs::write(&input, test_util::encode(&source, ImageFormat::Png)).unwrap();
+        let jobs: Vec<Job> = ["first", "second"]
+            .iter()
+            .map(|name| Job {
+                input: input.clone(),
+                output: dir.path().join(format!("{name}.png")),
+                width: 50,
+                height: 50,
+                maintain_aspect_ratio: true,
+                mode: None,
+                scale_percent: None,
+                output_format: OutputFormat::Png,
+                crop: None,
+                settings: None,
+            })
+            .collect();
+        let job_path = dir.path().join("jobs.json");
+        write_job_file(&job_path, &jobs).unwrap();
+        let crop = "0.25,0.25,0.5,0.5".parse().unwrap();
+
+        assert!(run_job_file(&job_path, false, Some(crop), &RunLog::console()).unwrap());
+
+        // The 50x50 crop already has the target size, so it is written unscaled
+        let expected = source.crop_imm(25, 25, 50, 50).to_rgb8();
+        for job in &jobs {
+            let output = image::open(&job.output).unwrap().to_rgb8();
+            assert_eq!(output, expected, "{}", job.output.display());
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use anyhow::{ensure, Result};
+use image::{DynamicImage, Rgba, Rgba32FImage};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+use crate::color;
+
+/// Widest support accepted, in source pixels at 1:1. Wider kernels blur more than they
+/// resample and cost a pass over that many pixels per output pixel and side.
+pub const MAX_SUPPORT: f32 = 8.0;
+/// Narrowest support accepted, below this some output pixels would miss every source pixel.
+pub const MIN_SUPPORT: f32 = 0.5;
+
+/// A resampling kernel with user-chosen parameters, applied separably by `resize`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Kernel {
+    /// Linear falloff to zero at `radius`, the Triangle filter at radius 1.
+    Triangle { radius: f32 },
+    /// Gaussian cut off at three `sigma`.
+    Gaussian { sigma: f32 },
+    /// Windowed sinc with `lobes` lobes either side, Lanczos3 at 3.
+    Lanczos { lobes: f32 },
+    /// Mitchell-Netravali cubic, Catmull-Rom at B 0 and C 0.5.
+    Cubic { b: f32, c: f32 },
+}
+
+impl Kernel {
+    /// One of each kind with its usual parameters.
+    pub const ALL: [Self; 4] = [
+        Self::Triangle { radius: 1.0 },
+        Self::Gaussian { sigma: 0.5 },
+        Self::Lanczos { lobes: 3.0 },
+        Self::Cubic { b: 0.0, c: 0.5 },
+    ];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Triangle { .. } => "Triangle",
+            Self::Gaussian { .. } => "Gaussian",
+            Self::Lanczos { .. } => "Lanczos",
+            Self::Cubic { .. } => "Cubic (B, C)",
+        }
+    }
+
+    /// Whether `other` is the same kind of kernel, whatever its parameters.
+    pub fn same_kind(self, other: Self) -> bool {
+        std::mem::discriminant(&self) == std::mem::discriminant(&other)
+    }
+
+    /// Distance from the center past which the weight is zero.
+    pub fn support(self) -> f32 {
+        match self {
+            Self::Triangle { radius } => radius,
+            Self::Gaussian { sigma } => 3.0 * sigma,
+            Self::Lanczos { lobes } => lobes,
+            Self::Cubic { .. } => 2.0,
+        }
+    }
+
+    /// Checks the parameters are finite and the support is between `MIN_SUPPORT` and
+    /// `MAX_SUPPORT`.
+    pub fn validate(self) -> Result<()> {
+        let parameters: &[f32] = match &self {
+            Self::Triangle { radius } => &[*radius],
+            Self::Gaussian { sigma } => &[*sigma],
+            Self::Lanczos { lobes } => &[*lobes],
+            Self::Cubic { b, c } => &[*b, *c],
+        };
+        ensure!(
+            parameters.iter().all(|parameter| parameter.is_finite()),
+            "{} kernel parameters must be finite numbers",
+            self.label()
+        );
+        let support = self.support();
+        ensure!(
+            (MIN_SUPPORT..=MAX_SUPPORT).contains(&support),
+            "{} kernel support must be {MIN_SUPPORT} to {MAX_SUPPORT} pixels, got {support}",
+            self.label()
+        );
+        Ok(())
+    }
+
+    /// Unnormalized weight of a source sample `x` pixels from the center.
+    fn weight(self, x: f32) -> f32 {
+        let x = x.abs();
+        if x >= self.support() {
+            return 0.0;
+        }
+        match self {
+            Self::Triangle { radius } => 1.0 - x / radius,
+            Self::Gaussian { sigma } => (-x * x / (2.0 * sigma * sigma)).exp(),
+            Self::Lanczos { lobes } => sinc(x) * sinc(x / lobes),
+            Self::Cubic { b, c } => {
+                // Polynomial coefficients from the highest power down, per piece
+                let coefficients = if x < 1.0 {
+                    [
+                        (-6.0_f32).mul_add(c, (-9.0_f32).mul_add(b, 12.0)),
+                        6.0_f32.mul_add(c, 12.0_f32.mul_add(b, -18.0)),
+                        0.0,
+                        (-2.0_f32).mul_add(b, 6.0),
+                    ]
+                } else {
+                    [
+                        (-6.0_f32).mul_add(c, -b),
+                        30.0_f32.mul_add(c, 6.0 * b),
+                        (-48.0_f32).mul_add(c, -12.0 * b),
+                        24.0_f32.mul_add(c, 8.0 * b),
+                    ]
+                };
+                coefficients
+                    .into_iter()
+                    .fold(0.0_f32, |sum, coefficient| sum.mul_add(x, coefficient))
+                    / 6.0
+            }
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < f32::EPSILON {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Resizes `img` to exactly `width` by `height`, filtering each side in turn with `kernel`.
+///
+/// Samples are placed like the built-in filters place them, and the kernel is stretched by
+/// the shrink factor when downscaling, so a kernel matching a built-in filter gives the same
+/// result. Colors are weighted by alpha. The result keeps the source's color type.
+pub fn resize(img: &DynamicImage, width: u32, height: u32, kernel: Kernel) -> DynamicImage {
+    let mut source = img.to_rgba32f();
+    for pixel in source.pixels_mut() {
+        let alpha = pixel.0[3];
+        for channel in &mut pixel.0[..3] {
+            *channel *= alpha;
+        }
+    }
+
+    let columns = contributions(source.width(), width.max(1), kernel);
+    let rows = contributions(source.height(), height.max(1), kernel);
+
+    let vertical = Rgba32FImage::from_fn(source.width(), height.max(1), |x, y| {
+        let (start, weights) = &rows[y as usize];
+        weighted_sum(weights, |i| source.get_pixel(x, start + i as u32))
+    });
+    let mut resized = Rgba32FImage::from_fn(width.max(1), height.max(1), |x, y| {
+        let (start, weights) = &columns[x as usize];
+        weighted_sum(weights, |i| vertical.get_pixel(start + i as u32, y))
+    });
+
+    for pixel in resized.pixels_mut() {
+        let alpha = pixel.0[3];
+        if alpha > 0.0 {
+            for channel in &mut pixel.0[..3] {
+                *channel /= alpha;
+            }
+        }
+    }
+    color::convert(&DynamicImage::ImageRgba32F(resized), img.color())
+}
+
+/// Scales `img` with `kernel` until it covers `width` by `height`, then crops the overflow
+/// around the center, like `DynamicImage::resize_to_fill`.
+pub fn resize_to_fill(img: &DynamicImage, width: u32, height: u32, kernel: Kernel) -> DynamicImage {
+    let scale = f64::max(
+        f64::from(width) / f64::from(img.width()),
+        f64::from(height) / f64::from(img.height()),
+    );
+    let covering_width = ((f64::from(img.width()) * scale).round() as u32).max(width);
+    let covering_height = ((f64::from(img.height()) * scale).round() as u32).max(height);
+    let covering = resize(img, covering_width, covering_height, kernel);
+    covering.crop_imm(
+        (covering_width - width) / 2,
+        (covering_height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// First source index and normalized weights for each of `output_len` samples.
+fn contributions(source_len: u32, output_len: u32, kernel: Kernel) -> Vec<(u32, Vec<f32>)> {
+    let ratio = source_len as f32 / output_len as f32;
+    // Downscaling widens the kernel so every source pixel contributes
+    let stretch = ratio.max(1.0);
+    let support = kernel.support() * stretch;
+    let last = i64::from(source_len) - 1;
+
+    (0..output_len)
+        .map(|output| {
+            let center = (output as f32 + 0.5) * ratio;
+            let left = ((center - support).floor() as i64).clamp(0, last);
+            let right = ((center + support).ceil() as i64).clamp(left + 1, last + 1);
+            let mut weights: Vec<f32> = (left..right)
+                .map(|index| kernel.weight((index as f32 - (center - 0.5)) / stretch))
+                .collect();
+
+            let total: f32 = weights.iter().sum();
+            if total.abs() > f32::EPSILON {
+                for weight in &mut weights {
+                    *weight /= total;
+                }
+            } else {
+                // Nothing in reach, take the nearest sample
+                weights.fill(0.0);
+                let nearest = ((center - 0.5).round() as i64).clamp(left, right - 1);
+                weights[(nearest - left) as usize] = 1.0;
+            }
+            (left as u32, weights)
+        })
+        .collect()
+}
+
+fn weighted_sum<'a>(weights: &[f32], sample: impl Fn(usize) -> &'a Rgba<f32>) -> Rgba<f32> {
+    let mut sum = [0.0_f32; 4];
+    for (i, &weight) in weights.iter().enumerate() {
+        for (total, value) in sum.iter_mut().zip(sample(i).0) {
+            *total = value.mul_add(weight, *total);
+        }
+    }
+    Rgba(sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use image::imageops::FilterType;
+
+    #[test]
+    fn triangle_kernel_matches_the_triangle_filter() {
+        let img = test_util::photo(97, 61);
+        for (width, height) in [(40, 25), (150, 90)] {
+            let custom = resize(&img, width, height, Kernel::Triangle { radius: 1.0 }).to_rgb8();
+            let builtin = img
+                .resize_exact(width, height, FilterType::Triangle)
+                .to_rgb8();
+
+            let mut worst = 0;
+            let mut total = 0_u64;
+            for (a, b) in custom.pixels().zip(builtin.pixels()) {
+                for (a, b) in a.0.into_iter().zip(b.0) {
+                    let difference = a.abs_diff(b);
+                    worst = worst.max(difference);
+                    total += u64::from(difference);
+                }
+            }
+            let mean = total as f64 / f64::from(width * height * 3);
+            assert!(worst <= 3, "{width}x{height}: off by up to {worst}");
+            assert!(mean < 0.5, "{width}x{height}: off by {mean} on average");
+        }
+    }
+
+    #[test]
+    fn unreasonable_kernels_are_rejected() {
+        assert!(Kernel::Triangle { radius: f32::NAN }.validate().is_err());
+        assert!(Kernel::Cubic {
+            b: f32::INFINITY,
+            c: 0.5
+        }
+        .validate()
+        .is_err());
+        assert!(Kernel::Lanczos { lobes: 20.0 }.validate().is_err());
+        assert!(Kernel::Gaussian { sigma: 0.1 }.validate().is_err());
+        for kernel in Kernel::ALL {
+            kernel.validate().unwrap();
+        }
+    }
+}
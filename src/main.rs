@@ -4,16 +4,84 @@
 //! Built with eframe/egui for a modern, cross-platform user interface.
 
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+// Mapping inputs needs one unsafe call, which only the mmap feature compiles in
+#![cfg_attr(not(feature = "mmap"), forbid(unsafe_code))]
+#![cfg_attr(feature = "mmap", deny(unsafe_code))]
 
 use eframe::egui;
 
+mod adjust;
 mod app;
+mod area;
+mod caption;
+mod checksum;
+mod cmyk;
+mod color;
+mod compat;
+mod contact_sheet;
+mod container;
+mod deskew;
+mod discovery;
+mod encoder_params;
+#[cfg(feature = "event-server")]
+mod event_server;
+mod gallery;
+mod jobs;
+mod kernel;
+mod manifest;
+mod mask;
+mod metadata;
+mod naming;
+mod overlay;
+#[cfg(feature = "battery-pause")]
+mod power;
+mod preflight;
+mod preset_store;
 mod presets;
+mod probe;
+mod region;
 mod resizer;
+mod run_log;
+#[cfg(feature = "s3")]
+mod s3;
+#[cfg(feature = "seam-carving")]
+mod seam;
+mod sharpness;
+mod sink;
+mod sorting;
+mod source;
+mod split;
+mod ssim;
+mod summary;
+#[cfg(test)]
+mod test_util;
+mod transliterate;
+#[cfg(feature = "video")]
+mod video;
+mod xmp;
 
 use app::ImageResizerApp;
+use presets::NormalizedCrop;
+use run_log::RunLog;
+
+const JOBS_USAGE: &str =
+    "Usage: resize-rs --jobs <jobs.json> [--force] [--crop <x,y,width,height>] \
+     [--log-file <run.log> [--rotate-log]]";
+
+fn main() {
+    // `--jobs jobs.json` processes a job file without opening the window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "--jobs") {
+        let code = JobArgs::parse(&args[1..]).map_or_else(
+            || {
+                eprintln!("{JOBS_USAGE}");
+                2
+            },
+            |job_args| run_jobs(&job_args),
+        );
+        std::process::exit(code);
+    }
 
-fn main() -> eframe::Result<()> {
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([800.0, 600.0])
@@ -22,9 +90,142 @@ fn main() -> eframe::Result<()> {
         ..Default::default()
     };
 
-    eframe::run_native(
+    if let Err(e) = eframe::run_native(
         "Image Resizer",
         options,
-        Box::new(|_cc| Ok(Box::new(ImageResizerApp::new()))),
+        Box::new(|cc| Ok(Box::new(ImageResizerApp::from_storage(cc.storage)))),
+    ) {
+        eprintln!("{}", startup_error_message(&e));
+        std::process::exit(1);
+    }
+}
+
+/// What to tell someone whose window couldn't open, usually because there is no display.
+/// Batches can still run without one from a job file.
+fn startup_error_message(error: &eframe::Error) -> String {
+    let reason = match error {
+        eframe::Error::Winit(_) | eframe::Error::WinitEventLoop(_) => {
+            "no display is available to open a window on"
+        }
+        eframe::Error::AppCreation(_) => "the app failed to initialize",
+        _ => "the graphics driver could not be initialized",
+    };
+    format!(
+        "Could not start Image Resizer: {reason}.\n\
+         Details: {error}\n\
+         To resize without a window, export a plan from the app on another machine and run it \
+         here:\n  {JOBS_USAGE}"
     )
 }
+
+/// Options of a `--jobs` run.
+struct JobArgs<'a> {
+    path: &'a str,
+    /// Lets outputs replace their own inputs.
+    force: bool,
+    /// Crops every job's input to this, in fractions of the source size, e.g. `0.1,0.1,0.8,0.8`.
+    crop: Option<NormalizedCrop>,
+    /// Also record the results here, with timestamps.
+    log_file: Option<&'a str>,
+    /// Keep the previous log file instead of overwriting it.
+    rotate_log: bool,
+}
+
+impl<'a> JobArgs<'a> {
+    /// Reads the job file path and the options after it, `None` for anything unexpected.
+    fn parse(args: &'a [String]) -> Option<Self> {
+        let (path, options) = args.split_first()?;
+        let mut job_args = Self {
+            path,
+            force: false,
+            crop: None,
+            log_file: None,
+            rotate_log: false,
+        };
+        let mut options = options.iter();
+        while let Some(option) = options.next() {
+            match option.as_str() {
+                "--force" => job_args.force = true,
+                "--crop" => job_args.crop = Some(options.next()?.parse().ok()?),
+                "--log-file" => job_args.log_file = Some(options.next()?),
+                "--rotate-log" => job_args.rotate_log = true,
+                _ => return None,
+            }
+        }
+        Some(job_args)
+    }
+}
+
+/// Exit code for a `--jobs` run.
+fn run_jobs(job_args: &JobArgs) -> i32 {
+    let run_log = match job_args.log_file {
+        Some(path) => match RunLog::open(std::path::Path::new(path), job_args.rotate_log) {
+            Ok(run_log) => run_log,
+            Err(e) => {
+                eprintln!("{e:#}");
+                return 2;
+            }
+        },
+        None => RunLog::console(),
+    };
+    match jobs::run_job_file(
+        std::path::Path::new(job_args.path),
+        job_args.force,
+        job_args.crop,
+        &run_log,
+    ) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => {
+            eprintln!("{e:#}");
+            2
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crop_option_is_parsed_for_every_job() {
+        let args = |args: &[&str]| args.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        let with_crop = args(&["jobs.json", "--crop", "0.1,0.1,0.8,0.8", "--force"]);
+        let job_args = JobArgs::parse(&with_crop).unwrap();
+        assert_eq!(job_args.path, "jobs.json");
+        assert!(job_args.force);
+        assert_eq!(
+            job_args.crop.unwrap().to_pixels(1000, 500),
+            (100, 50, 800, 400)
+        );
+
+        for invalid in [
+            args(&["jobs.json", "--crop"]),
+            args(&["jobs.json", "--crop", "0.5,0.5,0.8,0.8"]),
+        ] {
+            assert!(JobArgs::parse(&invalid).is_none(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn missing_display_suggests_a_job_file() {
+        let error = eframe::Error::WinitEventLoop(winit::error::EventLoopError::RecreationAttempt);
+        let message = startup_error_message(&error);
+
+        assert!(message.contains("no display is available"), "{message}");
+        assert!(message.contains(JOBS_USAGE), "{message}");
+    }
+
+    #[test]
+    fn app_failures_are_not_blamed_on_the_display() {
+        let error = eframe::Error::AppCreation("storage is corrupt".into());
+        let message = startup_error_message(&error);
+
+        assert!(
+            message.contains("the app failed to initialize"),
+            "{message}"
+        );
+        assert!(message.contains("storage is corrupt"), "{message}");
+    }
+}
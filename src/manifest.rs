@@ -0,0 +1,210 @@
+use anyhow::{Context, Result};
+use image::ImageFormat;
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+const MANIFEST_FILE_NAME: &str = "resize.d";
+const IMAGE_MANIFEST_FILE_NAME: &str = "images.json";
+
+/// One source and the files made from it, as image components consume them.
+#[derive(Debug, Serialize)]
+struct ImageEntry {
+    source: String,
+    /// Narrowest first, the order `srcset` lists them in.
+    variants: Vec<Variant>,
+}
+
+#[derive(Debug, Serialize)]
+struct Variant {
+    /// Relative to the manifest, with `/` separators so it can be joined onto a URL.
+    path: String,
+    width: u32,
+    height: u32,
+    /// Subtype of the file's MIME type, e.g. `webp` for `image/webp`.
+    format: String,
+}
+
+/// Writes a Make-style depfile into `output_dir` with one `output: input` rule per produced file,
+/// headed by a comment naming the batch that produced them.
+///
+/// Inputs that failed are left out so a build system retries them on the next run.
+pub fn write_manifest(
+    output_dir: &Path,
+    batch_id: &str,
+    entries: &[(PathBuf, Vec<PathBuf>)],
+) -> Result<PathBuf> {
+    let mut depfile = format!("# batch {batch_id}\n");
+
+    for (input, outputs) in entries {
+        for output in outputs {
+            let _ = writeln!(depfile, "{}: {}", escape_path(output), escape_path(input));
+        }
+    }
+
+    let manifest_path = output_dir.join(MANIFEST_FILE_NAME);
+    std::fs::write(&manifest_path, depfile)
+        .with_context(|| format!("Failed to write manifest: {}", manifest_path.display()))?;
+    Ok(manifest_path)
+}
+
+/// Writes an `images.json` into `output_dir` listing each source's variants with their
+/// path, width, height and format, for sizes manifests of web image pipelines such as the
+/// Next.js and Cloudflare loaders. Outputs that aren't readable images are left out.
+pub fn write_image_manifest(
+    output_dir: &Path,
+    entries: &[(PathBuf, Vec<PathBuf>)],
+) -> Result<PathBuf> {
+    let images: Vec<_> = entries
+        .iter()
+        .map(|(input, outputs)| {
+            let mut variants: Vec<_> = outputs
+                .iter()
+                .filter_map(|output| {
+                    let format = ImageFormat::from_path(output).ok()?;
+                    let (width, height) = image::image_dimensions(output).ok()?;
+                    let mime = format.to_mime_type();
+                    Some(Variant {
+                        path: url_path(output.strip_prefix(output_dir).unwrap_or(output)),
+                        width,
+                        height,
+                        format: mime.strip_prefix("image/").unwrap_or(mime).to_string(),
+                    })
+                })
+                .collect();
+            variants.sort_by_key(|variant| (variant.width, variant.height));
+            ImageEntry {
+                source: input.to_string_lossy().into_owned(),
+                variants,
+            }
+        })
+        .collect();
+
+    let manifest_path = output_dir.join(IMAGE_MANIFEST_FILE_NAME);
+    let file = File::create(&manifest_path)
+        .with_context(|| format!("Failed to create manifest: {}", manifest_path.display()))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), &images)
+        .with_context(|| format!("Failed to write manifest: {}", manifest_path.display()))?;
+    Ok(manifest_path)
+}
+
+fn url_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Escapes the characters Make and Ninja treat specially in depfile paths.
+fn escape_path(path: &Path) -> String {
+    let text = path.to_string_lossy();
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            ' ' | '#' | ':' if !is_drive_colon(&escaped, c) => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '$' => escaped.push_str("$$"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Windows drive letters (`C:`) are understood by both tools without escaping.
+fn is_drive_colon(preceding: &str, c: char) -> bool {
+    c == ':' && preceding.len() == 1 && preceding.chars().all(|p| p.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::{OutputFormat, ResizePreset};
+    use crate::resizer::ImageResizer;
+    use crate::sink::FileSystemSink;
+    use crate::test_util;
+
+    #[test]
+    fn depfile_pairs_each_output_with_its_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let entries = [
+            (
+                PathBuf::from("in/a.jpg"),
+                vec![PathBuf::from("out/a.jpg"), PathBuf::from("out/a.webp")],
+            ),
+            (
+                PathBuf::from("in/my photo.png"),
+                vec![PathBuf::from("out/my photo.png")],
+            ),
+            (PathBuf::from("in/failed.png"), Vec::new()),
+        ];
+
+        let path = write_manifest(dir.path(), "20240518-093012-4f1c", &entries).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "# batch 20240518-093012-4f1c\n\
+             out/a.jpg: in/a.jpg\n\
+             out/a.webp: in/a.jpg\n\
+             out/my\\ photo.png: in/my\\ photo.png\n"
+        );
+    }
+
+    #[test]
+    fn image_manifest_lists_each_variant() {
+        let dir = tempfile::tempdir().unwrap();
+        let bytes = test_util::encode(&test_util::gradient(600, 300), ImageFormat::Png);
+        let preset = ResizePreset {
+            width: 100,
+            height: 100,
+            output_format: OutputFormat::Webp,
+            webp_lossless: true,
+            jpeg_fallback: true,
+            dpr_variants: true,
+            ..ResizePreset::BASE
+        };
+        let outputs = ImageResizer::resize_image(
+            Path::new("in/hero.png"),
+            &bytes,
+            &dir.path().join("img/hero.webp"),
+            &preset,
+            &FileSystemSink,
+            &|_| {},
+        )
+        .unwrap();
+
+        let path =
+            write_image_manifest(dir.path(), &[(PathBuf::from("in/hero.png"), outputs)]).unwrap();
+        let manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+
+        assert_eq!(manifest[0]["source"], "in/hero.png");
+        let variants: Vec<_> = manifest[0]["variants"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| {
+                (
+                    variant["path"].as_str().unwrap(),
+                    variant["width"].as_u64().unwrap(),
+                    variant["height"].as_u64().unwrap(),
+                    variant["format"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            variants,
+            [
+                ("img/hero.webp", 100, 50, "webp"),
+                ("img/hero.jpg", 100, 50, "jpeg"),
+                ("img/hero@2x.webp", 200, 100, "webp"),
+                ("img/hero@2x.jpg", 200, 100, "jpeg"),
+                ("img/hero@3x.webp", 300, 150, "webp"),
+                ("img/hero@3x.jpg", 300, 150, "jpeg"),
+            ]
+        );
+    }
+}
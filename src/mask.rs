@@ -0,0 +1,76 @@
+use image::{DynamicImage, Rgba};
+
+use crate::presets::MaskShape;
+
+/// Makes everything outside `shape` transparent, with a one pixel anti-aliased edge.
+pub fn apply_mask(img: &mut DynamicImage, shape: MaskShape) {
+    let mut rgba = img.to_rgba8();
+    let (width, height) = (rgba.width() as f32, rgba.height() as f32);
+    let (center_x, center_y) = (width / 2.0, height / 2.0);
+
+    for (x, y, pixel) in rgba.enumerate_pixels_mut() {
+        // Signed distance from the pixel center to the shape edge, negative inside
+        let (px, py) = (x as f32 + 0.5 - center_x, y as f32 + 0.5 - center_y);
+        let distance = match shape {
+            MaskShape::Circle => px.hypot(py) - center_x.min(center_y),
+            MaskShape::RoundedRect(radius) => {
+                let radius = (radius as f32).min(center_x.min(center_y));
+                let qx = px.abs() - (center_x - radius);
+                let qy = py.abs() - (center_y - radius);
+                qx.max(0.0).hypot(qy.max(0.0)) + qx.max(qy).min(0.0) - radius
+            }
+        };
+
+        let coverage = (0.5 - distance).clamp(0.0, 1.0);
+        pixel[3] = (f32::from(pixel[3]) * coverage).round() as u8;
+    }
+
+    *img = DynamicImage::ImageRgba8(rgba);
+}
+
+/// Composites transparent pixels onto white, for formats that cannot store alpha.
+pub fn flatten_alpha(img: &mut DynamicImage) {
+    let mut rgba = img.to_rgba8();
+    for pixel in rgba.pixels_mut() {
+        let alpha = u16::from(pixel[3]);
+        for channel in &mut pixel.0[..3] {
+            *channel = ((u16::from(*channel) * alpha + 255 * (255 - alpha)) / 255) as u8;
+        }
+        *pixel = Rgba([pixel[0], pixel[1], pixel[2], 255]);
+    }
+    *img = DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    #[test]
+    fn circle_mask_clears_the_corners() {
+        let mut img =
+            DynamicImage::ImageRgb8(RgbImage::from_pixel(100, 100, image::Rgb([9, 99, 199])));
+
+        apply_mask(&mut img, MaskShape::Circle);
+
+        let rgba = img.as_rgba8().unwrap();
+        for (x, y) in [(0, 0), (99, 0), (0, 99), (99, 99), (10, 10)] {
+            assert_eq!(rgba.get_pixel(x, y)[3], 0, "({x}, {y})");
+        }
+        for (x, y) in [(50, 50), (50, 2), (2, 50), (80, 50)] {
+            assert_eq!(rgba.get_pixel(x, y).0, [9, 99, 199, 255], "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn flattening_composites_onto_white() {
+        let mut img = DynamicImage::ImageRgb8(RgbImage::from_pixel(10, 10, image::Rgb([0, 0, 0])));
+        apply_mask(&mut img, MaskShape::Circle);
+
+        flatten_alpha(&mut img);
+
+        let rgb = img.as_rgb8().unwrap();
+        assert_eq!(rgb.get_pixel(0, 0).0, [255, 255, 255]);
+        assert_eq!(rgb.get_pixel(5, 5).0, [0, 0, 0]);
+    }
+}
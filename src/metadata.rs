@@ -0,0 +1,360 @@
+use anyhow::{Context, Result};
+use exif::{In, Reader, Tag, Value};
+use image::{ImageDecoder, ImageFormat};
+use img_parts::{Bytes, DynImage, ImageEXIF, ImageICC};
+use std::fmt;
+use std::io::{BufRead, Cursor, Seek};
+use std::path::Path;
+
+use crate::sink::OutputSink;
+
+const PNG_CHUNK_ICCP: [u8; 4] = *b"iCCP";
+
+/// When a photo was taken, as recorded in its EXIF `DateTimeOriginal` tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CaptureDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+}
+
+impl fmt::Display for CaptureDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute
+        )
+    }
+}
+
+/// Reads the capture date from an encoded image, `None` when it has no usable EXIF date.
+pub fn read_capture_date(bytes: &[u8]) -> Option<CaptureDate> {
+    let exif = Reader::new()
+        .read_from_container(&mut Cursor::new(bytes))
+        .ok()?;
+    let field = exif.get_field(Tag::DateTimeOriginal, In::PRIMARY)?;
+
+    let Value::Ascii(ref values) = field.value else {
+        return None;
+    };
+    let date = exif::DateTime::from_ascii(values.first()?).ok()?;
+
+    Some(CaptureDate {
+        year: date.year,
+        month: date.month,
+        day: date.day,
+        hour: date.hour,
+        minute: date.minute,
+    })
+}
+
+/// Reads the EXIF orientation (1 to 8) from an image file, reading only as far as the EXIF block.
+pub fn read_orientation<R: BufRead + Seek>(reader: &mut R) -> Option<u32> {
+    let exif = Reader::new().read_from_container(reader).ok()?;
+    exif.get_field(Tag::Orientation, In::PRIMARY)?
+        .value
+        .get_uint(0)
+        .filter(|orientation| (1..=8).contains(orientation))
+}
+
+/// The color profile and EXIF block of a source, to be copied into its outputs.
+pub struct SourceMetadata {
+    icc_profile: Option<Bytes>,
+    exif: Option<Bytes>,
+}
+
+impl SourceMetadata {
+    /// Reads the metadata of a JPEG, PNG or WebP, `None` for other formats or without any.
+    /// `upright` resets the EXIF orientation, for outputs whose pixels were already rotated.
+    pub fn read(bytes: &[u8], upright: bool) -> Option<Self> {
+        let image = DynImage::from_bytes(Bytes::copy_from_slice(bytes)).ok()??;
+        // img-parts panics on PNG profiles missing their name terminator or compression byte
+        let icc_profile = match &image {
+            DynImage::Png(png)
+                if !png.chunk_by_type(PNG_CHUNK_ICCP).is_some_and(|chunk| {
+                    let contents = chunk.contents();
+                    contents
+                        .iter()
+                        .position(|&byte| byte == 0)
+                        .is_some_and(|end| end + 1 < contents.len())
+                }) =>
+            {
+                None
+            }
+            image => image.icc_profile(),
+        };
+        let exif = image.exif().map(|exif| {
+            if upright {
+                let mut exif = exif.to_vec();
+                reset_orientation(&mut exif);
+                Bytes::from(exif)
+            } else {
+                exif
+            }
+        });
+        (icc_profile.is_some() || exif.is_some()).then_some(Self { icc_profile, exif })
+    }
+
+    /// Adds the metadata to an encoded JPEG or PNG, other formats are returned as-is. The
+    /// profile is left out when it doesn't describe the output's colors, e.g. a CMYK source
+    /// written as RGB or a color source converted to gray.
+    pub fn embed(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        let format = image::guess_format(bytes).ok();
+        if !matches!(format, Some(ImageFormat::Jpeg | ImageFormat::Png)) {
+            return Ok(bytes.to_vec());
+        }
+        let has_color = image::ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()?
+            .into_decoder()?
+            .color_type()
+            .has_color();
+        let icc_profile = self.icc_profile.clone().filter(|profile| {
+            let space = profile.get(16..20);
+            space == Some(if has_color { b"RGB " } else { b"GRAY" })
+        });
+
+        let mut image =
+            DynImage::from_bytes(Bytes::copy_from_slice(bytes))?.context("Unreadable output")?;
+        if icc_profile.is_some() {
+            image.set_icc_profile(icc_profile);
+        }
+        if self.exif.is_some() {
+            image.set_exif(self.exif.clone());
+        }
+        Ok(image.encoder().bytes().to_vec())
+    }
+}
+
+/// Sets the orientation tag in the first IFD of a TIFF-structured EXIF block to upright, if
+/// it has one.
+fn reset_orientation(exif: &mut [u8]) {
+    let big_endian = match exif.get(..2) {
+        Some(b"MM") => true,
+        Some(b"II") => false,
+        _ => return,
+    };
+    let read_u16 = |exif: &[u8], at: usize| {
+        exif.get(at..at + 2).map(|bytes| {
+            let bytes = [bytes[0], bytes[1]];
+            if big_endian {
+                u16::from_be_bytes(bytes)
+            } else {
+                u16::from_le_bytes(bytes)
+            }
+        })
+    };
+    let Some(ifd) = exif.get(4..8).map(|bytes| {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        let offset = if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        };
+        offset as usize
+    }) else {
+        return;
+    };
+    let Some(count) = read_u16(exif, ifd) else {
+        return;
+    };
+
+    // Entries are 12 bytes: tag, type, count and the value, which a SHORT starts with
+    for entry in (0..usize::from(count)).map(|index| ifd + 2 + index * 12) {
+        if read_u16(exif, entry) == Some(Tag::Orientation.number()) {
+            let upright: u16 = 1;
+            let value = if big_endian {
+                upright.to_be_bytes()
+            } else {
+                upright.to_le_bytes()
+            };
+            if let Some(slot) = exif.get_mut(entry + 8..entry + 10) {
+                slot.copy_from_slice(&value);
+            }
+            return;
+        }
+    }
+}
+
+/// Copies the source's ICC profile and EXIF into every output before passing it on.
+pub struct MetadataSink<'a> {
+    inner: &'a dyn OutputSink,
+    metadata: SourceMetadata,
+}
+
+impl<'a> MetadataSink<'a> {
+    pub const fn new(inner: &'a dyn OutputSink, metadata: SourceMetadata) -> Self {
+        Self { inner, metadata }
+    }
+}
+
+impl OutputSink for MetadataSink<'_> {
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        let bytes = self
+            .metadata
+            .embed(bytes)
+            .with_context(|| format!("Failed to copy metadata: {}", path.display()))?;
+        self.inner.write(path, &bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::{MetadataPolicy, OutputFormat, ResizePreset};
+    use crate::resizer::ImageResizer;
+    use crate::sink::MemorySink;
+    use crate::test_util;
+    use std::path::PathBuf;
+
+    /// A profile header, which is all `embed` reads to decide whether it fits the output.
+    fn rgb_profile() -> Bytes {
+        let mut profile = vec![0; 128];
+        profile[16..20].copy_from_slice(b"RGB ");
+        profile.into()
+    }
+
+    /// A 60x40 JPEG with an RGB profile, a capture date and `orientation`.
+    fn tagged_jpeg(orientation: u16) -> Vec<u8> {
+        let jpeg = test_util::jpeg_with_exif(
+            &test_util::photo(60, 40),
+            &[
+                test_util::exif_date("2024:06:15 10:30:00"),
+                test_util::exif_orientation(orientation),
+            ],
+        );
+        let mut image = DynImage::from_bytes(jpeg.into()).unwrap().unwrap();
+        image.set_icc_profile(Some(rgb_profile()));
+        image.encoder().bytes().to_vec()
+    }
+
+    fn resize(bytes: &[u8], preset: &ResizePreset) -> Vec<(PathBuf, Vec<u8>)> {
+        let sink = MemorySink::default();
+        ImageResizer::resize_image(
+            Path::new("photo.jpg"),
+            bytes,
+            Path::new("out/photo.png"),
+            preset,
+            &sink,
+            &|_| {},
+        )
+        .unwrap();
+        sink.into_files()
+    }
+
+    /// The ICC profile and EXIF block an encoded image carries.
+    fn metadata_of(bytes: &[u8]) -> (Option<Bytes>, Option<Bytes>) {
+        let image = DynImage::from_bytes(Bytes::copy_from_slice(bytes))
+            .unwrap()
+            .unwrap();
+        (image.icc_profile(), image.exif())
+    }
+
+    #[test]
+    fn profile_and_exif_survive_into_jpeg_and_png() {
+        let source = tagged_jpeg(1);
+        let (_, source_exif) = metadata_of(&source);
+
+        for output_format in [OutputFormat::Jpeg, OutputFormat::Png] {
+            let preset = ResizePreset {
+                width: 30,
+                height: 30,
+                output_format,
+                metadata: MetadataPolicy::Preserve,
+                ..ResizePreset::BASE
+            };
+
+            let files = resize(&source, &preset);
+            let (icc_profile, exif) = metadata_of(&files[0].1);
+            assert_eq!(icc_profile, Some(rgb_profile()), "{output_format:?}");
+            assert_eq!(exif, source_exif, "{output_format:?}");
+            assert_eq!(
+                read_capture_date(&files[0].1).map(|date| date.to_string()),
+                Some("2024-06-15 10:30".to_string()),
+                "{output_format:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn strip_writes_neither_profile_nor_exif() {
+        for output_format in [OutputFormat::Jpeg, OutputFormat::Png] {
+            let preset = ResizePreset {
+                width: 30,
+                height: 30,
+                output_format,
+                metadata: MetadataPolicy::Strip,
+                ..ResizePreset::BASE
+            };
+
+            let files = resize(&tagged_jpeg(1), &preset);
+            assert_eq!(metadata_of(&files[0].1), (None, None), "{output_format:?}");
+        }
+    }
+
+    #[test]
+    fn orientation_is_reset_once_applied() {
+        let orientation_of = |respect_exif_orientation| {
+            let preset = ResizePreset {
+                width: 60,
+                height: 60,
+                output_format: OutputFormat::Jpeg,
+                metadata: MetadataPolicy::Preserve,
+                respect_exif_orientation,
+                ..ResizePreset::BASE
+            };
+            let files = resize(&tagged_jpeg(6), &preset);
+            (
+                read_orientation(&mut Cursor::new(&files[0].1)),
+                test_util::dimensions(&files[0].1),
+            )
+        };
+
+        // Turned upright, so a viewer must not turn it again
+        assert_eq!(orientation_of(true), (Some(1), (40, 60)));
+        assert_eq!(orientation_of(false), (Some(6), (60, 40)));
+    }
+
+    #[test]
+    fn side_files_carry_no_source_metadata() {
+        let preset = ResizePreset {
+            width: 30,
+            height: 30,
+            output_format: OutputFormat::Png,
+            metadata: MetadataPolicy::Preserve,
+            lqip: true,
+            average_color: true,
+            ..ResizePreset::BASE
+        };
+
+        let files = resize(&tagged_jpeg(1), &preset);
+        let names: Vec<_> = files.iter().map(|(path, _)| path.as_path()).collect();
+        assert_eq!(
+            names,
+            [
+                "out/photo.png",
+                "out/photo_lqip.png",
+                "out/photo_swatch.png"
+            ]
+            .map(Path::new)
+        );
+        assert!(metadata_of(&files[0].1).1.is_some());
+        for (path, bytes) in &files[1..] {
+            assert_eq!(metadata_of(bytes), (None, None), "{}", path.display());
+        }
+
+        // The JPEG next to a WebP output is a fallback, not the output itself
+        let fallback = ResizePreset {
+            output_format: OutputFormat::Webp,
+            jpeg_fallback: true,
+            lqip: false,
+            average_color: false,
+            ..preset
+        };
+        let files = resize(&tagged_jpeg(1), &fallback);
+        assert_eq!(files[1].0, Path::new("out/photo.jpg"));
+        assert_eq!(metadata_of(&files[1].1), (None, None));
+    }
+}
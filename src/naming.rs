@@ -0,0 +1,530 @@
+use anyhow::{ensure, Context, Result};
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::metadata;
+use crate::transliterate;
+
+const PROXY_DIR: &str = "proxies";
+const ORIGINALS_DIR: &str = "originals";
+
+/// Placeholders a file name template can use, written in braces like `{stem}`.
+pub const TEMPLATE_PLACEHOLDERS: [&str; 6] = ["stem", "width", "height", "ext", "index", "preset"];
+
+/// Which subfolder of the output directory each output goes in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Subfolders {
+    #[default]
+    None,
+    /// The input's subfolder of the folder the inputs were gathered from.
+    MirrorInput,
+    /// `YYYY/MM` of the photo's EXIF capture date, or else of when its file was modified.
+    ByCaptureDate,
+}
+
+impl Subfolders {
+    pub const ALL: [Self; 3] = [Self::None, Self::MirrorInput, Self::ByCaptureDate];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::MirrorInput => "Same as the inputs",
+            Self::ByCaptureDate => "Year/month taken",
+        }
+    }
+}
+
+/// What to do when an output's file already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    #[default]
+    Overwrite,
+    /// Leave the existing file alone and don't process the input.
+    Skip,
+    /// Write next to it as `name (1).ext`, `name (2).ext`, ...
+    Rename,
+}
+
+impl OverwritePolicy {
+    pub const ALL: [Self; 3] = [Self::Overwrite, Self::Skip, Self::Rename];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Overwrite => "Overwrite",
+            Self::Skip => "Skip",
+            Self::Rename => "Rename",
+        }
+    }
+
+    /// Where to write an output meant for `path`, `None` to skip it. Paths another input
+    /// of the batch has reserved count as existing, and the returned path is reserved.
+    pub fn resolve(self, path: PathBuf, reserved: &ReservedPaths) -> Option<PathBuf> {
+        // Held until the chosen path is reserved, so no other input can pick it meanwhile
+        let mut reserved = reserved.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let taken = |path: &Path| path.exists() || reserved.contains(path);
+
+        let resolved = match self {
+            _ if !taken(&path) => Some(path),
+            Self::Overwrite => Some(path),
+            Self::Skip => None,
+            Self::Rename => {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                let extension = path
+                    .extension()
+                    .map(|extension| format!(".{}", extension.to_string_lossy()))
+                    .unwrap_or_default();
+                (1..=u32::MAX)
+                    .map(|number| path.with_file_name(format!("{stem} ({number}){extension}")))
+                    .find(|renamed| !taken(renamed))
+            }
+        };
+        if let Some(path) = &resolved {
+            reserved.insert(path.clone());
+        }
+        resolved
+    }
+}
+
+/// Output paths claimed by the inputs of one batch, which may be resolved in parallel
+/// before any of them is written.
+#[derive(Debug, Default)]
+pub struct ReservedPaths(Mutex<HashSet<PathBuf>>);
+
+/// How output files are named, the extension is appended separately.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Naming {
+    pub prefix: String,
+    pub suffix: String,
+    /// When set, outputs are named `{sequence_name}_001`, `_002`, ... in processing order
+    /// instead of after their source.
+    pub sequence_name: String,
+    /// Write resized outputs to `proxies/` and an untouched copy of each input to
+    /// `originals/`, so a proxy and its original share the input's stem.
+    pub proxy_layout: bool,
+    /// Allow an output path to be the same file as its input, replacing the original.
+    pub allow_input_overwrite: bool,
+    /// What happens to outputs whose file already exists.
+    pub overwrite: OverwritePolicy,
+    /// Appended to every output name as `_{batch_id}`, set for the run it belongs to.
+    pub batch_id: Option<String>,
+    /// Spell output names in ASCII, e.g. `café` as `cafe`.
+    pub transliterate: bool,
+    /// File name template such as `{stem}_thumb.{ext}`, which replaces the prefix, suffix,
+    /// sequence and batch ID naming when set. `{index}` is padded to three digits and the
+    /// extension is appended when the template has no `{ext}`.
+    pub template: String,
+    /// Name of the preset outputs are made with, for the template's `{preset}`.
+    pub preset_name: String,
+    pub subfolders: Subfolders,
+    /// Folder the inputs were gathered from, mirrored with `Subfolders::MirrorInput`.
+    pub input_root: Option<PathBuf>,
+}
+
+impl Naming {
+    /// Output file name for the `index`th (from 1) input named `stem` that comes out `size`
+    /// large, `{stem}_resized_{w}x{h}.{extension}` unless a template, prefix, suffix or
+    /// sequence name is set. An empty `extension` adds none.
+    pub fn output_file_name(
+        &self,
+        stem: &str,
+        index: usize,
+        size: (u32, u32),
+        extension: &str,
+    ) -> Result<String> {
+        let mut name = if self.template.is_empty() {
+            let stem = self.base_stem(stem, index, size);
+            match &self.batch_id {
+                Some(batch_id) => format!("{stem}_{batch_id}"),
+                None => stem,
+            }
+        } else {
+            self.fill_template(stem, index, size, extension)?
+        };
+        if !extension.is_empty() && !self.template.contains("{ext}") {
+            name.push('.');
+            name.push_str(extension);
+        }
+        ensure!(
+            !name.contains(['/', '\\']) && !matches!(name.as_str(), "" | "." | ".."),
+            "The file name template makes {name:?}, which is not a file name"
+        );
+        Ok(if self.transliterate {
+            transliterate::to_ascii(&name)
+        } else {
+            name
+        })
+    }
+
+    fn fill_template(
+        &self,
+        stem: &str,
+        index: usize,
+        (width, height): (u32, u32),
+        extension: &str,
+    ) -> Result<String> {
+        fill_template(&self.template, |placeholder| {
+            Some(match placeholder {
+                "stem" => stem.to_string(),
+                "width" => width.to_string(),
+                "height" => height.to_string(),
+                "ext" => extension.to_string(),
+                "index" => format!("{index:03}"),
+                "preset" => self.preset_name.clone(),
+                _ => return None,
+            })
+        })
+    }
+
+    /// What the template names the first output of `photo.jpg` at 800x600, or why it can't
+    /// be used.
+    pub fn template_example(&self) -> Result<String> {
+        self.output_file_name("photo", 1, (800, 600), "jpg")
+    }
+
+    fn base_stem(&self, stem: &str, index: usize, (width, height): (u32, u32)) -> String {
+        if !self.sequence_name.is_empty() {
+            format!(
+                "{}{}_{index:03}{}",
+                self.prefix, self.sequence_name, self.suffix
+            )
+        } else if self.prefix.is_empty() && self.suffix.is_empty() {
+            format!("{stem}_resized_{width}x{height}")
+        } else {
+            format!("{}{stem}{}", self.prefix, self.suffix)
+        }
+    }
+
+    /// Directory resized outputs are written to.
+    fn resized_dir(&self, output_dir: &Path) -> PathBuf {
+        if self.proxy_layout {
+            output_dir.join(PROXY_DIR)
+        } else {
+            output_dir.to_path_buf()
+        }
+    }
+
+    /// Folder the output of `input` goes in. `bytes` are the encoded input when they are at
+    /// hand, otherwise the file is read if its capture date is needed.
+    pub fn output_dir_for(&self, input: &Path, bytes: Option<&[u8]>, output_dir: &Path) -> PathBuf {
+        self.resized_dir(output_dir)
+            .join(self.subfolder(input, bytes))
+    }
+
+    /// Where the untouched copy of `input` goes, `None` unless originals are kept.
+    pub fn original_copy_path(
+        &self,
+        input: &Path,
+        bytes: Option<&[u8]>,
+        output_dir: &Path,
+    ) -> Option<PathBuf> {
+        let file_name = input.file_name()?;
+        self.proxy_layout.then(|| {
+            output_dir
+                .join(ORIGINALS_DIR)
+                .join(self.subfolder(input, bytes))
+                .join(file_name)
+        })
+    }
+
+    /// Subfolder for the outputs of `input`, empty when it gets none.
+    fn subfolder(&self, input: &Path, bytes: Option<&[u8]>) -> PathBuf {
+        match self.subfolders {
+            Subfolders::None => PathBuf::new(),
+            Subfolders::MirrorInput => self.relative_dir(input),
+            Subfolders::ByCaptureDate => date_folder(input, bytes).unwrap_or_default(),
+        }
+    }
+
+    /// Subfolder of `input_root` that `input` is in, empty for inputs outside of it.
+    fn relative_dir(&self, input: &Path) -> PathBuf {
+        self.input_root
+            .as_deref()
+            .and_then(|root| input.parent()?.strip_prefix(root).ok())
+            // Only plain folder names, nothing that could climb out of the output directory
+            .filter(|relative| {
+                relative
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)))
+            })
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    }
+
+    /// Rejects text that would place outputs outside the output directory.
+    pub fn validate_affixes(&self) -> Result<()> {
+        for text in [&self.prefix, &self.suffix, &self.sequence_name] {
+            ensure!(
+                !text.contains(['/', '\\']),
+                "File names cannot contain path separators"
+            );
+        }
+        Ok(())
+    }
+
+    /// Rejects affixes and templates that would place outputs outside the output directory
+    /// and templates with unknown placeholders.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_affixes()?;
+        if !self.template.is_empty() {
+            ensure!(
+                !self.template.contains(['/', '\\']),
+                "File name templates cannot contain path separators"
+            );
+            self.template_example()?;
+        }
+        Ok(())
+    }
+}
+
+/// Replaces each `{name}` in `template` with `value(name)`, which is `None` for names that
+/// aren't placeholders.
+fn fill_template(template: &str, value: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        ensure!(
+            rest[start..].starts_with('{'),
+            "Unmatched }} in the file name template"
+        );
+        filled.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after
+            .find('}')
+            .context("Unmatched { in the file name template")?;
+        let name = &after[..end];
+        filled.push_str(&value(name).with_context(|| {
+            format!(
+                "Unknown placeholder {{{name}}}, known ones are {{{}}}",
+                TEMPLATE_PLACEHOLDERS.join("}, {")
+            )
+        })?);
+        rest = &after[end + 1..];
+    }
+    filled.push_str(rest);
+    Ok(filled)
+}
+
+/// A new ID for one batch run, the UTC start time plus a random part so runs started in
+/// the same second still differ, e.g. `20240518-093012-4f1c`.
+pub fn new_batch_id() -> String {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(since_epoch.as_nanos());
+
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days(seconds / 86_400);
+    let time = seconds % 86_400;
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}-{:04x}",
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        hasher.finish() & 0xffff
+    )
+}
+
+/// The current UTC time as `2024-05-18T09:30:12Z`.
+pub fn utc_timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (year, month, day) = civil_from_days(seconds / 86_400);
+    let time = seconds % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// `YYYY/MM` of when the photo was taken, or of when its file was last modified (in UTC) when
+/// it has no EXIF date. `None` for inputs that are neither, such as downloads without EXIF.
+fn date_folder(input: &Path, bytes: Option<&[u8]>) -> Option<PathBuf> {
+    let captured = bytes.map_or_else(
+        || {
+            std::fs::read(input)
+                .ok()
+                .and_then(|bytes| metadata::read_capture_date(&bytes))
+        },
+        metadata::read_capture_date,
+    );
+
+    let (year, month) = if let Some(date) = captured {
+        (u64::from(date.year), u64::from(date.month))
+    } else {
+        let modified = std::fs::metadata(input).and_then(|metadata| metadata.modified());
+        let seconds = modified.ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let (year, month, _) = civil_from_days(seconds / 86_400);
+        (year, month)
+    };
+    Some(Path::new(&format!("{year:04}")).join(format!("{month:02}")))
+}
+
+/// Converts days since 1970-01-01 to a `(year, month, day)` date.
+const fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Counted in 400-year eras starting on 0000-03-01, so leap days fall at the end of a year
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Reads a target size embedded in a file name such as `logo@800x600.png` or `hero_1920x1080.jpg`.
+/// The last `WxH` in the stem wins.
+pub fn size_from_file_name(path: &Path) -> Option<(u32, u32)> {
+    let stem = path.file_stem()?.to_str()?;
+
+    stem.char_indices()
+        .rev()
+        .filter(|&(_, c)| c == 'x' || c == 'X')
+        .find_map(|(index, _)| {
+            let before = &stem[..index];
+            let after = &stem[index + 1..];
+            let width_start = before
+                .char_indices()
+                .rev()
+                .find(|(_, c)| !c.is_ascii_digit())
+                .map_or(0, |(i, c)| i + c.len_utf8());
+            let height_end = after
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(after.len());
+
+            let width = before[width_start..].parse().ok()?;
+            let height = after[..height_end].parse().ok()?;
+            (width > 0 && height > 0).then_some((width, height))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_and_suffix_wrap_the_stem() {
+        let naming = Naming {
+            prefix: "web_".to_string(),
+            suffix: "_sm".to_string(),
+            ..Naming::default()
+        };
+        assert_eq!(
+            naming
+                .output_file_name("photo", 1, (800, 600), "jpg")
+                .unwrap(),
+            "web_photo_sm.jpg"
+        );
+        assert_eq!(
+            Naming::default()
+                .output_file_name("photo", 1, (800, 600), "jpg")
+                .unwrap(),
+            "photo_resized_800x600.jpg"
+        );
+
+        let escaping = Naming {
+            prefix: "../".to_string(),
+            ..Naming::default()
+        };
+        assert!(escaping.validate().is_err());
+    }
+
+    #[test]
+    fn size_is_read_from_file_names() {
+        for (name, size) in [
+            ("logo@800x600.png", Some((800, 600))),
+            ("hero_1920X1080.jpg", Some((1920, 1080))),
+            ("a_10x10_then_20x30.png", Some((20, 30))),
+            ("写真800x600.jpg", Some((800, 600))),
+            ("pic.png", None),
+            ("résuméx.jpg", None),
+            ("写真x.jpg", None),
+            ("box_0x600.png", None),
+            ("éx1.png", None),
+        ] {
+            assert_eq!(size_from_file_name(Path::new(name)), size, "{name}");
+        }
+    }
+
+    #[test]
+    fn batch_ids_differ_between_runs() {
+        let first = new_batch_id();
+        let second = new_batch_id();
+        assert_ne!(first, second);
+        assert_eq!(first.len(), "20240518-093012-4f1c".len(), "{first}");
+
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(19_861), (2024, 5, 18));
+    }
+
+    #[test]
+    fn transliteration_only_applies_when_enabled() {
+        let transliterating = Naming {
+            transliterate: true,
+            ..Naming::default()
+        };
+        for _ in 0..2 {
+            assert_eq!(
+                transliterating
+                    .output_file_name("café", 1, (800, 600), "jpg")
+                    .unwrap(),
+                "cafe_resized_800x600.jpg"
+            );
+        }
+        assert_eq!(
+            Naming::default()
+                .output_file_name("café", 1, (800, 600), "jpg")
+                .unwrap(),
+            "café_resized_800x600.jpg"
+        );
+    }
+
+    #[test]
+    fn parallel_resolves_never_share_a_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.jpg");
+        std::fs::write(&path, b"existing").unwrap();
+
+        for (policy, expected) in [(OverwritePolicy::Rename, 8), (OverwritePolicy::Skip, 0)] {
+            let reserved = ReservedPaths::default();
+            let resolved: HashSet<PathBuf> = std::thread::scope(|scope| {
+                let handles: Vec<_> = (0..8)
+                    .map(|_| scope.spawn(|| policy.resolve(path.clone(), &reserved)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .filter_map(|handle| handle.join().unwrap())
+                    .collect()
+            });
+
+            assert_eq!(resolved.len(), expected, "{policy:?}: {resolved:?}");
+            assert!(!resolved.contains(&path));
+        }
+
+        // A path nobody has yet goes to the first input only
+        let fresh = dir.path().join("fresh.jpg");
+        let reserved = ReservedPaths::default();
+        assert_eq!(
+            OverwritePolicy::Skip.resolve(fresh.clone(), &reserved),
+            Some(fresh.clone())
+        );
+        assert_eq!(OverwritePolicy::Skip.resolve(fresh, &reserved), None);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::presets::{ResizeMode, ResizePreset};
+use crate::resizer::ImageResizer;
+
+/// A rectangle in source pixels, which may reach past the source into padding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Region {
+    const fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn right(self) -> f32 {
+        self.x + self.width
+    }
+
+    fn bottom(self) -> f32 {
+        self.y + self.height
+    }
+}
+
+/// What a preset does to the edges of a source, for drawing over the source in the preview.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    /// Everything to draw: the source and any padding around it.
+    pub bounds: Region,
+    /// The source itself.
+    pub source: Region,
+    /// Parts of the source left out of the output by the crop or by filling.
+    pub discarded: Vec<Region>,
+    /// Letterbox bars added around the image.
+    pub padding: Vec<Region>,
+}
+
+/// Where `preset` crops and pads a source of `source` pixels, as displayed.
+pub fn layout(source: (u32, u32), preset: &ResizePreset) -> Layout {
+    let full = Region::new(0.0, 0.0, source.0 as f32, source.1 as f32);
+    let mut layout = Layout {
+        bounds: full,
+        source: full,
+        discarded: Vec::new(),
+        padding: Vec::new(),
+    };
+    // Only the masked region is processed, the output keeps the whole source
+    if preset.region_mask {
+        return layout;
+    }
+
+    let mut kept = preset.crop.map_or(full, |crop| {
+        let (x, y, width, height) = crop.to_pixels(source.0, source.1);
+        Region::new(x as f32, y as f32, width as f32, height as f32)
+    });
+    let cropped = (kept.width as u32, kept.height as u32);
+    let ((width, height), mode) = ImageResizer::target_size(cropped, preset);
+
+    // Fill scales to cover the target and cuts the overflow around the center
+    if mode == ResizeMode::Fill {
+        let scale = (width as f32 / kept.width).max(height as f32 / kept.height);
+        let (kept_width, kept_height) = (width as f32 / scale, height as f32 / scale);
+        kept = Region::new(
+            kept.x + (kept.width - kept_width) / 2.0,
+            kept.y + (kept.height - kept_height) / 2.0,
+            kept_width,
+            kept_height,
+        );
+    }
+    layout.discarded = strips_between(full, kept);
+
+    if ImageResizer::pads_to_target(preset) {
+        // Fit keeps the ratio, so one scale maps output pixels back to source pixels
+        let scale = width as f32 / kept.width;
+        let canvas_width = preset.width as f32 / scale;
+        let canvas_height = preset.height as f32 / scale;
+        let canvas = Region::new(
+            kept.x - (canvas_width - kept.width) / 2.0,
+            kept.y - (canvas_height - kept.height) / 2.0,
+            canvas_width,
+            canvas_height,
+        );
+        layout.padding = strips_between(canvas, kept);
+        let (left, top) = (full.x.min(canvas.x), full.y.min(canvas.y));
+        layout.bounds = Region::new(
+            left,
+            top,
+            full.right().max(canvas.right()) - left,
+            full.bottom().max(canvas.bottom()) - top,
+        );
+    }
+    layout
+}
+
+/// The parts of `outer` outside `inner`: full-width bars above and below, then the sides.
+fn strips_between(outer: Region, inner: Region) -> Vec<Region> {
+    // Less than half a pixel is rounding, not a visible strip
+    const MIN_SIZE: f32 = 0.5;
+
+    [
+        Region::new(outer.x, outer.y, outer.width, inner.y - outer.y),
+        Region::new(
+            outer.x,
+            inner.bottom(),
+            outer.width,
+            outer.bottom() - inner.bottom(),
+        ),
+        Region::new(outer.x, inner.y, inner.x - outer.x, inner.height),
+        Region::new(
+            inner.right(),
+            inner.y,
+            outer.right() - inner.right(),
+            inner.height,
+        ),
+    ]
+    .into_iter()
+    .filter(|strip| strip.width >= MIN_SIZE && strip.height >= MIN_SIZE)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::NormalizedCrop;
+
+    fn preset(mode: ResizeMode, pad_to_target: bool) -> ResizePreset {
+        ResizePreset {
+            width: 100,
+            height: 100,
+            mode,
+            pad_to_target,
+            ..ResizePreset::BASE
+        }
+    }
+
+    #[test]
+    fn fill_discards_the_overflowing_sides() {
+        let layout = layout((400, 200), &preset(ResizeMode::Fill, false));
+
+        assert_eq!(
+            layout.discarded,
+            vec![
+                Region::new(0.0, 0.0, 100.0, 200.0),
+                Region::new(300.0, 0.0, 100.0, 200.0),
+            ]
+        );
+        assert!(layout.padding.is_empty());
+        assert_eq!(layout.bounds, Region::new(0.0, 0.0, 400.0, 200.0));
+    }
+
+    #[test]
+    fn padded_fit_adds_bars_in_source_pixels() {
+        let layout = layout((400, 200), &preset(ResizeMode::Fit, true));
+
+        assert!(layout.discarded.is_empty());
+        assert_eq!(
+            layout.padding,
+            vec![
+                Region::new(0.0, -100.0, 400.0, 100.0),
+                Region::new(0.0, 200.0, 400.0, 100.0),
+            ]
+        );
+        assert_eq!(layout.bounds, Region::new(0.0, -100.0, 400.0, 400.0));
+        assert_eq!(layout.source, Region::new(0.0, 0.0, 400.0, 200.0));
+    }
+
+    #[test]
+    fn plain_fit_marks_nothing() {
+        let layout = layout((400, 200), &preset(ResizeMode::Fit, false));
+
+        assert!(layout.discarded.is_empty());
+        assert!(layout.padding.is_empty());
+    }
+
+    #[test]
+    fn crop_discards_everything_outside_it() {
+        let preset = ResizePreset {
+            crop: Some(NormalizedCrop {
+                x: 0.25,
+                y: 0.25,
+                width: 0.5,
+                height: 0.5,
+            }),
+            ..preset(ResizeMode::Fit, false)
+        };
+        let layout = layout((400, 200), &preset);
+
+        assert_eq!(
+            layout.discarded,
+            vec![
+                Region::new(0.0, 0.0, 400.0, 50.0),
+                Region::new(0.0, 150.0, 400.0, 50.0),
+                Region::new(0.0, 50.0, 100.0, 100.0),
+                Region::new(300.0, 50.0, 100.0, 100.0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// How often a paused batch looks for a cancel between power checks.
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Whether the machine runs from its battery and how full that battery is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerState {
+    pub on_battery: bool,
+    pub charge_percent: u8,
+}
+
+/// Heavy batches wait while running on a battery that is below `threshold_percent`.
+pub const fn should_pause(state: PowerState, threshold_percent: u8) -> bool {
+    state.on_battery && state.charge_percent < threshold_percent
+}
+
+/// Blocks while [`should_pause`] says so, logging when it pauses and resumes. Returns
+/// early once `cancel` is set.
+pub fn wait_for_power(threshold_percent: u8, cancel: &AtomicBool, log: &dyn Fn(String)) {
+    wait_while_paused(read_power_state, threshold_percent, cancel, log);
+}
+
+fn wait_while_paused(
+    read_state: impl Fn() -> Option<PowerState>,
+    threshold_percent: u8,
+    cancel: &AtomicBool,
+    log: &dyn Fn(String),
+) {
+    let mut paused = false;
+    while let Some(state) = read_state().filter(|state| should_pause(*state, threshold_percent)) {
+        if !paused {
+            log(format!(
+                "Paused: on battery at {}%, waiting for external power",
+                state.charge_percent
+            ));
+            paused = true;
+        }
+        let next_check = Instant::now() + POLL_INTERVAL;
+        while Instant::now() < next_check {
+            if cancel.load(Ordering::Relaxed) {
+                return;
+            }
+            thread::sleep(CANCEL_CHECK_INTERVAL);
+        }
+    }
+    if paused {
+        log("Resumed: external power connected".to_string());
+    }
+}
+
+/// Current power state, `None` on desktops or when the platform can't tell.
+pub fn read_power_state() -> Option<PowerState> {
+    if cfg!(target_os = "linux") {
+        read_linux_power_state(Path::new("/sys/class/power_supply"))
+    } else if cfg!(target_os = "macos") {
+        read_macos_power_state()
+    } else {
+        None
+    }
+}
+
+/// Reads the first battery listed in sysfs, the machine is on battery while it discharges.
+fn read_linux_power_state(power_supply: &Path) -> Option<PowerState> {
+    std::fs::read_dir(power_supply)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find_map(|supply| {
+            let read = |name: &str| std::fs::read_to_string(supply.join(name)).ok();
+            if read("type")?.trim() != "Battery" {
+                return None;
+            }
+            Some(PowerState {
+                on_battery: read("status")?.trim() == "Discharging",
+                charge_percent: read("capacity")?.trim().parse().ok()?,
+            })
+        })
+}
+
+/// Parses `pmset -g batt`, which reports "Now drawing from 'Battery Power'" and a line with
+/// the charge such as "-InternalBattery-0 (id=1) 83%; discharging".
+fn read_macos_power_state() -> Option<PowerState> {
+    let output = std::process::Command::new("pmset")
+        .args(["-g", "batt"])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+
+    let charge_percent = text
+        .split_whitespace()
+        .find_map(|word| word.strip_suffix("%;"))?
+        .parse()
+        .ok()?;
+    Some(PowerState {
+        on_battery: text.contains("'Battery Power'"),
+        charge_percent,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn pauses_only_on_a_low_battery() {
+        let state = |on_battery, charge_percent| PowerState {
+            on_battery,
+            charge_percent,
+        };
+        assert!(should_pause(state(true, 10), 20));
+        assert!(should_pause(state(true, 19), 20));
+        assert!(!should_pause(state(true, 20), 20));
+        assert!(!should_pause(state(true, 80), 20));
+        assert!(!should_pause(state(false, 5), 20));
+        assert!(!should_pause(state(true, 100), 0));
+    }
+
+    #[test]
+    fn cancel_ends_the_pause() {
+        let cancel = AtomicBool::new(false);
+        let logged = Mutex::new(Vec::new());
+        let started = Instant::now();
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(200));
+                cancel.store(true, Ordering::Relaxed);
+            });
+            wait_while_paused(
+                || {
+                    Some(PowerState {
+                        on_battery: true,
+                        charge_percent: 5,
+                    })
+                },
+                20,
+                &cancel,
+                &|message| logged.lock().unwrap().push(message),
+            );
+        });
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(
+            logged.into_inner().unwrap(),
+            ["Paused: on battery at 5%, waiting for external power"]
+        );
+    }
+
+    #[test]
+    fn external_power_never_pauses() {
+        let logged = Mutex::new(Vec::new());
+        wait_while_paused(
+            || {
+                Some(PowerState {
+                    on_battery: false,
+                    charge_percent: 5,
+                })
+            },
+            20,
+            &AtomicBool::new(false),
+            &|message| logged.lock().unwrap().push(message),
+        );
+        assert!(logged.into_inner().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::presets::ResizePreset;
+use crate::resizer::ImageResizer;
+
+/// Extra room on top of the estimate, encoders vary a lot between images.
+const HEADROOM: f64 = 1.25;
+
+/// Rough number of bytes a batch will write.
+///
+/// Each input contributes its own size, scaled down by how many fewer pixels the output
+/// will have. Inputs whose header cannot be read are counted at full size.
+pub fn estimate_output_bytes(inputs: &[PathBuf], preset: &ResizePreset) -> u64 {
+    let estimate: f64 = inputs
+        .iter()
+        .map(|input| {
+            let size = std::fs::metadata(input).map_or(0, |metadata| metadata.len()) as f64;
+            let ratio = image::image_dimensions(input).map_or(1.0, |(width, height)| {
+                let (target_width, target_height) =
+                    ImageResizer::output_dimensions((width, height), preset);
+                let target_pixels = f64::from(target_width) * f64::from(target_height);
+                let source_pixels = f64::from(width) * f64::from(height);
+                (target_pixels / source_pixels).min(1.0)
+            });
+            size * ratio
+        })
+        .sum();
+
+    (estimate * HEADROOM) as u64
+}
+
+pub fn available_space(output_dir: &Path) -> Result<u64> {
+    fs2::available_space(output_dir)
+        .with_context(|| format!("Failed to query free space for {}", output_dir.display()))
+}
+
+/// Errors if `required` bytes will not fit into `available`.
+pub fn ensure_space(required: u64, available: u64) -> Result<()> {
+    anyhow::ensure!(
+        required <= available,
+        "Not enough disk space: the batch needs about {} MB but only {} MB are free",
+        required.div_ceil(1024 * 1024),
+        available / (1024 * 1024)
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use image::ImageFormat;
+
+    #[test]
+    fn estimate_scales_inputs_by_pixel_ratio() {
+        let dir = tempfile::tempdir().unwrap();
+        let image_path = dir.path().join("big.png");
+        let image_bytes = test_util::encode(&test_util::gradient(400, 200), ImageFormat::Png);
+        std::fs::write(&image_path, &image_bytes).unwrap();
+        let other_path = dir.path().join("notes.bin");
+        std::fs::write(&other_path, [0_u8; 1000]).unwrap();
+        let preset = ResizePreset {
+            width: 200,
+            height: 200,
+            ..ResizePreset::BASE
+        };
+
+        // A quarter of the pixels, the unreadable file at full size, plus headroom
+        let expected = (image_bytes.len() as f64).mul_add(0.25, 1000.0) * HEADROOM;
+        assert_eq!(
+            estimate_output_bytes(&[image_path, other_path], &preset),
+            expected as u64
+        );
+    }
+
+    #[test]
+    fn low_space_stops_the_batch() {
+        assert!(ensure_space(5 * 1024 * 1024, 6 * 1024 * 1024).is_ok());
+
+        let error = ensure_space(50 * 1024 * 1024, 3 * 1024 * 1024)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "Not enough disk space: the batch needs about 50 MB but only 3 MB are free"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use crate::adjust::Look;
+use crate::presets::NamedPreset;
+
+/// This app's folder in the platform's config directory, `None` when the environment
+/// doesn't say where that is.
+fn config_dir() -> Option<PathBuf> {
+    let env_dir = |name: &str| {
+        std::env::var_os(name)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+    };
+    let config_dir = if cfg!(windows) {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        env_dir("XDG_CONFIG_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".config")))
+    }?;
+    Some(config_dir.join("resize-rs"))
+}
+
+/// `presets.json` in this app's folder of the platform's config directory.
+pub fn presets_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("presets.json"))
+}
+
+/// `looks.json` next to the presets.
+pub fn looks_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("looks.json"))
+}
+
+/// The presets saved at `path`, or the built-in ones if nothing has been saved yet.
+pub fn load_presets(path: &Path) -> Result<Vec<NamedPreset>> {
+    if !path.exists() {
+        return Ok(NamedPreset::PRESETS.to_vec());
+    }
+    load(path, "presets")
+}
+
+pub fn save_presets(path: &Path, presets: &[NamedPreset]) -> Result<()> {
+    save(path, "presets", presets)
+}
+
+/// The looks saved at `path`, none if nothing has been saved yet.
+pub fn load_looks(path: &Path) -> Result<Vec<Look>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    load(path, "looks")
+}
+
+pub fn save_looks(path: &Path, looks: &[Look]) -> Result<()> {
+    save(path, "looks", looks)
+}
+
+fn load<T: DeserializeOwned>(path: &Path, what: &str) -> Result<T> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open {what}: {}", path.display()))?;
+    serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("Invalid {what} file: {}", path.display()))
+}
+
+fn save<T: Serialize + ?Sized>(path: &Path, what: &str, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create {what} file: {}", path.display()))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), value)
+        .with_context(|| format!("Failed to write {what}: {}", path.display()))
+}
@@ -1,4 +1,15 @@
+use anyhow::Context;
+use image::imageops::FilterType;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::adjust::AdjustmentStack;
+use crate::encoder_params::EncoderParams;
+use crate::kernel::Kernel;
+use crate::naming;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OutputFormat {
@@ -8,101 +19,710 @@ pub enum OutputFormat {
     Webp,
     Bmp,
     Tiff,
+    /// Needs the `avif` feature, which enables the image crate's AVIF encoder.
+    Avif,
+}
+
+/// How the source's aspect ratio meets the target's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResizeMode {
+    /// Scale to fit inside the target, keeping the ratio, so one side may come out shorter.
+    Fit,
+    /// Scale to cover the target, keeping the ratio, and crop the overflow around the center.
+    Fill,
+    /// Scale each side to the target independently.
+    Stretch,
+}
+
+impl ResizeMode {
+    pub const ALL: [Self; 3] = [Self::Fit, Self::Fill, Self::Stretch];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Fit => "Fit (keep aspect ratio)",
+            Self::Fill => "Fill (crop to the exact size)",
+            Self::Stretch => "Stretch",
+        }
+    }
+}
+
+/// What to do when an animated source is written to a format that only holds one frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnimationPolicy {
+    FirstFrame,
+    Error,
+    AllFramesNumbered,
+}
+
+impl AnimationPolicy {
+    pub const ALL: [Self; 3] = [Self::FirstFrame, Self::Error, Self::AllFramesNumbered];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::FirstFrame => "First frame only",
+            Self::Error => "Fail",
+            Self::AllFramesNumbered => "All frames, numbered",
+        }
+    }
+}
+
+/// What to do when the output format can't store the image as it is, such as a 16-bit source
+/// written as BMP or a transparent one as JPEG.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IncompatibleOutput {
+    /// Reduce the bit depth and flatten transparency onto white, and log it.
+    Convert,
+    Fail,
+}
+
+impl IncompatibleOutput {
+    pub const ALL: [Self; 2] = [Self::Convert, Self::Fail];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Convert => "Convert and log",
+            Self::Fail => "Fail",
+        }
+    }
+}
+
+/// Whether outputs carry the source's ICC profile and EXIF block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MetadataPolicy {
+    Strip,
+    /// Copy them from JPEG, PNG and WebP sources into JPEG and PNG outputs. Other formats
+    /// are written without them.
+    Preserve,
+}
+
+impl MetadataPolicy {
+    pub const ALL: [Self; 2] = [Self::Strip, Self::Preserve];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Strip => "Strip",
+            Self::Preserve => "Keep ICC profile and EXIF",
+        }
+    }
+}
+
+/// Resampling filter, a serializable stand-in for the image crate's `FilterType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResampleFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+
+impl ResampleFilter {
+    /// Fastest first, the same five `examples/filter_test.rs` compares.
+    pub const ALL: [Self; 5] = [
+        Self::Nearest,
+        Self::Triangle,
+        Self::CatmullRom,
+        Self::Gaussian,
+        Self::Lanczos3,
+    ];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Nearest => "Nearest",
+            Self::Triangle => "Triangle",
+            Self::CatmullRom => "Catmull-Rom",
+            Self::Gaussian => "Gaussian",
+            Self::Lanczos3 => "Lanczos3",
+        }
+    }
+
+    pub const fn filter_type(self) -> FilterType {
+        match self {
+            Self::Nearest => FilterType::Nearest,
+            Self::Triangle => FilterType::Triangle,
+            Self::CatmullRom => FilterType::CatmullRom,
+            Self::Gaussian => FilterType::Gaussian,
+            Self::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    pub const ALL: [Self; 4] = [
+        Self::TopLeft,
+        Self::TopRight,
+        Self::BottomLeft,
+        Self::BottomRight,
+    ];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::TopLeft => "Top left",
+            Self::TopRight => "Top right",
+            Self::BottomLeft => "Bottom left",
+            Self::BottomRight => "Bottom right",
+        }
+    }
+}
+
+/// How color is reduced to a single gray level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GrayscaleMode {
+    /// Mean of red, green and blue.
+    Average,
+    /// Rec. 709 luma, weighting each channel by how bright it looks.
+    Luminosity,
+    /// Midpoint of the brightest and darkest channel.
+    Lightness,
+}
+
+impl GrayscaleMode {
+    pub const ALL: [Self; 3] = [Self::Average, Self::Luminosity, Self::Lightness];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Average => "Average",
+            Self::Luminosity => "Luminosity (Rec. 709)",
+            Self::Lightness => "Lightness",
+        }
+    }
+
+    /// Gray level of a color with channels in `0.0..=1.0`.
+    pub fn gray(self, [r, g, b]: [f32; 3]) -> f32 {
+        match self {
+            Self::Average => (r + g + b) / 3.0,
+            Self::Luminosity => 0.0722_f32.mul_add(b, 0.2126_f32.mul_add(r, 0.7152 * g)),
+            Self::Lightness => r.max(g).max(b).midpoint(r.min(g).min(b)),
+        }
+    }
+}
+
+/// Shape cut out of the output, everything outside becomes transparent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MaskShape {
+    Circle,
+    /// Rounded rectangle with this corner radius in output pixels.
+    RoundedRect(u32),
+}
+
+impl MaskShape {
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Circle => "Circle",
+            Self::RoundedRect(_) => "Rounded corners",
+        }
+    }
+}
+
+/// Which image to resize from a TIFF or ICO that holds several.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MultiImageSelect {
+    /// The first image, what viewers show.
+    Primary,
+    /// The image with the most pixels.
+    Largest,
+    /// The image at this zero-based position.
+    Index(u32),
+}
+
+impl MultiImageSelect {
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Primary => "Primary",
+            Self::Largest => "Largest",
+            Self::Index(_) => "By index",
+        }
+    }
+}
+
+/// Largest JPEG to write, and the lowest quality that may be used to get there.
+/// When the floor quality is still too large, the output is made smaller instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteBudget {
+    pub max_bytes: u64,
+    pub min_quality: u8,
 }
 
+/// Resize to about this many pixels in total, keeping the source's aspect ratio, with both
+/// sides a multiple of `multiple` for encoders that need it.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PixelBudget {
+    pub pixels: u64,
+    pub multiple: u32,
+}
+
+/// Crop rectangle in fractions of the source size, applied before resizing.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NormalizedCrop {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl NormalizedCrop {
+    /// Converts to a pixel rectangle `(x, y, width, height)` that lies inside the source.
+    pub fn to_pixels(self, source_width: u32, source_height: u32) -> (u32, u32, u32, u32) {
+        let scale = |value: f32, size: u32| (value * size as f32).round() as u32;
+
+        let x = scale(self.x, source_width).min(source_width.saturating_sub(1));
+        let y = scale(self.y, source_height).min(source_height.saturating_sub(1));
+        let width = scale(self.width, source_width).clamp(1, source_width - x);
+        let height = scale(self.height, source_height).clamp(1, source_height - y);
+        (x, y, width, height)
+    }
+}
+
+impl FromStr for NormalizedCrop {
+    type Err = anyhow::Error;
+
+    /// Parses `x,y,width,height`, e.g. `0.1,0.1,0.8,0.8`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Allow for rounding in hand-written values such as 0.1 + 0.9
+        const TOLERANCE: f32 = 1e-4;
+
+        let values = s
+            .split(',')
+            .map(|part| {
+                part.trim()
+                    .parse::<f32>()
+                    .with_context(|| format!("Invalid crop value: {:?}", part.trim()))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let [x, y, width, height] = values[..] else {
+            anyhow::bail!("Crop needs four values: x,y,width,height");
+        };
+
+        anyhow::ensure!(
+            values.iter().all(|v| (0.0..=1.0).contains(v)),
+            "Crop values must be between 0 and 1"
+        );
+        anyhow::ensure!(
+            width > 0.0 && height > 0.0,
+            "Crop width and height must be greater than 0"
+        );
+        anyhow::ensure!(
+            x + width <= 1.0 + TOLERANCE && y + height <= 1.0 + TOLERANCE,
+            "Crop rectangle extends past the image"
+        );
+
+        Ok(Self {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
+// Each bool is an independent on/off setting, not a state better modeled as an enum
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct ResizePreset {
-    pub name: &'static str,
     pub width: u32,
     pub height: u32,
-    pub maintain_aspect_ratio: bool,
+    pub mode: ResizeMode,
+    /// Scale each image by this percentage of its own size instead of to `width` by `height`.
+    pub scale_percent: Option<u32>,
+    /// Keep sources that already fit inside the target at their size instead of enlarging them.
+    pub prevent_upscale: bool,
+    /// In `Fit` mode, center the output on a `background` canvas of exactly the target size.
+    pub pad_to_target: bool,
+    /// Straight (not premultiplied) RGBA color of the padding.
+    pub background: [u8; 4],
     pub output_format: OutputFormat,
+    /// Quality from 1 to 100 for JPEG outputs without a size budget or SSIM target.
+    pub jpeg_quality: u8,
+    /// Quality from 0 to 100 for lossy WebP outputs.
+    pub webp_quality: f32,
+    /// Write WebP losslessly, lossy WebP needs the `webp-lossy` feature.
+    pub webp_lossless: bool,
+    pub filter: ResampleFilter,
+    /// Filter for `Stretch` mode, e.g. a softer one to hide the distortion, `filter` when unset.
+    pub stretch_filter: Option<ResampleFilter>,
+    /// Resample with this kernel instead of `filter`.
+    pub custom_kernel: Option<Kernel>,
+    pub animation_policy: AnimationPolicy,
+    /// Sources with more pixels than this are resized with Triangle when `filter` is slower.
+    pub fast_filter_above_pixels: Option<u64>,
+    /// Burn the EXIF capture date into this corner of each output.
+    pub date_caption: Option<Corner>,
+    pub crop: Option<NormalizedCrop>,
+    /// Insert a JPEG restart marker every this many MCUs.
+    pub jpeg_restart_interval: Option<u16>,
+    /// Pick the lowest JPEG quality whose output reaches this SSIM against the resized image.
+    pub ssim_target: Option<f32>,
+    /// In `Stretch` mode, fit instead if the target ratio is within this fraction of the
+    /// source ratio.
+    pub aspect_tolerance: Option<f32>,
+    /// Applied after resizing, needs an output format with alpha to keep the transparency.
+    pub mask: Option<MaskShape>,
+    /// Desaturate outputs with this conversion.
+    pub grayscale: Option<GrayscaleMode>,
+    /// Applied in order to resized images, after the grayscale conversion.
+    pub adjustments: AdjustmentStack,
+    /// Reduce each color channel to this many levels, after the adjustments.
+    pub posterize: Option<u8>,
+    /// Dither when posterizing, trading banding for fine noise.
+    pub dither: bool,
+    /// Only change the area a `<stem>_mask.png` next to each source marks white. The
+    /// resized image is scaled back over the source and written at the source's size, the
+    /// crop and DPR variants don't apply.
+    pub region_mask: bool,
+    /// Take the target size from a `WxH` in each input's file name when it has one.
+    pub size_from_file_name: bool,
+    /// Log each output's average color and write a small `_swatch.png` of it next to the output.
+    pub average_color: bool,
+    /// Copy the source's XMP packet (ratings, keywords, rights) into JPEG, PNG and WebP outputs.
+    pub preserve_xmp: bool,
+    /// Keep the source's ICC profile and EXIF, including any GPS position, or strip them.
+    pub metadata: MetadataPolicy,
+    /// Straighten slightly rotated scans before cropping and resizing.
+    pub deskew: bool,
+    /// Treat each input as a scan of several prints and write every photo found on it as its
+    /// own straightened, resized output.
+    pub split_photos: bool,
+    /// Image taken from multi-image TIFF and ICO inputs.
+    pub multi_image: MultiImageSelect,
+    /// Keep JPEG outputs under a size, takes precedence over the SSIM target.
+    pub byte_budget: Option<ByteBudget>,
+    /// Also write `@2x` and `@3x` outputs at two and three times the target size.
+    pub dpr_variants: bool,
+    /// Shrink by exact whole-number factors (1/2, 1/4, ...) with a box average instead of the filter.
+    pub area_average_integer_factors: bool,
+    /// Size outputs by total pixel count instead of width and height.
+    pub pixel_budget: Option<PixelBudget>,
+    pub incompatible_output: IncompatibleOutput,
+    /// Write a `.jpg` next to every WebP output for clients without WebP support.
+    pub jpeg_fallback: bool,
+    /// Write a tiny `_lqip` copy of every output, a placeholder pages show while the full
+    /// image loads. None of the formats has room for an embedded preview.
+    pub lqip: bool,
+    /// Skip sources whose Laplacian variance is below this, see `sharpness::laplacian_variance`.
+    pub min_sharpness: Option<f32>,
+    /// Encoder knobs the other settings don't cover, from raw `key=value` parameters.
+    pub encoder_params: EncoderParams,
+    /// With the `seam-carving` feature, targets of a different aspect ratio are seam carved
+    /// instead of stretched when the image to carve has at most this many pixels.
+    pub seam_carving_max_pixels: Option<u64>,
+    /// Give up on an image that takes longer than this to decode, resize and encode.
+    pub timeout: Option<Duration>,
+    /// Resize at most this many images at once instead of one per core. Each holds its
+    /// decoded pixels in memory while it is worked on.
+    pub max_parallel_images: Option<usize>,
+    /// Read inputs on this many threads of their own and hand them to the resizing threads,
+    /// so slow storage is read from while earlier images are resized.
+    pub max_parallel_reads: Option<usize>,
+    /// Turn and mirror sources as their EXIF orientation says before resizing.
+    pub respect_exif_orientation: bool,
 }
 
 impl ResizePreset {
+    /// Defaults for every setting, presets and custom sizes override what they need.
+    pub const BASE: Self = Self {
+        width: 0,
+        height: 0,
+        mode: ResizeMode::Fit,
+        scale_percent: None,
+        prevent_upscale: false,
+        pad_to_target: false,
+        background: [255, 255, 255, 255],
+        output_format: OutputFormat::KeepOriginal,
+        jpeg_quality: 85,
+        webp_quality: 80.0,
+        webp_lossless: true,
+        filter: ResampleFilter::Lanczos3,
+        stretch_filter: None,
+        animation_policy: AnimationPolicy::FirstFrame,
+        fast_filter_above_pixels: None,
+        date_caption: None,
+        crop: None,
+        jpeg_restart_interval: None,
+        ssim_target: None,
+        aspect_tolerance: None,
+        mask: None,
+        size_from_file_name: false,
+        average_color: false,
+        preserve_xmp: false,
+        metadata: MetadataPolicy::Strip,
+        deskew: false,
+        split_photos: false,
+        multi_image: MultiImageSelect::Primary,
+        byte_budget: None,
+        dpr_variants: false,
+        area_average_integer_factors: true,
+        pixel_budget: None,
+        incompatible_output: IncompatibleOutput::Convert,
+        jpeg_fallback: false,
+        lqip: false,
+        min_sharpness: None,
+        encoder_params: EncoderParams::NONE,
+        seam_carving_max_pixels: None,
+        timeout: None,
+        max_parallel_images: None,
+        max_parallel_reads: None,
+        respect_exif_orientation: true,
+        custom_kernel: None,
+        grayscale: None,
+        adjustments: AdjustmentStack::EMPTY,
+        posterize: None,
+        dither: false,
+        region_mask: false,
+    };
+
+    /// The preset to use for `input`, with the size from its file name if enabled and present.
+    pub fn for_input(&self, input: &Path) -> Self {
+        if !self.size_from_file_name {
+            return *self;
+        }
+        match naming::size_from_file_name(input) {
+            Some((width, height)) => Self {
+                width,
+                height,
+                scale_percent: None,
+                ..*self
+            },
+            None => *self,
+        }
+    }
+}
+
+/// A size and format under a name, as picked from the preset list. Built-in presets and the
+/// user's own ones saved to `presets.json` are both this.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamedPreset {
+    pub name: Cow<'static, str>,
+    pub width: u32,
+    pub height: u32,
+    pub mode: ResizeMode,
+    pub output_format: OutputFormat,
+    /// Makes this a relative preset that scales each image instead of using the size above.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale_percent: Option<u32>,
+}
+
+impl NamedPreset {
     pub const PRESETS: &'static [Self] = &[
         Self {
-            name: "340×570",
+            name: Cow::Borrowed("340×570"),
             width: 340,
             height: 570,
-            maintain_aspect_ratio: true,
+            mode: ResizeMode::Fit,
             output_format: OutputFormat::Png,
+            scale_percent: None,
         },
         Self {
-            name: "1040×570",
+            name: Cow::Borrowed("1040×570"),
             width: 1040,
             height: 570,
-            maintain_aspect_ratio: true,
+            mode: ResizeMode::Fit,
             output_format: OutputFormat::Png,
+            scale_percent: None,
         },
         Self {
-            name: "Instagram Square",
+            name: Cow::Borrowed("Instagram Square"),
             width: 1080,
             height: 1080,
-            maintain_aspect_ratio: false,
+            mode: ResizeMode::Fill,
             output_format: OutputFormat::KeepOriginal,
+            scale_percent: None,
         },
         Self {
-            name: "Instagram Story",
+            name: Cow::Borrowed("Instagram Story"),
             width: 1080,
             height: 1920,
-            maintain_aspect_ratio: false,
+            mode: ResizeMode::Fill,
             output_format: OutputFormat::KeepOriginal,
+            scale_percent: None,
         },
         Self {
-            name: "Facebook Cover",
+            name: Cow::Borrowed("Facebook Cover"),
             width: 820,
             height: 312,
-            maintain_aspect_ratio: false,
+            mode: ResizeMode::Fill,
             output_format: OutputFormat::KeepOriginal,
+            scale_percent: None,
         },
         Self {
-            name: "Twitter Header",
+            name: Cow::Borrowed("Twitter Header"),
             width: 1500,
             height: 500,
-            maintain_aspect_ratio: false,
+            mode: ResizeMode::Fill,
             output_format: OutputFormat::KeepOriginal,
+            scale_percent: None,
         },
         Self {
-            name: "YouTube Thumbnail",
+            name: Cow::Borrowed("YouTube Thumbnail"),
             width: 1280,
             height: 720,
-            maintain_aspect_ratio: false,
+            mode: ResizeMode::Fill,
             output_format: OutputFormat::KeepOriginal,
+            scale_percent: None,
         },
         Self {
-            name: "HD 1080p",
+            name: Cow::Borrowed("HD 1080p"),
             width: 1920,
             height: 1080,
-            maintain_aspect_ratio: true,
+            mode: ResizeMode::Fit,
             output_format: OutputFormat::KeepOriginal,
+            scale_percent: None,
         },
         Self {
-            name: "HD 720p",
+            name: Cow::Borrowed("HD 720p"),
             width: 1280,
             height: 720,
-            maintain_aspect_ratio: true,
+            mode: ResizeMode::Fit,
             output_format: OutputFormat::KeepOriginal,
+            scale_percent: None,
         },
         Self {
-            name: "Small Web",
+            name: Cow::Borrowed("Small Web"),
             width: 800,
             height: 600,
-            maintain_aspect_ratio: true,
+            mode: ResizeMode::Fit,
             output_format: OutputFormat::KeepOriginal,
+            scale_percent: None,
         },
         Self {
-            name: "Thumbnail",
+            name: Cow::Borrowed("Thumbnail"),
             width: 150,
             height: 150,
-            maintain_aspect_ratio: true,
+            mode: ResizeMode::Fit,
             output_format: OutputFormat::KeepOriginal,
+            scale_percent: None,
+        },
+        Self {
+            name: Cow::Borrowed("Half"),
+            width: 0,
+            height: 0,
+            mode: ResizeMode::Fit,
+            output_format: OutputFormat::KeepOriginal,
+            scale_percent: Some(50),
+        },
+        Self {
+            name: Cow::Borrowed("Quarter"),
+            width: 0,
+            height: 0,
+            mode: ResizeMode::Fit,
+            output_format: OutputFormat::KeepOriginal,
+            scale_percent: Some(25),
+        },
+        Self {
+            name: Cow::Borrowed("Tenth"),
+            width: 0,
+            height: 0,
+            mode: ResizeMode::Fit,
+            output_format: OutputFormat::KeepOriginal,
+            scale_percent: Some(10),
         },
     ];
+
+    /// The resize settings for this preset, everything it doesn't name keeps its default.
+    pub const fn preset(&self) -> ResizePreset {
+        ResizePreset {
+            width: self.width,
+            height: self.height,
+            mode: self.mode,
+            output_format: self.output_format,
+            scale_percent: self.scale_percent,
+            ..ResizePreset::BASE
+        }
+    }
+
+    /// `800x600`, or `50%` for a relative preset.
+    pub fn size_label(&self) -> String {
+        self.scale_percent.map_or_else(
+            || format!("{}x{}", self.width, self.height),
+            |percent| format!("{percent}%"),
+        )
+    }
+
+    /// The preset in `presets` closest to `width`x`height`, if one is close enough to be what
+    /// the user probably meant.
+    pub fn nearest(presets: &[Self], width: u32, height: u32) -> Option<&Self> {
+        // Relative size difference per side plus the difference in aspect ratio
+        const MAX_DISTANCE: f64 = 0.1;
+
+        let (width, height) = (f64::from(width), f64::from(height));
+        if width == 0.0 || height == 0.0 {
+            return None;
+        }
+
+        presets
+            .iter()
+            .filter(|preset| preset.scale_percent.is_none())
+            .map(|preset| {
+                let (preset_width, preset_height) =
+                    (f64::from(preset.width), f64::from(preset.height));
+                let distance = (width - preset_width).abs() / preset_width
+                    + (height - preset_height).abs() / preset_height
+                    + ((width / height) / (preset_width / preset_height) - 1.0).abs();
+                (preset, distance)
+            })
+            .filter(|(_, distance)| *distance <= MAX_DISTANCE)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(preset, _)| preset)
+    }
 }
 
-impl Default for ResizePreset {
-    fn default() -> Self {
-        Self::PRESETS[0]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resizer::ImageResizer;
+
+    #[test]
+    fn normalized_crop_maps_to_source_pixels() {
+        let crop: NormalizedCrop = "0.1,0.1,0.8,0.8".parse().unwrap();
+        assert_eq!(crop.to_pixels(1000, 500), (100, 50, 800, 400));
+
+        let corner: NormalizedCrop = " 0.5, 0.25 ,0.5,0.75".parse().unwrap();
+        assert_eq!(corner.to_pixels(640, 480), (320, 120, 320, 360));
+
+        for invalid in ["0.1,0.1,0.8", "0.5,0,0.6,1", "0,0,0,1", "a,b,c,d"] {
+            assert!(invalid.parse::<NormalizedCrop>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn near_miss_snaps_to_instagram_square() {
+        let nearest = NamedPreset::nearest(NamedPreset::PRESETS, 1070, 1075).unwrap();
+        assert_eq!(nearest.name, "Instagram Square");
+
+        assert_eq!(
+            NamedPreset::nearest(NamedPreset::PRESETS, 1900, 1085)
+                .unwrap()
+                .name,
+            "HD 1080p"
+        );
+        assert!(NamedPreset::nearest(NamedPreset::PRESETS, 333, 777).is_none());
+        assert!(NamedPreset::nearest(NamedPreset::PRESETS, 0, 1080).is_none());
+    }
+
+    #[test]
+    fn half_preset_halves_each_side() {
+        let half = NamedPreset::PRESETS
+            .iter()
+            .find(|preset| preset.name == "Half")
+            .unwrap();
+        assert_eq!(half.size_label(), "50%");
+
+        let preset = half.preset();
+        assert_eq!(
+            ImageResizer::target_size((1000, 600), &preset).0,
+            (500, 300)
+        );
+        assert_eq!(
+            ImageResizer::output_dimensions((1000, 600), &preset),
+            (500, 300)
+        );
     }
 }
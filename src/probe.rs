@@ -0,0 +1,199 @@
+use anyhow::{Context, Result};
+use image::{ExtendedColorType, ImageDecoder, ImageFormat, ImageReader};
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::path::Path;
+
+use crate::metadata;
+use crate::presets::{OutputFormat, ResizePreset};
+use crate::resizer::ImageResizer;
+
+/// What an image's header says about it, read without decoding the pixels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceInfo {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    pub color_type: ExtendedColorType,
+    /// EXIF orientation from 1 (upright) to 8, `None` without EXIF.
+    pub orientation: Option<u32>,
+}
+
+impl SourceInfo {
+    /// Size `preset` resizes from: as displayed, with the EXIF orientation's quarter turn
+    /// applied, unless the preset ignores the orientation.
+    pub fn source_dimensions(&self, preset: &ResizePreset) -> (u32, u32) {
+        if preset.respect_exif_orientation {
+            oriented((self.width, self.height), self.orientation)
+        } else {
+            (self.width, self.height)
+        }
+    }
+}
+
+/// Size `preset` resizes an encoded image from, read from its header like
+/// `SourceInfo::source_dimensions`. `None` if the header can't be read.
+pub fn source_dimensions(bytes: &[u8], preset: &ResizePreset) -> Option<(u32, u32)> {
+    let dimensions = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()?;
+    if preset.respect_exif_orientation {
+        Some(oriented(
+            dimensions,
+            metadata::read_orientation(&mut Cursor::new(bytes)),
+        ))
+    } else {
+        Some(dimensions)
+    }
+}
+
+/// Orientations 5 to 8 turn the image a quarter, swapping its sides.
+fn oriented((width, height): (u32, u32), orientation: Option<u32>) -> (u32, u32) {
+    if orientation.is_some_and(|orientation| orientation >= 5) {
+        (height, width)
+    } else {
+        (width, height)
+    }
+}
+
+/// Reads the format, size, color type and EXIF orientation of an image file.
+pub fn probe(path: &Path) -> Result<SourceInfo> {
+    let reader = ImageReader::open(path)
+        .and_then(ImageReader::with_guessed_format)
+        .with_context(|| format!("Failed to open image: {}", path.display()))?;
+    let format = reader
+        .format()
+        .with_context(|| format!("Unrecognized image data: {}", path.display()))?;
+    let decoder = reader
+        .into_decoder()
+        .with_context(|| format!("Failed to read image header: {}", path.display()))?;
+    let (width, height) = decoder.dimensions();
+    let color_type = decoder.original_color_type();
+
+    let orientation = File::open(path)
+        .ok()
+        .and_then(|file| metadata::read_orientation(&mut BufReader::new(file)));
+
+    Ok(SourceInfo {
+        format,
+        width,
+        height,
+        color_type,
+        orientation,
+    })
+}
+
+/// Display name of a format, such as `JPEG`.
+pub fn format_name(format: ImageFormat) -> String {
+    format!("{format:?}").to_uppercase()
+}
+
+/// Rows of `(property, source, output)` comparing a source with what `preset` makes of it.
+/// The output column is empty for properties the resize doesn't decide.
+pub fn describe(info: &SourceInfo, preset: &ResizePreset) -> Vec<(&'static str, String, String)> {
+    let output_format = match preset.output_format {
+        OutputFormat::KeepOriginal => format_name(info.format),
+        output_format => format!("{output_format:?}").to_uppercase(),
+    };
+    let (width, height) = info.source_dimensions(preset);
+    let (output_width, output_height) = ImageResizer::output_dimensions((width, height), preset);
+
+    let channels = info.color_type.channel_count();
+    let bits = info.color_type.bits_per_pixel();
+    let bit_depth = if channels > 0 && bits.is_multiple_of(u16::from(channels)) {
+        format!("{} bits per channel", bits / u16::from(channels))
+    } else {
+        format!("{bits} bits per pixel")
+    };
+
+    let orientation = match info.orientation {
+        None => "none".to_string(),
+        Some(1) => "1 (upright)".to_string(),
+        Some(orientation) => format!("{orientation} (rotated or mirrored)"),
+    };
+
+    vec![
+        ("Format", format_name(info.format), output_format),
+        (
+            "Dimensions",
+            format!("{width}x{height}"),
+            format!("{output_width}x{output_height}"),
+        ),
+        ("Bit depth", bit_depth, String::new()),
+        (
+            "Color type",
+            format!("{:?}", info.color_type),
+            String::new(),
+        ),
+        ("EXIF orientation", orientation, String::new()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use image::{DynamicImage, Rgb, RgbImage};
+
+    #[test]
+    fn describes_a_sixteen_bit_png_and_its_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("deep.png");
+        let img = DynamicImage::ImageRgb16(
+            DynamicImage::ImageRgb8(RgbImage::from_pixel(400, 200, Rgb([10, 20, 30]))).to_rgb16(),
+        );
+        std::fs::write(&path, test_util::encode(&img, ImageFormat::Png)).unwrap();
+
+        let info = probe(&path).unwrap();
+        assert_eq!(
+            info,
+            SourceInfo {
+                format: ImageFormat::Png,
+                width: 400,
+                height: 200,
+                color_type: ExtendedColorType::Rgb16,
+                orientation: None,
+            }
+        );
+
+        let preset = ResizePreset {
+            width: 100,
+            height: 100,
+            output_format: OutputFormat::Jpeg,
+            ..ResizePreset::BASE
+        };
+        assert_eq!(
+            describe(&info, &preset),
+            [
+                ("Format", "PNG".to_string(), "JPEG".to_string()),
+                ("Dimensions", "400x200".to_string(), "100x50".to_string()),
+                (
+                    "Bit depth",
+                    "16 bits per channel".to_string(),
+                    String::new()
+                ),
+                ("Color type", "Rgb16".to_string(), String::new()),
+                ("EXIF orientation", "none".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_the_exif_orientation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rotated.jpg");
+        let jpeg = test_util::jpeg_with_exif(
+            &test_util::gradient(30, 20),
+            &[test_util::exif_orientation(6)],
+        );
+        std::fs::write(&path, jpeg).unwrap();
+
+        let info = probe(&path).unwrap();
+        assert_eq!(info.orientation, Some(6));
+        let rows = describe(&info, &ResizePreset::BASE);
+        assert_eq!(rows[4].1, "6 (rotated or mirrored)");
+        assert_eq!(rows[2].1, "8 bits per channel");
+    }
+}
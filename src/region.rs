@@ -0,0 +1,43 @@
+use anyhow::{ensure, Context, Result};
+use image::{DynamicImage, GrayImage};
+use std::path::{Path, PathBuf};
+
+use crate::color;
+
+/// Mask for `input` in the same folder, `photo.jpg` is masked by `photo_mask.png`.
+pub fn mask_path(input: &Path) -> PathBuf {
+    let mut name = input.file_stem().unwrap_or_default().to_os_string();
+    name.push("_mask.png");
+    input.with_file_name(name)
+}
+
+/// Reads the mask for `input` as gray levels, it must be exactly `width` by `height`.
+pub fn load_mask(input: &Path, (width, height): (u32, u32)) -> Result<GrayImage> {
+    let path = mask_path(input);
+    let mask = image::open(&path)
+        .with_context(|| format!("Failed to open mask: {}", path.display()))?
+        .to_luma8();
+    ensure!(
+        mask.dimensions() == (width, height),
+        "Mask {} is {}x{} but the image is {width}x{height}",
+        path.display(),
+        mask.width(),
+        mask.height()
+    );
+    Ok(mask)
+}
+
+/// `processed` where `mask` is white and `original` where it is black, mixed in proportion
+/// for the grays between. Both images must have the mask's size. The result keeps the
+/// original's color type.
+pub fn blend(original: &DynamicImage, processed: &DynamicImage, mask: &GrayImage) -> DynamicImage {
+    let mut blended = original.to_rgba32f();
+    let processed = processed.to_rgba32f();
+    for ((x, y, pixel), weight) in blended.enumerate_pixels_mut().zip(mask.pixels()) {
+        let weight = f32::from(weight.0[0]) / 255.0;
+        for (channel, target) in pixel.0.iter_mut().zip(processed.get_pixel(x, y).0) {
+            *channel = (target - *channel).mul_add(weight, *channel);
+        }
+    }
+    color::convert(&DynamicImage::ImageRgba32F(blended), original.color())
+}
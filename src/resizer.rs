@@ -1,68 +1,804 @@
 use anyhow::{Context, Result};
-use image::{DynamicImage, GenericImageView, ImageFormat};
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPDecoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{
+    AnimationDecoder, DynamicImage, Frame, GenericImageView, ImageEncoder, ImageFormat, Rgba,
+    RgbaImage,
+};
+use jpeg_encoder::ColorType;
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::ops::ControlFlow;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::presets::{OutputFormat, ResizePreset};
+use crate::area;
+use crate::caption;
+use crate::cmyk;
+use crate::color;
+use crate::compat;
+use crate::container;
+use crate::deskew;
+use crate::jobs::Job;
+use crate::kernel;
+use crate::mask;
+use crate::metadata::{self, MetadataSink};
+use crate::naming::{Naming, ReservedPaths};
+use crate::presets::{
+    AnimationPolicy, ByteBudget, Corner, MetadataPolicy, OutputFormat, PixelBudget, ResampleFilter,
+    ResizeMode, ResizePreset,
+};
+use crate::probe;
+use crate::region;
+#[cfg(feature = "seam-carving")]
+use crate::seam;
+use crate::sharpness;
+use crate::sink::{MemorySink, OutputSink, SizeSink};
+use crate::source::{InputSource, ReadAhead};
+use crate::split;
+use crate::ssim;
+use crate::xmp::{self, XmpSink};
+
+/// Device pixel ratios written next to the base output when DPR variants are on.
+const DPR_SCALES: [u32; 2] = [2, 3];
+/// Longest side of the low-quality placeholder written next to outputs.
+const LQIP_SIZE: u32 = 32;
+
+/// A file a batch wrote and its size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrittenFile {
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+/// Where a batch's inputs write, with the output paths they have claimed so far.
+struct BatchOutput<'a> {
+    dir: &'a Path,
+    naming: &'a Naming,
+    reserved: ReservedPaths,
+}
 
 pub struct ImageResizer;
 
 impl ImageResizer {
+    /// Resizes one encoded input and returns the paths of every file written.
     pub fn resize_image(
-        input_path: &Path,
+        identifier: &Path,
+        bytes: &[u8],
         output_path: &Path,
         preset: &ResizePreset,
-    ) -> Result<()> {
-        // Load the image
-        let img = image::open(input_path)
-            .with_context(|| format!("Failed to open image: {}", input_path.display()))?;
+        sink: &dyn OutputSink,
+        log: &dyn Fn(String),
+    ) -> Result<Vec<PathBuf>> {
+        if let Some(kernel) = preset.custom_kernel {
+            kernel.validate()?;
+        }
+        preset.adjustments.validate()?;
 
-        // Calculate new dimensions
-        let (new_width, new_height) = if preset.maintain_aspect_ratio {
-            Self::calculate_aspect_ratio_size(&img, preset.width, preset.height)
-        } else {
-            (preset.width, preset.height)
+        let input_format = Self::get_image_format(identifier).or_else(|_| {
+            image::guess_format(bytes)
+                .with_context(|| format!("Unrecognized image data: {}", identifier.display()))
+        })?;
+
+        // Determine output format from preset or the input format
+        let format = match preset.output_format {
+            OutputFormat::KeepOriginal => input_format,
+            output_format => Self::get_output_format(output_format, identifier)?,
         };
 
-        // Resize the image using highest quality filter
-        // Lanczos3 is the best choice for quality - excellent for both upscaling and downscaling
-        let resized_img = if preset.maintain_aspect_ratio {
-            img.resize(new_width, new_height, image::imageops::FilterType::Lanczos3)
+        // Previews, swatches and fallbacks are derived files, the source's EXIF (and its GPS
+        // position) and XMP only go into the outputs themselves
+        let side_sink = sink;
+        let metadata_sink = (preset.metadata == MetadataPolicy::Preserve)
+            .then(|| metadata::SourceMetadata::read(bytes, preset.respect_exif_orientation))
+            .flatten()
+            .map(|metadata| MetadataSink::new(sink, metadata));
+        let sink = metadata_sink
+            .as_ref()
+            .map_or(sink, |metadata_sink| metadata_sink as &dyn OutputSink);
+
+        let xmp_sink = preset
+            .preserve_xmp
+            .then(|| xmp::read_xmp(bytes))
+            .flatten()
+            .map(|packet| XmpSink::new(sink, packet));
+        let sink = xmp_sink
+            .as_ref()
+            .map_or(sink, |xmp_sink| xmp_sink as &dyn OutputSink);
+
+        let caption = preset.date_caption.and_then(|corner| {
+            let date = metadata::read_capture_date(bytes);
+            if date.is_none() {
+                log(format!(
+                    "{}: no EXIF capture date, caption skipped",
+                    identifier.file_name().unwrap_or_default().to_string_lossy()
+                ));
+            }
+            date.map(|date| (date.to_string(), corner))
+        });
+
+        if preset.mask.is_some() && format == ImageFormat::Jpeg {
+            log(format!(
+                "{}: JPEG has no transparency, the mask is flattened onto white",
+                identifier.file_name().unwrap_or_default().to_string_lossy()
+            ));
+        }
+
+        // GIF outputs stay animated, the other formats need an explicit decision
+        let img = if let Some(frames) =
+            Self::decode_animation_frames(identifier, bytes, input_format)?
+        {
+            if format == ImageFormat::Gif {
+                return Self::resize_gif_animation(
+                    bytes,
+                    frames,
+                    output_path,
+                    caption.as_ref(),
+                    preset,
+                    sink,
+                    log,
+                )
+                .map(|path| vec![path])
+                .with_context(|| format!("Failed to resize animation: {}", identifier.display()));
+            }
+            match preset.animation_policy {
+                AnimationPolicy::FirstFrame => {
+                    let first = frames
+                        .into_iter()
+                        .next()
+                        .context("Animation has no frames")?;
+                    DynamicImage::ImageRgba8(first.into_buffer())
+                }
+                AnimationPolicy::Error => anyhow::bail!(
+                    "{} is animated ({} frames) but {:?} output cannot store animation",
+                    identifier.display(),
+                    frames.len(),
+                    format
+                ),
+                AnimationPolicy::AllFramesNumbered => {
+                    let canvas = frames.first().map_or((0, 0), |f| f.buffer().dimensions());
+                    let filter = Self::select_filter(identifier, canvas, preset, log);
+                    let mut written = Vec::with_capacity(frames.len());
+                    for (index, frame) in frames.into_iter().enumerate() {
+                        let frame_img = DynamicImage::ImageRgba8(frame.into_buffer());
+                        let frame_path = Self::numbered_output_path(output_path, index + 1);
+                        let mut resized_img =
+                            Self::resize_dynamic_image(&frame_img, preset, filter);
+                        Self::decorate(&mut resized_img, caption.as_ref(), preset, format);
+                        Self::save_image_with_format(
+                            &resized_img,
+                            &frame_path,
+                            format,
+                            preset,
+                            sink,
+                            log,
+                        )?;
+                        written.push(frame_path);
+                    }
+                    return Ok(written);
+                }
+            }
+        } else if let Some(img) =
+            container::decode_selected(bytes, input_format, preset.multi_image)
+                .with_context(|| format!("Failed to decode image: {}", identifier.display()))?
+        {
+            img
+        } else if let Some(img) = Self::decode_cmyk(identifier, bytes, input_format)? {
+            img
         } else {
-            // Use resize_exact for non-aspect-ratio preserving resize
-            img.resize_exact(new_width, new_height, image::imageops::FilterType::Lanczos3)
+            // Decode the image
+            image::load_from_memory_with_format(bytes, input_format)
+                .with_context(|| format!("Failed to decode image: {}", identifier.display()))?
+        };
+
+        // Phones store portrait shots sideways and tag how to turn them
+        let orientation = preset
+            .respect_exif_orientation
+            .then(|| metadata::read_orientation(&mut Cursor::new(bytes)))
+            .flatten()
+            .and_then(|orientation| Orientation::from_exif(orientation as u8));
+        let img = match orientation {
+            Some(orientation) => {
+                let mut img = img;
+                img.apply_orientation(orientation);
+                img
+            }
+            None => img,
         };
 
-        // Determine output format from preset or file extension
-        let format = Self::get_output_format(preset.output_format, input_path)?;
+        if let Some(min_sharpness) = preset.min_sharpness {
+            let sharpness = sharpness::laplacian_variance(&img);
+            if sharpness < f64::from(min_sharpness) {
+                log(format!(
+                    "{}: too blurry, skipped (sharpness {sharpness:.1}, minimum {min_sharpness})",
+                    identifier.file_name().unwrap_or_default().to_string_lossy()
+                ));
+                return Ok(Vec::new());
+            }
+        }
+
+        let img = preset
+            .deskew
+            .then(|| deskew::deskew(&img))
+            .flatten()
+            .map_or(img, |(straightened, angle)| {
+                log(format!(
+                    "{}: straightened by {angle:.2}°",
+                    identifier.file_name().unwrap_or_default().to_string_lossy()
+                ));
+                straightened
+            });
+
+        let filter = Self::select_filter(identifier, img.dimensions(), preset, log);
+
+        if preset.split_photos {
+            let name = identifier.file_name().unwrap_or_default().to_string_lossy();
+            let regions = split::find_photos(&img);
+            if regions.is_empty() {
+                log(format!("{name}: no photos found, resized as a whole"));
+            } else {
+                log(format!("{name}: {} photos found", regions.len()));
+                // The crop applies to the scan, each photo is resized whole
+                let uncropped = ResizePreset {
+                    crop: None,
+                    ..*preset
+                };
+                let mut written = Vec::with_capacity(regions.len());
+                for (index, (x, y, width, height)) in regions.into_iter().enumerate() {
+                    let photo = img.crop_imm(x, y, width, height);
+                    let photo =
+                        deskew::deskew(&photo).map_or(photo, |(straightened, _)| straightened);
+                    let filter = Self::select_filter(identifier, photo.dimensions(), preset, log);
+                    let mut resized = Self::resize_dynamic_image(&photo, &uncropped, filter);
+                    Self::decorate(&mut resized, caption.as_ref(), preset, format);
+                    let photo_path = Self::numbered_output_path(output_path, index + 1);
+                    written.extend(Self::save_with_fallback(
+                        &resized,
+                        &photo_path,
+                        format,
+                        preset,
+                        sink,
+                        side_sink,
+                        log,
+                    )?);
+                }
+                return Ok(written);
+            }
+        }
+
+        if preset.region_mask {
+            let mask = region::load_mask(identifier, img.dimensions())?;
+            let uncropped = ResizePreset {
+                crop: None,
+                ..*preset
+            };
+            let mut processed = Self::resize_dynamic_image(&img, &uncropped, filter);
+            Self::decorate(&mut processed, caption.as_ref(), preset, format);
+            let processed = processed.resize_exact(img.width(), img.height(), filter);
+            let blended = region::blend(&img, &processed, &mask);
+            return Self::save_with_fallback(
+                &blended,
+                output_path,
+                format,
+                preset,
+                sink,
+                side_sink,
+                log,
+            );
+        }
+
+        let mut resized_img = Self::resize_dynamic_image(&img, preset, filter);
+        Self::decorate(&mut resized_img, caption.as_ref(), preset, format);
 
         // Save the resized image with appropriate quality settings
-        Self::save_image_with_format(&resized_img, output_path, format)?;
+        let mut written = Self::save_with_fallback(
+            &resized_img,
+            output_path,
+            format,
+            preset,
+            sink,
+            side_sink,
+            log,
+        )?;
 
-        Ok(())
+        if preset.lqip {
+            // Size budgets and SSIM targets are meant for the real output
+            let lqip_preset = ResizePreset {
+                byte_budget: None,
+                ssim_target: None,
+                ..*preset
+            };
+            let lqip_path = Self::suffixed_output_path(output_path, "_lqip");
+            Self::save_image_with_format(
+                &resized_img.thumbnail(LQIP_SIZE, LQIP_SIZE),
+                &lqip_path,
+                format,
+                &lqip_preset,
+                side_sink,
+                log,
+            )?;
+            written.push(lqip_path);
+        }
+
+        if preset.dpr_variants {
+            let (source_width, source_height) = preset.crop.map_or_else(
+                || img.dimensions(),
+                |crop| {
+                    let (_, _, width, height) = crop.to_pixels(img.width(), img.height());
+                    (width, height)
+                },
+            );
+            for scale in DPR_SCALES {
+                let variant_preset = ResizePreset {
+                    width: preset.width.saturating_mul(scale),
+                    height: preset.height.saturating_mul(scale),
+                    scale_percent: preset
+                        .scale_percent
+                        .map(|percent| percent.saturating_mul(scale)),
+                    // Upscaled variants are skipped below rather than kept at the source size
+                    prevent_upscale: false,
+                    ..*preset
+                };
+                let mut variant = Self::resize_dynamic_image(&img, &variant_preset, filter);
+                // Variants exist to be sharper, an upscaled one only costs bandwidth
+                if variant.width() > source_width || variant.height() > source_height {
+                    log(format!(
+                        "{}: too small for an @{scale}x variant, skipped",
+                        identifier.file_name().unwrap_or_default().to_string_lossy()
+                    ));
+                    break;
+                }
+                Self::decorate(&mut variant, caption.as_ref(), &variant_preset, format);
+
+                let variant_path = Self::suffixed_output_path(output_path, &format!("@{scale}x"));
+                written.extend(Self::save_with_fallback(
+                    &variant,
+                    &variant_path,
+                    format,
+                    preset,
+                    sink,
+                    side_sink,
+                    log,
+                )?);
+            }
+        }
+
+        if preset.average_color {
+            let average = color::average_color(&resized_img);
+            log(format!(
+                "{}: average color {}",
+                identifier.file_name().unwrap_or_default().to_string_lossy(),
+                color::to_hex(average)
+            ));
+
+            let mut swatch_name = output_path.file_stem().unwrap_or_default().to_os_string();
+            swatch_name.push("_swatch.png");
+            let swatch_path = output_path.with_file_name(swatch_name);
+            Self::save_image_with_format(
+                &color::swatch(average),
+                &swatch_path,
+                ImageFormat::Png,
+                preset,
+                side_sink,
+                log,
+            )?;
+            written.push(swatch_path);
+        }
+
+        Ok(written)
     }
 
-    fn calculate_aspect_ratio_size(
+    /// Print workflows produce CMYK JPEGs, which get their own conversion to RGB.
+    fn decode_cmyk(
+        identifier: &Path,
+        bytes: &[u8],
+        format: ImageFormat,
+    ) -> Result<Option<DynamicImage>> {
+        if format != ImageFormat::Jpeg {
+            return Ok(None);
+        }
+        cmyk::decode_cmyk_jpeg(bytes)
+            .with_context(|| format!("Failed to decode image: {}", identifier.display()))
+    }
+
+    /// Desaturates, adjusts, posterizes, draws the caption and applies the mask on a resized image.
+    fn decorate(
+        img: &mut DynamicImage,
+        caption: Option<&(String, Corner)>,
+        preset: &ResizePreset,
+        format: ImageFormat,
+    ) {
+        if let Some(mode) = preset.grayscale {
+            *img = color::to_grayscale(img, mode);
+        }
+        if !preset.adjustments.is_empty() {
+            *img = preset.adjustments.apply(img);
+        }
+        if let Some(levels) = preset.posterize {
+            *img = color::posterize(img, levels, preset.dither);
+        }
+        if let Some((text, corner)) = caption {
+            caption::draw_caption(img, text, *corner);
+        }
+        if let Some(shape) = preset.mask {
+            mask::apply_mask(img, shape);
+            if format == ImageFormat::Jpeg {
+                mask::flatten_alpha(img);
+            }
+        }
+    }
+
+    fn resize_dynamic_image(
         img: &DynamicImage,
-        target_width: u32,
-        target_height: u32,
-    ) -> (u32, u32) {
-        let (original_width, original_height) = img.dimensions();
-        let original_aspect_ratio = f64::from(original_width) / f64::from(original_height);
+        preset: &ResizePreset,
+        filter: FilterType,
+    ) -> DynamicImage {
+        let resized = Self::scale_dynamic_image(img, preset, filter);
+        if Self::pads_to_target(preset) {
+            Self::pad(&resized, (preset.width, preset.height), preset.background)
+        } else {
+            resized
+        }
+    }
 
-        // Always set the smaller side to the target, and calculate the other to preserve aspect ratio
-        if target_width <= target_height {
-            // Width is the smaller side
-            let width = target_width;
-            let height = (f64::from(width) / original_aspect_ratio).round() as u32;
-            (width, height)
+    /// Whether outputs are letterboxed to exactly the preset's size.
+    pub fn pads_to_target(preset: &ResizePreset) -> bool {
+        preset.pad_to_target && preset.mode == ResizeMode::Fit && preset.scale_percent.is_none()
+    }
+
+    /// `img` centered on a canvas of `size` filled with `background`.
+    fn pad(img: &DynamicImage, (width, height): (u32, u32), background: [u8; 4]) -> DynamicImage {
+        if img.dimensions() == (width, height) {
+            return img.clone();
+        }
+        let mut canvas = RgbaImage::from_pixel(width, height, Rgba(background));
+        let x = i64::from(width.saturating_sub(img.width()) / 2);
+        let y = i64::from(height.saturating_sub(img.height()) / 2);
+        image::imageops::overlay(&mut canvas, &img.to_rgba8(), x, y);
+
+        // Opaque padding around an opaque image needs no alpha channel, e.g. for JPEG
+        if background[3] == u8::MAX && !img.color().has_alpha() {
+            DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(canvas).to_rgb8())
         } else {
-            // Height is the smaller side
-            let height = target_height;
-            let width = (f64::from(height) * original_aspect_ratio).round() as u32;
+            DynamicImage::ImageRgba8(canvas)
+        }
+    }
+
+    /// Scales `img` to the preset's target, without padding.
+    fn scale_dynamic_image(
+        img: &DynamicImage,
+        preset: &ResizePreset,
+        filter: FilterType,
+    ) -> DynamicImage {
+        let img = preset.crop.map_or(Cow::Borrowed(img), |crop| {
+            let (x, y, width, height) = crop.to_pixels(img.width(), img.height());
+            Cow::Owned(img.crop_imm(x, y, width, height))
+        });
+
+        let ((new_width, new_height), mode) = Self::target_size(img.dimensions(), preset);
+        if (new_width, new_height) == img.dimensions() {
+            return img.into_owned();
+        }
+
+        #[cfg(feature = "seam-carving")]
+        if let Some(carved) = Self::seam_carve(&img, (new_width, new_height), mode, preset, filter)
+        {
+            return carved;
+        }
+
+        // Whole-number shrink factors are averaged exactly instead of filtered, unless
+        // filling needs the sides scaled alike
+        let (factor_x, factor_y) = (img.width() / new_width, img.height() / new_height);
+        if preset.area_average_integer_factors
+            && factor_x * new_width == img.width()
+            && factor_y * new_height == img.height()
+            && factor_x * factor_y > 1
+            && (mode != ResizeMode::Fill || factor_x == factor_y)
+        {
+            return area::downscale(&img, factor_x, factor_y);
+        }
+
+        if let Some(custom) = preset.custom_kernel {
+            return match mode {
+                ResizeMode::Fill => kernel::resize_to_fill(&img, new_width, new_height, custom),
+                ResizeMode::Fit | ResizeMode::Stretch => {
+                    kernel::resize(&img, new_width, new_height, custom)
+                }
+            };
+        }
+
+        match mode {
+            // The size already keeps the ratio, fitting it again could round a side down
+            ResizeMode::Fit => img.resize_exact(new_width, new_height, filter),
+            // Scales to cover the target and crops the overflow around the center
+            ResizeMode::Fill => img.resize_to_fill(new_width, new_height, filter),
+            ResizeMode::Stretch => {
+                // Unless the large-source fallback already swapped the filter for Triangle
+                let filter = preset
+                    .stretch_filter
+                    .filter(|_| filter == preset.filter.filter_type())
+                    .map_or(filter, ResampleFilter::filter_type);
+                img.resize_exact(new_width, new_height, filter)
+            }
+        }
+    }
+
+    /// Scales uniformly until the image covers the target, then carves the excess away.
+    /// `None` when seam carving is off, the ratio doesn't change, or the scaled image is over
+    /// the preset's pixel cap.
+    #[cfg(feature = "seam-carving")]
+    fn seam_carve(
+        img: &DynamicImage,
+        (width, height): (u32, u32),
+        mode: ResizeMode,
+        preset: &ResizePreset,
+        filter: FilterType,
+    ) -> Option<DynamicImage> {
+        let max_pixels = preset
+            .seam_carving_max_pixels
+            .filter(|_| mode == ResizeMode::Stretch)?;
+
+        let scale = (f64::from(width) / f64::from(img.width()))
+            .max(f64::from(height) / f64::from(img.height()));
+        let covering_width = ((f64::from(img.width()) * scale).round() as u32).max(width);
+        let covering_height = ((f64::from(img.height()) * scale).round() as u32).max(height);
+        if (covering_width, covering_height) == (width, height)
+            || u64::from(covering_width) * u64::from(covering_height) > max_pixels
+        {
+            return None;
+        }
+
+        let covering = img.resize_exact(covering_width, covering_height, filter);
+        Some(seam::carve(&covering, width, height))
+    }
+
+    /// Size an already cropped source is resized to, and the mode that gets it there.
+    pub fn target_size(source: (u32, u32), preset: &ResizePreset) -> ((u32, u32), ResizeMode) {
+        if let Some(percent) = preset.scale_percent {
+            if preset.prevent_upscale && percent >= 100 {
+                return (source, ResizeMode::Fit);
+            }
+            let scale =
+                |side: u32| ((f64::from(side) * f64::from(percent) / 100.0).round() as u32).max(1);
+            return ((scale(source.0), scale(source.1)), ResizeMode::Stretch);
+        }
+
+        // A zero-sized target can't be encoded, the smallest valid output is 1 pixel
+        let (target_width, target_height) = preset.pixel_budget.map_or_else(
+            || (preset.width.max(1), preset.height.max(1)),
+            |budget| Self::pixel_budget_size(source, budget),
+        );
+
+        // A source that fits inside the target in both directions keeps its size
+        if preset.prevent_upscale && target_width >= source.0 && target_height >= source.1 {
+            return (source, ResizeMode::Fit);
+        }
+        // The budget already keeps the source's ratio
+        if preset.pixel_budget.is_some() {
+            return ((target_width, target_height), ResizeMode::Stretch);
+        }
+
+        let mode = match preset.mode {
+            ResizeMode::Stretch
+                if preset.aspect_tolerance.is_some_and(|tolerance| {
+                    Self::aspect_within_tolerance(source, target_width, target_height, tolerance)
+                }) =>
+            {
+                ResizeMode::Fit
+            }
+            mode => mode,
+        };
+
+        let size = match mode {
+            ResizeMode::Fit => {
+                Self::calculate_aspect_ratio_size(source, target_width, target_height)
+            }
+            ResizeMode::Fill | ResizeMode::Stretch => (target_width, target_height),
+        };
+        (size, mode)
+    }
+
+    /// Sides with about `budget.pixels` in total and the source's ratio, each snapped to a
+    /// multiple of `budget.multiple`. Of the nearest multiples either side, picks the pair
+    /// that misses the pixel count and the ratio least.
+    fn pixel_budget_size((width, height): (u32, u32), budget: PixelBudget) -> (u32, u32) {
+        let multiple = f64::from(budget.multiple.max(1));
+        let pixels = budget.pixels.max(1) as f64;
+        let ratio = f64::from(width.max(1)) / f64::from(height.max(1));
+
+        let ideal_width = (pixels * ratio).sqrt();
+        let ideal_height = (pixels / ratio).sqrt();
+        let snaps = |ideal: f64| {
+            let below = ((ideal / multiple).floor() * multiple).max(multiple);
+            [below, below + multiple]
+        };
+
+        let error =
+            |(w, h): (f64, f64)| ((w * h) / pixels - 1.0).abs() + ((w / h) / ratio - 1.0).abs();
+        let (width, height) = snaps(ideal_width)
+            .into_iter()
+            .flat_map(|w| snaps(ideal_height).map(|h| (w, h)))
+            .min_by(|&a, &b| error(a).total_cmp(&error(b)))
+            .unwrap_or((multiple, multiple));
+        (width as u32, height as u32)
+    }
+
+    /// Size of the output for a source of this size, including the preset's crop.
+    pub fn output_dimensions((width, height): (u32, u32), preset: &ResizePreset) -> (u32, u32) {
+        if preset.region_mask {
+            return (width, height);
+        }
+        if Self::pads_to_target(preset) {
+            return (preset.width, preset.height);
+        }
+        let cropped = preset.crop.map_or((width, height), |crop| {
+            let (_, _, width, height) = crop.to_pixels(width, height);
             (width, height)
+        });
+        Self::target_size(cropped, preset).0
+    }
+
+    fn select_filter(
+        identifier: &Path,
+        (width, height): (u32, u32),
+        preset: &ResizePreset,
+        log: &dyn Fn(String),
+    ) -> FilterType {
+        let pixels = u64::from(width) * u64::from(height);
+        let filter = preset.filter;
+        match preset.fast_filter_above_pixels {
+            // Triangle is several times faster and still fine for heavy downscaling
+            Some(threshold)
+                if pixels > threshold
+                    && preset.custom_kernel.is_none()
+                    && !matches!(filter, ResampleFilter::Nearest | ResampleFilter::Triangle) =>
+            {
+                log(format!(
+                    "{}: {:.1} MP source, using Triangle filter instead of {}",
+                    identifier.file_name().unwrap_or_default().to_string_lossy(),
+                    pixels as f64 / 1_000_000.0,
+                    filter.label()
+                ));
+                FilterType::Triangle
+            }
+            _ => filter.filter_type(),
         }
     }
 
+    /// Returns all frames when the input is an animated GIF or WebP, `None` otherwise.
+    fn decode_animation_frames(
+        identifier: &Path,
+        bytes: &[u8],
+        format: ImageFormat,
+    ) -> Result<Option<Vec<Frame>>> {
+        let decode_error = || format!("Failed to decode animation: {}", identifier.display());
+
+        let frames = match format {
+            ImageFormat::Gif => GifDecoder::new(Cursor::new(bytes))
+                .with_context(decode_error)?
+                .into_frames()
+                .collect_frames()
+                .with_context(decode_error)?,
+            ImageFormat::WebP => {
+                let decoder = WebPDecoder::new(Cursor::new(bytes)).with_context(decode_error)?;
+                if !decoder.has_animation() {
+                    return Ok(None);
+                }
+                decoder
+                    .into_frames()
+                    .collect_frames()
+                    .with_context(decode_error)?
+            }
+            _ => return Ok(None),
+        };
+
+        Ok((frames.len() > 1).then_some(frames))
+    }
+
+    /// Resizes every frame of an animated GIF and writes them as one GIF with the source's
+    /// frame delays and loop count.
+    fn resize_gif_animation(
+        bytes: &[u8],
+        frames: Vec<Frame>,
+        output_path: &Path,
+        caption: Option<&(String, Corner)>,
+        preset: &ResizePreset,
+        sink: &dyn OutputSink,
+        log: &dyn Fn(String),
+    ) -> Result<PathBuf> {
+        // A GIF without a loop count plays once
+        let repeat = match gif::DecodeOptions::new()
+            .read_info(Cursor::new(bytes))?
+            .repeat()
+        {
+            gif::Repeat::Infinite => Some(Repeat::Infinite),
+            gif::Repeat::Finite(0) => None,
+            gif::Repeat::Finite(count) => Some(Repeat::Finite(count)),
+        };
+
+        let canvas = frames
+            .first()
+            .map_or((0, 0), |frame| frame.buffer().dimensions());
+        let filter = Self::select_filter(output_path, canvas, preset, log);
+        let resized = frames.into_iter().map(|frame| {
+            let delay = frame.delay();
+            let mut resized = Self::resize_dynamic_image(
+                &DynamicImage::ImageRgba8(frame.into_buffer()),
+                preset,
+                filter,
+            );
+            Self::decorate(&mut resized, caption, preset, ImageFormat::Gif);
+            Frame::from_parts(resized.into_rgba8(), 0, 0, delay)
+        });
+
+        let mut encoded = Vec::new();
+        {
+            // Speed 10 is the gif crate's default, 1 takes minutes on large frames
+            let mut encoder = GifEncoder::new_with_speed(&mut encoded, 10);
+            if let Some(repeat) = repeat {
+                encoder.set_repeat(repeat)?;
+            }
+            encoder.encode_frames(resized)?;
+        }
+        sink.write(output_path, &encoded)?;
+        Ok(output_path.to_path_buf())
+    }
+
+    fn numbered_output_path(output_path: &Path, number: usize) -> PathBuf {
+        Self::suffixed_output_path(output_path, &format!("_{number:03}"))
+    }
+
+    fn suffixed_output_path(output_path: &Path, suffix: &str) -> PathBuf {
+        let stem = output_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let mut file_name = format!("{stem}{suffix}");
+        if let Some(extension) = output_path.extension() {
+            file_name.push('.');
+            file_name.push_str(&extension.to_string_lossy());
+        }
+        output_path.with_file_name(file_name)
+    }
+
+    /// Whether stretching to the target would change the aspect ratio by no more than `tolerance`.
+    fn aspect_within_tolerance(
+        (original_width, original_height): (u32, u32),
+        target_width: u32,
+        target_height: u32,
+        tolerance: f32,
+    ) -> bool {
+        let original_aspect_ratio = f64::from(original_width) / f64::from(original_height);
+        let target_aspect_ratio = f64::from(target_width) / f64::from(target_height);
+
+        (target_aspect_ratio / original_aspect_ratio - 1.0).abs() <= f64::from(tolerance)
+    }
+
+    /// The largest size with the source's ratio that fits inside the target box, so neither
+    /// side exceeds its target.
+    fn calculate_aspect_ratio_size(
+        (original_width, original_height): (u32, u32),
+        target_width: u32,
+        target_height: u32,
+    ) -> (u32, u32) {
+        let scale = (f64::from(target_width) / f64::from(original_width))
+            .min(f64::from(target_height) / f64::from(original_height));
+
+        // Rounding can't push the side that was fitted exactly past its target
+        let width = ((f64::from(original_width) * scale).round() as u32).clamp(1, target_width);
+        let height = ((f64::from(original_height) * scale).round() as u32).clamp(1, target_height);
+        (width, height)
+    }
+
     fn get_image_format(path: &Path) -> Result<ImageFormat> {
         let extension = path
             .extension()
@@ -77,6 +813,8 @@ impl ImageResizer {
             "bmp" => Ok(ImageFormat::Bmp),
             "tiff" | "tif" => Ok(ImageFormat::Tiff),
             "webp" => Ok(ImageFormat::WebP),
+            "ico" => Ok(ImageFormat::Ico),
+            "avif" => Ok(ImageFormat::Avif),
             _ => anyhow::bail!("Unsupported image format: {}", extension),
         }
     }
@@ -89,6 +827,7 @@ impl ImageResizer {
             OutputFormat::Webp => Ok(ImageFormat::WebP),
             OutputFormat::Bmp => Ok(ImageFormat::Bmp),
             OutputFormat::Tiff => Ok(ImageFormat::Tiff),
+            OutputFormat::Avif => Ok(ImageFormat::Avif),
         }
     }
 
@@ -96,28 +835,251 @@ impl ImageResizer {
         img: &DynamicImage,
         output_path: &Path,
         format: ImageFormat,
+        preset: &ResizePreset,
+        sink: &dyn OutputSink,
+        log: &dyn Fn(String),
     ) -> Result<()> {
-        match format {
-            ImageFormat::Jpeg => {
-                // Save JPEG with maximum quality (100)
-                use image::codecs::jpeg::JpegEncoder;
-                use std::fs::File;
-
-                let file = File::create(output_path).with_context(|| {
-                    format!("Failed to create output file: {}", output_path.display())
-                })?;
-                let mut encoder = JpegEncoder::new_with_quality(file, 100);
-                encoder
-                    .encode_image(img)
-                    .with_context(|| format!("Failed to encode JPEG: {}", output_path.display()))?;
+        let name = output_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        // Without the feature the image crate has no AVIF encoder to fall back on
+        anyhow::ensure!(
+            format != ImageFormat::Avif || cfg!(feature = "avif"),
+            "{name}: AVIF output needs resize-rs built with the `avif` feature"
+        );
+        anyhow::ensure!(
+            format != ImageFormat::WebP || preset.webp_lossless || cfg!(feature = "webp-lossy"),
+            "{name}: lossy WebP output needs resize-rs built with the `webp-lossy` feature"
+        );
+        let img = &*compat::fit_to_format(img, format, preset.incompatible_output, &name, log)?;
+
+        let bytes = if format == ImageFormat::Jpeg {
+            let encode_error = || format!("Failed to encode JPEG: {}", output_path.display());
+
+            if let Some(budget) = preset.byte_budget {
+                let (quality, bytes, (width, height)) =
+                    Self::encode_jpeg_for_budget(img, budget, preset).with_context(encode_error)?;
+                if (width, height) == img.dimensions() {
+                    log(format!(
+                        "{name}: JPEG quality {quality} fits {} bytes",
+                        budget.max_bytes
+                    ));
+                } else {
+                    log(format!(
+                        "{name}: reduced to {width}x{height} to fit {} bytes at quality {quality}",
+                        budget.max_bytes
+                    ));
+                }
+                bytes
+            } else if let Some(target) = preset.ssim_target {
+                let (quality, bytes, ssim) =
+                    Self::encode_jpeg_for_ssim(img, target, preset).with_context(encode_error)?;
+                log(format!(
+                    "{name}: JPEG quality {quality} reaches SSIM {ssim:.4}"
+                ));
+                bytes
+            } else {
+                let quality = preset
+                    .encoder_params
+                    .jpeg_quality
+                    .unwrap_or(preset.jpeg_quality)
+                    .clamp(1, 100);
+                Self::encode_jpeg(img, quality, preset).with_context(encode_error)?
+            }
+        } else {
+            if preset.byte_budget.is_some() {
+                log(format!(
+                    "{name}: the size budget only applies to JPEG output"
+                ));
+            }
+            let encode_error = || format!("Failed to encode image: {}", output_path.display());
+            let mut bytes = Vec::new();
+            if format == ImageFormat::Png {
+                let params = preset.encoder_params;
+                PngEncoder::new_with_quality(
+                    &mut bytes,
+                    params.png_compression.map(Into::into).unwrap_or_default(),
+                    params.png_filter.map(Into::into).unwrap_or_default(),
+                )
+                .write_image(
+                    img.as_bytes(),
+                    img.width(),
+                    img.height(),
+                    img.color().into(),
+                )
+                .with_context(encode_error)?;
+            } else if format == ImageFormat::WebP && !preset.webp_lossless {
+                // Refused above when the feature is off
+                #[cfg(feature = "webp-lossy")]
+                {
+                    bytes = Self::encode_lossy_webp(img, preset.webp_quality);
+                }
+            } else {
+                // For all other formats, use the image crate's default encoder settings
+                img.write_to(&mut Cursor::new(&mut bytes), format)
+                    .with_context(encode_error)?;
+            }
+            bytes
+        };
+
+        sink.write(output_path, &bytes)
+    }
+
+    /// Saves `img`, plus a JPEG of it next to WebP outputs when the preset asks for fallbacks.
+    /// Returns the paths written.
+    fn save_with_fallback(
+        img: &DynamicImage,
+        output_path: &Path,
+        format: ImageFormat,
+        preset: &ResizePreset,
+        sink: &dyn OutputSink,
+        side_sink: &dyn OutputSink,
+        log: &dyn Fn(String),
+    ) -> Result<Vec<PathBuf>> {
+        Self::save_image_with_format(img, output_path, format, preset, sink, log)?;
+        let mut written = vec![output_path.to_path_buf()];
+
+        if preset.jpeg_fallback && format == ImageFormat::WebP {
+            let fallback_path = output_path.with_extension("jpg");
+            Self::save_image_with_format(
+                img,
+                &fallback_path,
+                ImageFormat::Jpeg,
+                preset,
+                side_sink,
+                log,
+            )?;
+            written.push(fallback_path);
+        }
+        Ok(written)
+    }
+
+    fn encode_jpeg(img: &DynamicImage, quality: u8, preset: &ResizePreset) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+
+        let restart_interval = preset
+            .encoder_params
+            .jpeg_restart_interval
+            .or(preset.jpeg_restart_interval);
+        if let Some(interval) = restart_interval {
+            // The image crate's encoder cannot emit restart markers, so use jpeg-encoder for that
+            let width = u16::try_from(img.width()).context("Image too wide for JPEG")?;
+            let height = u16::try_from(img.height()).context("Image too tall for JPEG")?;
+
+            let mut encoder = jpeg_encoder::Encoder::new(&mut bytes, quality);
+            encoder.set_restart_interval(interval);
+
+            if img.color().has_color() {
+                encoder.encode(img.to_rgb8().as_raw(), width, height, ColorType::Rgb)?;
+            } else {
+                encoder.encode(img.to_luma8().as_raw(), width, height, ColorType::Luma)?;
+            }
+        } else {
+            JpegEncoder::new_with_quality(&mut bytes, quality).encode_image(img)?;
+        }
+
+        Ok(bytes)
+    }
+
+    /// Lossy WebP at `quality` from 0 to 100, encoded by libwebp.
+    #[cfg(feature = "webp-lossy")]
+    fn encode_lossy_webp(img: &DynamicImage, quality: f32) -> Vec<u8> {
+        // libwebp takes 8-bit RGB(A), the alpha channel only costs bytes when there is one
+        let quality = quality.clamp(0.0, 100.0);
+        let encoded = if img.color().has_alpha() {
+            let rgba = img.to_rgba8();
+            webp::Encoder::from_rgba(rgba.as_raw(), img.width(), img.height()).encode(quality)
+        } else {
+            let rgb = img.to_rgb8();
+            webp::Encoder::from_rgb(rgb.as_raw(), img.width(), img.height()).encode(quality)
+        };
+        encoded.to_vec()
+    }
+
+    /// Binary-searches the lowest JPEG quality whose decoded result reaches `target` SSIM.
+    /// Returns the chosen quality, the encoded bytes and the SSIM they achieved.
+    fn encode_jpeg_for_ssim(
+        img: &DynamicImage,
+        target: f32,
+        preset: &ResizePreset,
+    ) -> Result<(u8, Vec<u8>, f64)> {
+        let reference = img.to_luma8();
+        let measure = |quality: u8| -> Result<(Vec<u8>, f64)> {
+            let bytes = Self::encode_jpeg(img, quality, preset)?;
+            let decoded = image::load_from_memory_with_format(&bytes, ImageFormat::Jpeg)?;
+            let ssim = ssim::mean_ssim(&reference, &decoded.to_luma8());
+            Ok((bytes, ssim))
+        };
+
+        let (mut low, mut high) = (1_u8, 100_u8);
+        let mut best = None;
+        while low < high {
+            let quality = low + (high - low) / 2;
+            let (bytes, ssim) = measure(quality)?;
+            if ssim >= f64::from(target) {
+                high = quality;
+                best = Some((quality, bytes, ssim));
+            } else {
+                low = quality + 1;
             }
+        }
+
+        match best {
+            Some(best) if best.0 == low => Ok(best),
             _ => {
-                // For all other formats, use the standard save method
-                img.save_with_format(output_path, format)
-                    .with_context(|| format!("Failed to save image: {}", output_path.display()))?;
+                let (bytes, ssim) = measure(low)?;
+                Ok((low, bytes, ssim))
             }
         }
-        Ok(())
+    }
+
+    /// Finds the highest JPEG quality, no lower than the budget's floor, that fits the budget.
+    /// When even the floor doesn't fit, the image is scaled down until it does.
+    /// Returns the chosen quality, the encoded bytes and the dimensions they were encoded at.
+    fn encode_jpeg_for_budget(
+        img: &DynamicImage,
+        budget: ByteBudget,
+        preset: &ResizePreset,
+    ) -> Result<(u8, Vec<u8>, (u32, u32))> {
+        let floor = budget.min_quality.clamp(1, 100);
+        let fits = |bytes: &[u8]| bytes.len() as u64 <= budget.max_bytes;
+
+        let mut current = Cow::Borrowed(img);
+        loop {
+            let at_floor = Self::encode_jpeg(&current, floor, preset)?;
+            if fits(&at_floor) {
+                // Size grows with quality, so search upwards from the floor
+                let (mut low, mut high) = (floor, 100_u8);
+                let mut best = at_floor;
+                while low < high {
+                    let quality = high - (high - low) / 2;
+                    let bytes = Self::encode_jpeg(&current, quality, preset)?;
+                    if fits(&bytes) {
+                        low = quality;
+                        best = bytes;
+                    } else {
+                        high = quality - 1;
+                    }
+                }
+                return Ok((low, best, current.dimensions()));
+            }
+
+            let (width, height) = current.dimensions();
+            if width == 1 && height == 1 {
+                anyhow::bail!(
+                    "Cannot fit {} bytes even at 1x1 and quality {floor}",
+                    budget.max_bytes
+                );
+            }
+
+            // File size roughly follows the pixel count, aim a little below the budget
+            let scale = (budget.max_bytes as f64 / at_floor.len() as f64).sqrt() * 0.95;
+            let scale = scale.min(0.9);
+            let width = ((f64::from(width) * scale) as u32).max(1);
+            let height = ((f64::from(height) * scale) as u32).max(1);
+            current = Cow::Owned(current.resize_exact(width, height, FilterType::Lanczos3));
+        }
     }
 
     fn get_extension_for_format(output_format: OutputFormat, input_path: &Path) -> String {
@@ -132,43 +1094,2004 @@ impl ImageResizer {
             OutputFormat::Webp => "webp".to_string(),
             OutputFormat::Bmp => "bmp".to_string(),
             OutputFormat::Tiff => "tiff".to_string(),
+            OutputFormat::Avif => "avif".to_string(),
         }
     }
 
+    /// AVIF inputs are listed but only decode with the image crate's `avif-native` decoder.
     pub fn get_supported_extensions() -> Vec<&'static str> {
-        vec!["jpg", "jpeg", "png", "gif", "bmp", "tiff", "tif", "webp"]
+        vec![
+            "jpg", "jpeg", "png", "gif", "bmp", "tiff", "tif", "webp", "ico", "avif",
+        ]
+    }
+
+    /// Where the output for an input goes. `source` is the input's displayed size, with EXIF
+    /// rotations applied, so names carry the real output size; without it the target's.
+    /// `bytes` are the encoded input if it has been read already.
+    fn output_path_for(
+        input_path: &Path,
+        bytes: Option<&[u8]>,
+        source: Option<(u32, u32)>,
+        index: usize,
+        output_dir: &Path,
+        preset: &ResizePreset,
+        naming: &Naming,
+    ) -> Result<PathBuf> {
+        let file_stem = input_path.file_stem().context("Invalid file name")?;
+
+        // Determine output extension based on format
+        let output_extension = Self::get_extension_for_format(preset.output_format, input_path);
+
+        let size = source.map_or((preset.width, preset.height), |source| {
+            Self::output_dimensions(source, preset)
+        });
+        // Inputs that aren't files (e.g. in-memory buffers) may have no extension to keep
+        let file_name = naming.output_file_name(
+            &file_stem.to_string_lossy(),
+            index,
+            size,
+            &output_extension,
+        )?;
+
+        Ok(naming
+            .output_dir_for(input_path, bytes, output_dir)
+            .join(file_name))
+    }
+
+    /// Refuses to write `output` over the `input` it is made from unless `allow` is set.
+    pub fn ensure_not_input(input: &Path, output: &Path, allow: bool) -> Result<()> {
+        // Canonicalizing only works for existing files, but an output that doesn't exist
+        // can't be an existing input either
+        let same_file = input == output
+            || matches!(
+                (input.canonicalize(), output.canonicalize()),
+                (Ok(input), Ok(output)) if input == output
+            );
+
+        anyhow::ensure!(
+            allow || !same_file,
+            "Refusing to replace the source image {} with its own output",
+            input.display()
+        );
+        Ok(())
+    }
+
+    /// Runs `resize_image` on its own thread and gives up on it after `timeout`.
+    fn resize_image_with_timeout(
+        identifier: &Path,
+        bytes: &[u8],
+        output_path: &Path,
+        preset: &ResizePreset,
+        sink: &dyn OutputSink,
+        log: &dyn Fn(String),
+        timeout: Duration,
+    ) -> Result<Vec<PathBuf>> {
+        let (identifier_owned, bytes_owned, output_path_owned, preset_owned) = (
+            identifier.to_path_buf(),
+            bytes.to_vec(),
+            output_path.to_path_buf(),
+            *preset,
+        );
+        Self::run_with_timeout(identifier, sink, log, timeout, move |sink, log| {
+            Self::resize_image(
+                &identifier_owned,
+                &bytes_owned,
+                &output_path_owned,
+                &preset_owned,
+                sink,
+                log,
+            )
+        })
+    }
+
+    /// Runs the work for one image on its own thread and gives up on it after `timeout`.
+    ///
+    /// Outputs are held in memory until the image is done, so one that is given up on writes
+    /// nothing. Its thread can't be stopped from outside and finishes in the background.
+    fn run_with_timeout(
+        identifier: &Path,
+        sink: &dyn OutputSink,
+        log: &dyn Fn(String),
+        timeout: Duration,
+        work: impl FnOnce(&dyn OutputSink, &dyn Fn(String)) -> Result<Vec<PathBuf>> + Send + 'static,
+    ) -> Result<Vec<PathBuf>> {
+        enum Message {
+            Log(String),
+            Done(Result<Vec<PathBuf>>, MemorySink),
+        }
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let memory = MemorySink::default();
+            let log_tx = tx.clone();
+            let result = work(&memory, &|message| {
+                let _ = log_tx.send(Message::Log(message));
+            });
+            let _ = tx.send(Message::Done(result, memory));
+        });
+
+        let name = identifier.file_name().unwrap_or_default().to_string_lossy();
+        let deadline = Instant::now() + timeout;
+        loop {
+            match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(Message::Log(message)) => log(message),
+                Ok(Message::Done(result, memory)) => {
+                    let outputs = result?;
+                    for (path, bytes) in memory.into_files() {
+                        sink.write(&path, &bytes)?;
+                    }
+                    return Ok(outputs);
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    let message = format!(
+                        "{name}: timed out after {:.1}s, skipped",
+                        timeout.as_secs_f32()
+                    );
+                    log(message.clone());
+                    anyhow::bail!(message);
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    anyhow::bail!("{name}: processing stopped unexpectedly")
+                }
+            }
+        }
     }
 
-    pub fn batch_resize(
+    /// Describes what `batch_resize` would do without touching any files.
+    pub fn plan_batch(
         input_files: &[PathBuf],
         output_dir: &Path,
         preset: &ResizePreset,
-        progress_callback: impl Fn(usize, usize),
-    ) -> Result<Vec<Result<PathBuf>>> {
-        let mut results = Vec::new();
+        naming: &Naming,
+    ) -> Result<Vec<Job>> {
+        input_files
+            .iter()
+            .enumerate()
+            .map(|(index, input_path)| {
+                let preset = &preset.for_input(input_path);
+                Ok(Job {
+                    input: input_path.clone(),
+                    output: Self::output_path_for(
+                        input_path,
+                        None,
+                        probe::probe(input_path)
+                            .ok()
+                            .map(|info| info.source_dimensions(preset)),
+                        index + 1,
+                        output_dir,
+                        preset,
+                        naming,
+                    )?,
+                    width: preset.width,
+                    height: preset.height,
+                    maintain_aspect_ratio: preset.mode != ResizeMode::Stretch,
+                    mode: Some(preset.mode),
+                    scale_percent: preset.scale_percent,
+                    output_format: preset.output_format,
+                    crop: preset.crop,
+                    settings: Some(*preset),
+                })
+            })
+            .collect()
+    }
 
-        for (index, input_path) in input_files.iter().enumerate() {
-            progress_callback(index, input_files.len());
+    /// Resizes `inputs` in order into `output_dir`, one result per input with the files it
+    /// wrote. Inputs that were skipped, e.g. because their output exists, come back without
+    /// files.
+    pub fn batch_resize<S: InputSource>(
+        inputs: &[S],
+        output_dir: &Path,
+        preset: &ResizePreset,
+        naming: &Naming,
+        sink: &dyn OutputSink,
+        progress_callback: impl Fn(usize, usize) -> ControlFlow<()> + Sync,
+        log_callback: impl Fn(String) + Sync,
+    ) -> Result<Vec<Result<Vec<WrittenFile>>>> {
+        // Outputs are named after their input, one without a name stops the batch
+        for input in inputs {
+            input
+                .identifier()
+                .file_stem()
+                .context("Invalid file name")?;
+        }
 
-            let _file_name = input_path.file_name().context("Invalid file name")?;
+        let total = inputs.len();
+        let workers = preset
+            .max_parallel_images
+            .unwrap_or_else(rayon::current_num_threads)
+            .clamp(1, total.max(1));
 
-            // Determine output extension based on format
-            let output_extension = Self::get_extension_for_format(preset.output_format, input_path);
+        let sizes = SizeSink::new(sink);
+        let next = AtomicUsize::new(0);
+        let completed = AtomicUsize::new(0);
+        let stopped = AtomicBool::new(false);
+        let slots: Vec<_> = inputs.iter().map(|_| Mutex::new(None)).collect();
+        let output = BatchOutput {
+            dir: output_dir,
+            naming,
+            reserved: ReservedPaths::default(),
+        };
 
-            let output_path = output_dir.join(format!(
-                "{}_resized_{}x{}.{}",
-                input_path.file_stem().unwrap().to_string_lossy(),
-                preset.width,
-                preset.height,
-                output_extension
-            ));
+        // Inputs are claimed in order, so a stop always leaves a tail of them unprocessed
+        let claim = || {
+            if stopped.load(Ordering::Relaxed) {
+                return None;
+            }
+            // Breaking stops before the next image, the results so far are kept
+            if progress_callback(completed.load(Ordering::Relaxed), total).is_break() {
+                stopped.store(true, Ordering::Relaxed);
+                return None;
+            }
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            (index < total).then_some(index)
+        };
+        let process = |index: usize, input: &dyn InputSource| {
+            let result = Self::process_input(input, index, &output, preset, &sizes, &log_callback);
+            *slots[index].lock().unwrap_or_else(PoisonError::into_inner) = Some(result);
+            completed.fetch_add(1, Ordering::Relaxed);
+        };
 
-            let result = Self::resize_image(input_path, &output_path, preset).map(|()| output_path);
+        if let Some(readers) = preset.max_parallel_reads {
+            // Readers hand what they read to the workers through a short queue. Everything
+            // read before a stop is still processed, so the processed inputs stay in order.
+            let readers = readers.clamp(1, total.max(1));
+            let (sender, receiver) = mpsc::sync_channel(workers);
+            let receiver = Mutex::new(receiver);
+            let (claim, process) = (&claim, &process);
+            thread::scope(|scope| {
+                for _ in 0..readers {
+                    let sender = sender.clone();
+                    scope.spawn(move || {
+                        while let Some(index) = claim() {
+                            let bytes = inputs[index].read_bytes();
+                            if sender.send((index, bytes)).is_err() {
+                                break;
+                            }
+                        }
+                    });
+                }
+                drop(sender);
+                for _ in 0..workers {
+                    scope.spawn(|| loop {
+                        let received = receiver
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .recv();
+                        let Ok((index, bytes)) = received else {
+                            break;
+                        };
+                        process(index, &ReadAhead::new(inputs[index].identifier(), bytes));
+                    });
+                }
+            });
+        } else {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(workers)
+                .build()
+                .context("Failed to start worker threads")?;
+            pool.install(|| {
+                (0..workers).into_par_iter().for_each(|_| {
+                    while let Some(index) = claim() {
+                        process(index, &inputs[index]);
+                    }
+                });
+            });
+        }
 
-            results.push(result);
+        let results: Vec<_> = slots
+            .into_iter()
+            .map_while(|slot| slot.into_inner().unwrap_or_else(PoisonError::into_inner))
+            .map(|result| {
+                result.map(|paths| {
+                    paths
+                        .into_iter()
+                        .map(|path| WrittenFile {
+                            bytes: sizes.size(&path),
+                            path,
+                        })
+                        .collect()
+                })
+            })
+            .collect();
+        if results.len() < total {
+            log_callback(format!(
+                "Batch stopped, {} of {total} images not processed",
+                total - results.len()
+            ));
+            return Ok(results);
         }
 
-        progress_callback(input_files.len(), input_files.len());
+        let _ = progress_callback(total, total);
         Ok(results)
     }
+
+    /// Resizes the input at `index` of a batch and writes its outputs.
+    fn process_input(
+        input: &dyn InputSource,
+        index: usize,
+        output: &BatchOutput,
+        preset: &ResizePreset,
+        sink: &dyn OutputSink,
+        log_callback: &dyn Fn(String),
+    ) -> Result<Vec<PathBuf>> {
+        let BatchOutput {
+            dir: output_dir,
+            naming,
+            reserved,
+        } = output;
+        let identifier = input.identifier();
+        let preset = &preset.for_input(identifier);
+
+        input.read_bytes().and_then(|bytes| {
+            let output_path = Self::output_path_for(
+                identifier,
+                Some(&bytes),
+                probe::source_dimensions(&bytes, preset),
+                index + 1,
+                output_dir,
+                preset,
+                naming,
+            )?;
+            let Some(output_path) = naming.overwrite.resolve(output_path, reserved) else {
+                log_callback(format!(
+                    "{}: output exists, skipped",
+                    identifier.file_name().unwrap_or_default().to_string_lossy()
+                ));
+                return Ok(Vec::new());
+            };
+            Self::ensure_not_input(identifier, &output_path, naming.allow_input_overwrite)?;
+            let input_name = identifier.file_name().unwrap_or_default().to_string_lossy();
+            if naming.transliterate && !input_name.is_ascii() {
+                log_callback(format!(
+                    "{input_name}: named {}",
+                    output_path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                ));
+            }
+            let outputs = match preset.timeout {
+                Some(timeout) => Self::resize_image_with_timeout(
+                    identifier,
+                    &bytes,
+                    &output_path,
+                    preset,
+                    sink,
+                    log_callback,
+                    timeout,
+                )?,
+                None => Self::resize_image(
+                    identifier,
+                    &bytes,
+                    &output_path,
+                    preset,
+                    sink,
+                    log_callback,
+                )?,
+            };
+            if let Some(copy_path) = naming.original_copy_path(identifier, Some(&bytes), output_dir)
+            {
+                sink.write(&copy_path, &bytes)?;
+            }
+            Ok(outputs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder_params;
+    use crate::presets::IncompatibleOutput;
+    use crate::test_util;
+
+    /// An in-memory input that notes how many reads of a batch overlap.
+    struct CountingSource<'a> {
+        identifier: PathBuf,
+        bytes: Vec<u8>,
+        active: &'a AtomicUsize,
+        most_active: &'a AtomicUsize,
+        /// Reads wait up to a second for this many to overlap, so the peak doesn't depend
+        /// on how the threads happen to be scheduled.
+        expected_overlap: usize,
+    }
+
+    impl InputSource for CountingSource<'_> {
+        fn identifier(&self) -> &Path {
+            &self.identifier
+        }
+
+        fn read_bytes(&self) -> Result<crate::source::InputBytes> {
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most_active.fetch_max(active, Ordering::SeqCst);
+            let started = Instant::now();
+            while self.most_active.load(Ordering::SeqCst) < self.expected_overlap
+                && started.elapsed() < Duration::from_secs(1)
+            {
+                thread::sleep(Duration::from_millis(1));
+            }
+            thread::sleep(Duration::from_millis(20));
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            Ok(self.bytes.clone().into())
+        }
+    }
+
+    #[test]
+    fn parallelism_limit_caps_concurrent_images() {
+        let bytes = test_util::encode(&test_util::gradient(16, 16), ImageFormat::Png);
+        for limit in [1, 2, 3] {
+            let (active, most_active) = (AtomicUsize::new(0), AtomicUsize::new(0));
+            let inputs: Vec<_> = (0..8)
+                .map(|index| CountingSource {
+                    identifier: PathBuf::from(format!("{index}.png")),
+                    bytes: bytes.clone(),
+                    active: &active,
+                    most_active: &most_active,
+                    expected_overlap: limit,
+                })
+                .collect();
+            let preset = ResizePreset {
+                width: 8,
+                height: 8,
+                max_parallel_images: Some(limit),
+                ..ResizePreset::BASE
+            };
+
+            let results = ImageResizer::batch_resize(
+                &inputs,
+                Path::new("out"),
+                &preset,
+                &Naming::default(),
+                &MemorySink::default(),
+                |_, _| ControlFlow::Continue(()),
+                |_| {},
+            )
+            .unwrap();
+
+            assert_eq!(results.len(), 8);
+            assert!(results.iter().all(Result::is_ok));
+            // Reached but never exceeded, so the limit is what held the batch back
+            assert_eq!(most_active.load(Ordering::SeqCst), limit);
+        }
+    }
+
+    #[test]
+    fn animation_to_bmp_writes_numbered_frames() {
+        let bytes = test_util::animated_gif(20, 10, 3);
+        let preset = ResizePreset {
+            width: 10,
+            height: 10,
+            output_format: OutputFormat::Bmp,
+            animation_policy: AnimationPolicy::AllFramesNumbered,
+            ..ResizePreset::BASE
+        };
+        let sink = MemorySink::default();
+
+        let written = ImageResizer::resize_image(
+            Path::new("anim.gif"),
+            &bytes,
+            Path::new("out/anim.bmp"),
+            &preset,
+            &sink,
+            &|_| {},
+        )
+        .unwrap();
+
+        let expected: Vec<_> = ["out/anim_001.bmp", "out/anim_002.bmp", "out/anim_003.bmp"]
+            .map(PathBuf::from)
+            .into();
+        assert_eq!(written, expected);
+        let files = sink.into_files();
+        assert_eq!(files.len(), 3);
+        for (_, data) in files {
+            assert_eq!(image::guess_format(&data).unwrap(), ImageFormat::Bmp);
+            assert_eq!(test_util::dimensions(&data), (10, 5));
+        }
+    }
+
+    #[test]
+    fn sixteen_bit_to_bmp_follows_the_incompatible_output_policy() {
+        let deep = DynamicImage::ImageRgb16(test_util::gradient(20, 10).to_rgb16());
+        let bytes = test_util::encode(&deep, ImageFormat::Png);
+        let resize = |incompatible_output| {
+            let preset = ResizePreset {
+                width: 10,
+                height: 10,
+                output_format: OutputFormat::Bmp,
+                incompatible_output,
+                ..ResizePreset::BASE
+            };
+            let logged = Mutex::new(Vec::new());
+            let sink = MemorySink::default();
+            let result = ImageResizer::resize_image(
+                Path::new("deep.png"),
+                &bytes,
+                Path::new("out/deep.bmp"),
+                &preset,
+                &sink,
+                &|message| logged.lock().unwrap().push(message),
+            );
+            (result, sink.into_files(), logged.into_inner().unwrap())
+        };
+
+        let (result, files, logged) = resize(IncompatibleOutput::Convert);
+        result.unwrap();
+        let converted = image::load_from_memory(&files[0].1).unwrap();
+        assert_eq!(converted.color(), image::ColorType::Rgb8);
+        assert!(
+            logged.contains(&"deep.bmp: Bmp stores at most 8 bits per channel but the image has 16, reduced to 8".to_string()),
+            "{logged:?}"
+        );
+
+        let (result, files, _) = resize(IncompatibleOutput::Fail);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "deep.bmp: Bmp stores at most 8 bits per channel but the image has 16"
+        );
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn animation_to_bmp_follows_the_animation_policy() {
+        let bytes = test_util::animated_gif(20, 10, 3);
+        let resize = |animation_policy| {
+            let preset = ResizePreset {
+                width: 10,
+                height: 10,
+                output_format: OutputFormat::Bmp,
+                animation_policy,
+                ..ResizePreset::BASE
+            };
+            let sink = MemorySink::default();
+            let result = ImageResizer::resize_image(
+                Path::new("anim.gif"),
+                &bytes,
+                Path::new("out/anim.bmp"),
+                &preset,
+                &sink,
+                &|_| {},
+            );
+            (result, sink.into_files())
+        };
+
+        let (result, files) = resize(AnimationPolicy::FirstFrame);
+        assert_eq!(result.unwrap(), [PathBuf::from("out/anim.bmp")]);
+        assert_eq!(files.len(), 1);
+        assert_eq!(test_util::dimensions(&files[0].1), (10, 5));
+
+        let (result, files) = resize(AnimationPolicy::Error);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "anim.gif is animated (3 frames) but Bmp output cannot store animation"
+        );
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn huge_sources_fall_back_to_triangle() {
+        let preset = ResizePreset {
+            filter: ResampleFilter::Lanczos3,
+            fast_filter_above_pixels: Some(1_000_000),
+            ..ResizePreset::BASE
+        };
+        let logged = Mutex::new(Vec::new());
+        let log = |message| logged.lock().unwrap().push(message);
+
+        let huge = ImageResizer::select_filter(Path::new("big.jpg"), (2000, 1000), &preset, &log);
+        let small = ImageResizer::select_filter(Path::new("small.jpg"), (800, 600), &preset, &log);
+
+        assert_eq!(huge, FilterType::Triangle);
+        assert_eq!(small, FilterType::Lanczos3);
+        assert_eq!(
+            logged.into_inner().unwrap(),
+            ["big.jpg: 2.0 MP source, using Triangle filter instead of Lanczos3"]
+        );
+    }
+
+    #[test]
+    fn capture_date_is_burned_into_the_chosen_corner() {
+        let img = test_util::gradient(300, 200);
+        let dated = test_util::jpeg_with_exif(&img, &[test_util::exif_date("2024:06:15 10:30:00")]);
+        let undated = test_util::encode(&img, ImageFormat::Jpeg);
+        let preset = ResizePreset {
+            width: 300,
+            height: 200,
+            output_format: OutputFormat::Png,
+            ..ResizePreset::BASE
+        };
+        let resize = |bytes: &[u8], preset: &ResizePreset| {
+            let sink = MemorySink::default();
+            ImageResizer::resize_image(
+                Path::new("photo.jpg"),
+                bytes,
+                Path::new("photo.png"),
+                preset,
+                &sink,
+                &|_| {},
+            )
+            .unwrap();
+            image::load_from_memory(&sink.into_files()[0].1)
+                .unwrap()
+                .to_rgb8()
+        };
+        let captioned = ResizePreset {
+            date_caption: Some(Corner::BottomRight),
+            ..preset
+        };
+
+        let plain = resize(&dated, &preset);
+        let with_date = resize(&dated, &captioned);
+        let changed: Vec<_> = plain
+            .enumerate_pixels()
+            .filter(|&(x, y, pixel)| with_date.get_pixel(x, y) != pixel)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        assert!(!changed.is_empty());
+        assert!(changed.iter().all(|&(x, y)| x >= 150 && y >= 150));
+        assert!(with_date.pixels().any(|pixel| pixel.0 == [255, 255, 255]));
+
+        // Without a capture date nothing is drawn
+        assert_eq!(resize(&undated, &captioned), resize(&undated, &preset));
+    }
+
+    /// Resizes a 128x128 gradient with `preset` and returns the single output.
+    fn resize_to_bytes(preset: &ResizePreset) -> Vec<u8> {
+        let bytes = test_util::encode(&test_util::gradient(128, 128), ImageFormat::Png);
+        let sink = MemorySink::default();
+        ImageResizer::resize_image(
+            Path::new("photo.png"),
+            &bytes,
+            Path::new("photo.out"),
+            preset,
+            &sink,
+            &|_| {},
+        )
+        .unwrap();
+        let mut files = sink.into_files();
+        assert_eq!(files.len(), 1);
+        files.remove(0).1
+    }
+
+    #[test]
+    fn restart_interval_writes_restart_markers() {
+        let preset = ResizePreset {
+            width: 64,
+            height: 64,
+            output_format: OutputFormat::Jpeg,
+            jpeg_restart_interval: Some(4),
+            ..ResizePreset::BASE
+        };
+        let with_markers = resize_to_bytes(&preset);
+        let without_markers = resize_to_bytes(&ResizePreset {
+            jpeg_restart_interval: None,
+            ..preset
+        });
+
+        let markers = |bytes: &[u8]| {
+            bytes
+                .windows(2)
+                .filter(|pair| pair[0] == 0xFF && (0xD0..=0xD7).contains(&pair[1]))
+                .count()
+        };
+        // DRI segment with an interval of 4 MCUs
+        assert!(with_markers
+            .windows(6)
+            .any(|w| w == [0xFF, 0xDD, 0, 4, 0, 4]));
+        // 64x64 is 16 4:2:0 MCUs of 16x16, a marker after every 4 but the last
+        assert_eq!(markers(&with_markers), 3);
+        assert_eq!(markers(&without_markers), 0);
+        assert_eq!(test_util::dimensions(&with_markers), (64, 64));
+    }
+
+    #[test]
+    fn lower_jpeg_quality_makes_smaller_files() {
+        let img = test_util::photo(160, 120);
+        let preset = ResizePreset {
+            output_format: OutputFormat::Jpeg,
+            ..ResizePreset::BASE
+        };
+
+        let low = ImageResizer::encode_jpeg(&img, 50, &preset).unwrap();
+        let high = ImageResizer::encode_jpeg(&img, 95, &preset).unwrap();
+
+        assert!(low.len() < high.len(), "{} >= {}", low.len(), high.len());
+    }
+
+    #[test]
+    fn jpeg_quality_param_overrides_the_preset() {
+        let preset = ResizePreset {
+            width: 64,
+            height: 64,
+            output_format: OutputFormat::Jpeg,
+            ..ResizePreset::BASE
+        };
+        let params = encoder_params::parse_lines("jpeg.quality=70").unwrap();
+        let overridden = resize_to_bytes(&ResizePreset {
+            encoder_params: encoder_params::parse(&params, &|_| {}).unwrap(),
+            ..preset
+        });
+
+        assert_ne!(preset.jpeg_quality, 70);
+        assert_eq!(
+            overridden,
+            resize_to_bytes(&ResizePreset {
+                jpeg_quality: 70,
+                ..preset
+            })
+        );
+        assert_ne!(overridden, resize_to_bytes(&preset));
+    }
+
+    #[test]
+    fn ssim_target_is_met_below_maximum_quality() {
+        let img = test_util::photo(160, 120);
+        let preset = ResizePreset {
+            output_format: OutputFormat::Jpeg,
+            ..ResizePreset::BASE
+        };
+
+        let (quality, bytes, ssim) =
+            ImageResizer::encode_jpeg_for_ssim(&img, 0.95, &preset).unwrap();
+
+        let decoded = image::load_from_memory(&bytes).unwrap();
+        let measured = ssim::mean_ssim(&img.to_luma8(), &decoded.to_luma8());
+        assert!(measured >= 0.95, "SSIM {measured}");
+        assert!((measured - ssim).abs() < 1e-9);
+        assert!(quality < 100, "quality {quality}");
+        // The next lower quality misses the target, so the search didn't stop early
+        let lower = ImageResizer::encode_jpeg(&img, quality - 1, &preset).unwrap();
+        let lower = image::load_from_memory(&lower).unwrap();
+        assert!(ssim::mean_ssim(&img.to_luma8(), &lower.to_luma8()) < 0.95);
+    }
+
+    #[test]
+    fn tiny_budget_shrinks_the_image_at_the_quality_floor() {
+        let img = test_util::photo(320, 240);
+        let preset = ResizePreset {
+            output_format: OutputFormat::Jpeg,
+            ..ResizePreset::BASE
+        };
+        let budget = ByteBudget {
+            max_bytes: 4 * 1024,
+            min_quality: 60,
+        };
+        let at_floor = ImageResizer::encode_jpeg(&img, budget.min_quality, &preset).unwrap();
+        assert!(at_floor.len() as u64 > budget.max_bytes);
+
+        let (quality, bytes, (width, height)) =
+            ImageResizer::encode_jpeg_for_budget(&img, budget, &preset).unwrap();
+
+        assert!(
+            bytes.len() as u64 <= budget.max_bytes,
+            "{} bytes",
+            bytes.len()
+        );
+        assert!(quality >= budget.min_quality, "quality {quality}");
+        assert!(width < 320 && height < 240);
+        assert_eq!(test_util::dimensions(&bytes), (width, height));
+    }
+
+    #[test]
+    fn batch_hands_every_output_to_the_sink() {
+        let png = test_util::encode(&test_util::gradient(40, 20), ImageFormat::Png);
+        let jpeg = test_util::encode(&test_util::gradient(20, 40), ImageFormat::Jpeg);
+        let inputs = [
+            ReadAhead::new(Path::new("in/wide.png"), Ok(png.into())),
+            ReadAhead::new(Path::new("in/tall.jpg"), Ok(jpeg.into())),
+        ];
+        let preset = ResizePreset {
+            width: 10,
+            height: 10,
+            ..ResizePreset::BASE
+        };
+        let sink = MemorySink::default();
+
+        let results = ImageResizer::batch_resize(
+            &inputs,
+            Path::new("out"),
+            &preset,
+            &Naming::default(),
+            &sink,
+            |_, _| ControlFlow::Continue(()),
+            |_| {},
+        )
+        .unwrap();
+
+        let mut files = sink.into_files();
+        files.sort();
+        let keys: Vec<_> = files.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(
+            keys,
+            ["out/tall_resized_5x10.jpg", "out/wide_resized_10x5.png",].map(PathBuf::from)
+        );
+        let written: Vec<_> = results
+            .into_iter()
+            .flat_map(Result::unwrap)
+            .map(|file| (file.path, file.bytes))
+            .collect();
+        for (path, bytes) in &files {
+            assert!(written.contains(&(path.clone(), bytes.len() as u64)));
+        }
+        assert_eq!(test_util::dimensions(&files[1].1), (10, 5));
+    }
+
+    #[test]
+    fn stretch_within_aspect_tolerance_fits_instead() {
+        let preset = ResizePreset {
+            width: 800,
+            height: 600,
+            mode: ResizeMode::Stretch,
+            aspect_tolerance: Some(0.01),
+            ..ResizePreset::BASE
+        };
+
+        // 4000x2990 is 0.3% away from 4:3, fitting keeps its ratio exactly
+        assert_eq!(
+            ImageResizer::target_size((4000, 2990), &preset),
+            ((800, 598), ResizeMode::Fit)
+        );
+        // 16:9 is far from 4:3 and is stretched as asked
+        assert_eq!(
+            ImageResizer::target_size((1920, 1080), &preset),
+            ((800, 600), ResizeMode::Stretch)
+        );
+        // Without a tolerance even the near match is stretched
+        let exact = ResizePreset {
+            aspect_tolerance: None,
+            ..preset
+        };
+        assert_eq!(
+            ImageResizer::target_size((4000, 2990), &exact),
+            ((800, 600), ResizeMode::Stretch)
+        );
+    }
+
+    #[test]
+    fn square_thumbnails_fit_either_orientation() {
+        let thumbnail = ResizePreset {
+            width: 150,
+            height: 150,
+            ..ResizePreset::BASE
+        };
+        for (source, expected) in [((1920, 1080), (150, 84)), ((1080, 1920), (84, 150))] {
+            assert_eq!(
+                ImageResizer::output_dimensions(source, &thumbnail),
+                expected
+            );
+
+            let bytes = test_util::encode(
+                &test_util::gradient(source.0 / 8, source.1 / 8),
+                ImageFormat::Png,
+            );
+            let sink = MemorySink::default();
+            ImageResizer::resize_image(
+                Path::new("in.png"),
+                &bytes,
+                Path::new("out.png"),
+                &thumbnail,
+                &sink,
+                &|_| {},
+            )
+            .unwrap();
+            let (width, height) = test_util::dimensions(&sink.into_files()[0].1);
+            assert!(width <= 150 && height <= 150, "{width}x{height}");
+            assert_eq!(width.max(height), 150);
+        }
+    }
+
+    #[test]
+    fn empty_batch_completes_without_work() {
+        let progress = Mutex::new(Vec::new());
+        let results = ImageResizer::batch_resize::<ReadAhead>(
+            &[],
+            Path::new("out"),
+            &ResizePreset::BASE,
+            &Naming::default(),
+            &MemorySink::default(),
+            |current, total| {
+                progress.lock().unwrap().push((current, total));
+                ControlFlow::Continue(())
+            },
+            |_| {},
+        )
+        .unwrap();
+
+        assert!(results.is_empty());
+        assert_eq!(progress.into_inner().unwrap().last(), Some(&(0, 0)));
+    }
+
+    #[test]
+    fn degenerate_targets_make_one_pixel_outputs() {
+        for (width, height) in [(1, 1), (0, 0), (0, 1)] {
+            let output = resize_to_bytes(&ResizePreset {
+                width,
+                height,
+                mode: ResizeMode::Stretch,
+                output_format: OutputFormat::Png,
+                ..ResizePreset::BASE
+            });
+            assert_eq!(test_util::dimensions(&output), (1, 1));
+        }
+        let fitted = resize_to_bytes(&ResizePreset {
+            width: 1,
+            height: 1,
+            output_format: OutputFormat::Jpeg,
+            ..ResizePreset::BASE
+        });
+        assert_eq!(test_util::dimensions(&fitted), (1, 1));
+    }
+
+    #[test]
+    fn outputs_replace_their_source_only_when_allowed() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("photo.png");
+        let original = test_util::encode(&test_util::gradient(64, 64), ImageFormat::Png);
+        std::fs::write(&input, &original).unwrap();
+        let preset = ResizePreset {
+            width: 32,
+            height: 32,
+            ..ResizePreset::BASE
+        };
+        let run = |allow_input_overwrite| {
+            let naming = Naming {
+                template: "{stem}".to_string(),
+                allow_input_overwrite,
+                ..Naming::default()
+            };
+            let mut results = ImageResizer::batch_resize(
+                &[crate::source::FileSource::from(input.clone())],
+                dir.path(),
+                &preset,
+                &naming,
+                &crate::sink::FileSystemSink,
+                |_, _| ControlFlow::Continue(()),
+                |_| {},
+            )
+            .unwrap();
+            results.remove(0)
+        };
+
+        let error = run(false).unwrap_err().to_string();
+        assert!(
+            error.starts_with("Refusing to replace the source image"),
+            "{error}"
+        );
+        assert_eq!(std::fs::read(&input).unwrap(), original);
+
+        let written = run(true).unwrap();
+        assert_eq!(written[0].path, input);
+        assert_eq!(
+            test_util::dimensions(&std::fs::read(&input).unwrap()),
+            (32, 32)
+        );
+    }
+
+    #[test]
+    fn file_name_sizes_override_the_preset() {
+        let bytes = test_util::encode(&test_util::gradient(400, 300), ImageFormat::Png);
+        let inputs = ["logo@800x600.png", "pic.png", "résuméx.png"]
+            .map(|name| ReadAhead::new(Path::new(name), Ok(bytes.clone().into())));
+        let preset = ResizePreset {
+            width: 100,
+            height: 100,
+            size_from_file_name: true,
+            ..ResizePreset::BASE
+        };
+        let sink = MemorySink::default();
+
+        ImageResizer::batch_resize(
+            &inputs,
+            Path::new("out"),
+            &preset,
+            &Naming::default(),
+            &sink,
+            |_, _| ControlFlow::Continue(()),
+            |_| {},
+        )
+        .unwrap();
+
+        let mut sizes: Vec<_> = sink
+            .into_files()
+            .into_iter()
+            .map(|(path, data)| (path, test_util::dimensions(&data)))
+            .collect();
+        sizes.sort();
+        assert_eq!(
+            sizes,
+            [
+                (
+                    PathBuf::from("out/logo@800x600_resized_800x600.png"),
+                    (800, 600)
+                ),
+                (PathBuf::from("out/pic_resized_100x75.png"), (100, 75)),
+                (PathBuf::from("out/résuméx_resized_100x75.png"), (100, 75)),
+            ]
+        );
+    }
+
+    #[test]
+    fn proxy_layout_keeps_the_original_next_to_the_proxy() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("clip.png");
+        let original = test_util::encode(&test_util::gradient(200, 100), ImageFormat::Png);
+        std::fs::write(&input, &original).unwrap();
+        let output_dir = dir.path().join("out");
+        let naming = Naming {
+            proxy_layout: true,
+            ..Naming::default()
+        };
+
+        let results = ImageResizer::batch_resize(
+            &[crate::source::FileSource::from(input)],
+            &output_dir,
+            &ResizePreset {
+                width: 50,
+                height: 50,
+                ..ResizePreset::BASE
+            },
+            &naming,
+            &crate::sink::FileSystemSink,
+            |_, _| ControlFlow::Continue(()),
+            |_| {},
+        )
+        .unwrap();
+
+        let proxy = output_dir.join("proxies/clip_resized_50x25.png");
+        assert_eq!(results[0].as_ref().unwrap()[0].path, proxy);
+        assert_eq!(
+            test_util::dimensions(&std::fs::read(&proxy).unwrap()),
+            (50, 25)
+        );
+        let copy = std::fs::read(output_dir.join("originals/clip.png")).unwrap();
+        assert_eq!(copy, original);
+    }
+
+    #[test]
+    fn solid_blue_reports_a_blue_average() {
+        let blue = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            40,
+            30,
+            image::Rgb([10, 20, 240]),
+        ));
+        let bytes = test_util::encode(&blue, ImageFormat::Jpeg);
+        let preset = ResizePreset {
+            width: 20,
+            height: 20,
+            average_color: true,
+            ..ResizePreset::BASE
+        };
+        let logged = Mutex::new(Vec::new());
+        let sink = MemorySink::default();
+
+        let written = ImageResizer::resize_image(
+            Path::new("sky.jpg"),
+            &bytes,
+            Path::new("sky_small.jpg"),
+            &preset,
+            &sink,
+            &|message| logged.lock().unwrap().push(message),
+        )
+        .unwrap();
+
+        assert_eq!(written[1], Path::new("sky_small_swatch.png"));
+        let files = sink.into_files();
+        let swatch = image::load_from_memory(&files[1].1).unwrap().to_rgb8();
+        let [r, g, b] = swatch.get_pixel(0, 0).0;
+        assert!(r < 30 && g < 40 && b > 220, "{r} {g} {b}");
+        let logged = logged.into_inner().unwrap();
+        let report = logged
+            .iter()
+            .find(|message| message.contains("average color"))
+            .unwrap();
+        assert!(
+            report.contains(&color::to_hex(image::Rgb([r, g, b]))),
+            "{report}"
+        );
+    }
+
+    #[test]
+    fn dpr_variants_are_named_and_sized_by_scale() {
+        let bytes = test_util::encode(&test_util::gradient(900, 900), ImageFormat::Png);
+        let preset = ResizePreset {
+            width: 300,
+            height: 300,
+            dpr_variants: true,
+            ..ResizePreset::BASE
+        };
+        let sink = MemorySink::default();
+
+        ImageResizer::resize_image(
+            Path::new("logo.png"),
+            &bytes,
+            Path::new("out/logo.png"),
+            &preset,
+            &sink,
+            &|_| {},
+        )
+        .unwrap();
+
+        let outputs: Vec<_> = sink
+            .into_files()
+            .into_iter()
+            .map(|(path, bytes)| (path, test_util::dimensions(&bytes)))
+            .collect();
+        assert_eq!(
+            outputs,
+            [
+                (PathBuf::from("out/logo.png"), (300, 300)),
+                (PathBuf::from("out/logo@2x.png"), (600, 600)),
+                (PathBuf::from("out/logo@3x.png"), (900, 900)),
+            ]
+        );
+    }
+
+    #[test]
+    fn outputs_of_one_run_share_its_batch_id() {
+        let bytes = test_util::encode(&test_util::gradient(40, 30), ImageFormat::Png);
+        let preset = ResizePreset {
+            width: 20,
+            height: 20,
+            ..ResizePreset::BASE
+        };
+        let run = || {
+            let inputs = ["a.png", "b.png", "c.png"]
+                .map(|name| ReadAhead::new(Path::new(name), Ok(bytes.clone().into())));
+            let batch_id = crate::naming::new_batch_id();
+            let naming = Naming {
+                batch_id: Some(batch_id.clone()),
+                ..Naming::default()
+            };
+            let sink = MemorySink::default();
+            ImageResizer::batch_resize(
+                &inputs,
+                Path::new("out"),
+                &preset,
+                &naming,
+                &sink,
+                |_, _| ControlFlow::Continue(()),
+                |_| {},
+            )
+            .unwrap();
+            let stems: Vec<_> = sink
+                .into_files()
+                .into_iter()
+                .map(|(path, _)| path.file_stem().unwrap().to_string_lossy().into_owned())
+                .collect();
+            (batch_id, stems)
+        };
+
+        let (first_id, first_stems) = run();
+        let (second_id, second_stems) = run();
+
+        assert_ne!(first_id, second_id);
+        for (batch_id, stems) in [(first_id, first_stems), (second_id, second_stems)] {
+            assert_eq!(stems.len(), 3);
+            for stem in stems {
+                assert!(stem.ends_with(&format!("_{batch_id}")), "{stem}");
+            }
+        }
+    }
+
+    #[test]
+    fn pixel_budget_snaps_sides_near_one_megapixel() {
+        let preset = ResizePreset {
+            pixel_budget: Some(PixelBudget {
+                pixels: 1_000_000,
+                multiple: 16,
+            }),
+            ..ResizePreset::BASE
+        };
+        for source in [(4000, 3000), (1920, 1080), (3000, 4500)] {
+            let (width, height) = ImageResizer::output_dimensions(source, &preset);
+
+            assert_eq!((width % 16, height % 16), (0, 0), "{width}x{height}");
+            let pixels = f64::from(width * height);
+            assert!((pixels / 1e6 - 1.0).abs() < 0.03, "{width}x{height}");
+            let ratio = f64::from(width) / f64::from(height);
+            let source_ratio = f64::from(source.0) / f64::from(source.1);
+            assert!(
+                (ratio / source_ratio - 1.0).abs() < 0.03,
+                "{width}x{height}"
+            );
+        }
+    }
+
+    #[test]
+    fn webp_outputs_get_one_jpeg_fallback() {
+        let bytes = test_util::encode(&test_util::gradient(120, 90), ImageFormat::Png);
+        let preset = ResizePreset {
+            width: 40,
+            height: 40,
+            output_format: OutputFormat::Webp,
+            webp_lossless: true,
+            jpeg_fallback: true,
+            ..ResizePreset::BASE
+        };
+        let sink = MemorySink::default();
+
+        let written = ImageResizer::resize_image(
+            Path::new("photo.png"),
+            &bytes,
+            Path::new("out/photo.webp"),
+            &preset,
+            &sink,
+            &|_| {},
+        )
+        .unwrap();
+
+        assert_eq!(
+            written,
+            [
+                PathBuf::from("out/photo.webp"),
+                PathBuf::from("out/photo.jpg")
+            ]
+        );
+        let files = sink.into_files();
+        assert_eq!(files.len(), 2);
+        for ((path, data), format) in files.iter().zip([ImageFormat::WebP, ImageFormat::Jpeg]) {
+            assert_eq!(
+                image::guess_format(data).unwrap(),
+                format,
+                "{}",
+                path.display()
+            );
+            assert_eq!(test_util::dimensions(data), (40, 30), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn blurry_sources_are_skipped() {
+        let sharp = test_util::photo(200, 150);
+        let blurry = sharp.blur(6.0);
+        let threshold = 100.0;
+        assert!(sharpness::laplacian_variance(&sharp) > f64::from(threshold));
+        assert!(sharpness::laplacian_variance(&blurry) < f64::from(threshold));
+
+        let preset = ResizePreset {
+            width: 50,
+            height: 50,
+            min_sharpness: Some(threshold),
+            ..ResizePreset::BASE
+        };
+        let logged = Mutex::new(Vec::new());
+        let sink = MemorySink::default();
+        let mut written = Vec::new();
+        for (name, img) in [("sharp.png", &sharp), ("blurry.png", &blurry)] {
+            written.push(
+                ImageResizer::resize_image(
+                    Path::new(name),
+                    &test_util::encode(img, ImageFormat::Png),
+                    &Path::new("out").join(name),
+                    &preset,
+                    &sink,
+                    &|message| logged.lock().unwrap().push(message),
+                )
+                .unwrap(),
+            );
+        }
+
+        assert_eq!(written[0], [PathBuf::from("out/sharp.png")]);
+        assert!(written[1].is_empty());
+        assert_eq!(sink.into_files().len(), 1);
+        let logged = logged.into_inner().unwrap();
+        assert!(
+            logged
+                .iter()
+                .any(|message| message.starts_with("blurry.png: too blurry, skipped")),
+            "{logged:?}"
+        );
+    }
+
+    #[test]
+    fn rotated_sources_are_named_by_their_oriented_size() {
+        let bytes = test_util::jpeg_with_exif(
+            &test_util::gradient(60, 40),
+            &[test_util::exif_orientation(6)],
+        );
+        let preset = ResizePreset {
+            width: 30,
+            height: 30,
+            ..ResizePreset::BASE
+        };
+        assert_eq!(test_util::dimensions(&bytes), (60, 40));
+        assert_eq!(probe::source_dimensions(&bytes, &preset), Some((40, 60)));
+
+        let inputs = [ReadAhead::new(Path::new("photo.jpg"), Ok(bytes.into()))];
+        let sink = MemorySink::default();
+        let results = ImageResizer::batch_resize(
+            &inputs,
+            Path::new("out"),
+            &preset,
+            &Naming::default(),
+            &sink,
+            |_, _| ControlFlow::Continue(()),
+            |_| {},
+        )
+        .unwrap();
+
+        let written = results[0].as_ref().unwrap();
+        assert_eq!(written[0].path, Path::new("out/photo_resized_20x30.jpg"));
+        let files = sink.into_files();
+        assert_eq!(test_util::dimensions(&files[0].1), (20, 30));
+    }
+
+    #[test]
+    fn transliterated_names_are_logged() {
+        let bytes = test_util::encode(&test_util::gradient(40, 30), ImageFormat::Jpeg);
+        let inputs = ["café.jpg", "plain.jpg"]
+            .map(|name| ReadAhead::new(Path::new(name), Ok(bytes.clone().into())));
+        let preset = ResizePreset {
+            width: 20,
+            height: 20,
+            ..ResizePreset::BASE
+        };
+        let naming = Naming {
+            transliterate: true,
+            ..Naming::default()
+        };
+        let logged = Mutex::new(Vec::new());
+        let sink = MemorySink::default();
+
+        ImageResizer::batch_resize(
+            &inputs,
+            Path::new("out"),
+            &preset,
+            &naming,
+            &sink,
+            |_, _| ControlFlow::Continue(()),
+            |message| logged.lock().unwrap().push(message),
+        )
+        .unwrap();
+
+        let paths: Vec<_> = sink
+            .into_files()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert!(
+            paths.contains(&PathBuf::from("out/cafe_resized_20x15.jpg")),
+            "{paths:?}"
+        );
+        let logged = logged.into_inner().unwrap();
+        let mappings: Vec<_> = logged
+            .iter()
+            .filter(|message| message.contains(": named "))
+            .collect();
+        assert_eq!(mappings, ["café.jpg: named cafe_resized_20x15.jpg"]);
+    }
+
+    #[test]
+    fn fill_outputs_exactly_the_target_size() {
+        let fill = |source: &DynamicImage, (width, height)| {
+            let preset = ResizePreset {
+                width,
+                height,
+                mode: ResizeMode::Fill,
+                ..ResizePreset::BASE
+            };
+            let sink = MemorySink::default();
+            ImageResizer::resize_image(
+                Path::new("photo.png"),
+                &test_util::encode(source, ImageFormat::Png),
+                Path::new("out/photo.png"),
+                &preset,
+                &sink,
+                &|_| {},
+            )
+            .unwrap();
+            image::load_from_memory(&sink.into_files()[0].1).unwrap()
+        };
+
+        for source in [(300, 200), (200, 300), (257, 131), (90, 60)] {
+            for target in [(90, 60), (60, 90), (50, 50)] {
+                let filled = fill(&test_util::gradient(source.0, source.1), target);
+                assert_eq!(filled.dimensions(), target, "{source:?} to {target:?}");
+            }
+        }
+
+        // Red, green and blue thirds, filling a square keeps only the green middle
+        let thirds = DynamicImage::ImageRgb8(image::RgbImage::from_fn(300, 100, |x, _| {
+            let mut pixel = image::Rgb([0, 0, 0]);
+            pixel.0[(x / 100) as usize] = 255;
+            pixel
+        }));
+        let filled = fill(&thirds, (50, 50)).to_rgb8();
+        for (x, y) in [(0, 0), (49, 0), (25, 25), (0, 49), (49, 49)] {
+            let [r, g, b] = filled.get_pixel(x, y).0;
+            assert!(g > 200 && r < 60 && b < 60, "{x},{y}: {r} {g} {b}");
+        }
+    }
+
+    #[test]
+    fn slow_images_time_out_while_the_rest_finish() {
+        let timeout = Duration::from_millis(200);
+        let logged = Mutex::new(Vec::new());
+        let log = |message| logged.lock().unwrap().push(message);
+        let sink = MemorySink::default();
+
+        let results: Vec<_> = [
+            ("quick.png", 0),
+            ("stuck.png", 5_000),
+            ("also-quick.png", 10),
+        ]
+        .into_iter()
+        .map(|(name, decode_millis)| {
+            let output = PathBuf::from("out").join(name);
+            ImageResizer::run_with_timeout(Path::new(name), &sink, &log, timeout, {
+                move |sink, log| {
+                    // Stands in for a decode that takes this long
+                    thread::sleep(Duration::from_millis(decode_millis));
+                    log(format!("{name}: decoded"));
+                    sink.write(&output, b"resized")?;
+                    Ok(vec![output])
+                }
+            })
+        })
+        .collect();
+
+        assert_eq!(
+            results[0].as_ref().unwrap(),
+            &[PathBuf::from("out/quick.png")]
+        );
+        assert_eq!(
+            results[1].as_ref().unwrap_err().to_string(),
+            "stuck.png: timed out after 0.2s, skipped"
+        );
+        assert_eq!(
+            results[2].as_ref().unwrap(),
+            &[PathBuf::from("out/also-quick.png")]
+        );
+        let written: Vec<_> = sink
+            .into_files()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(
+            written,
+            [
+                PathBuf::from("out/quick.png"),
+                PathBuf::from("out/also-quick.png")
+            ]
+        );
+        let logged = logged.into_inner().unwrap();
+        assert!(logged.contains(&"stuck.png: timed out after 0.2s, skipped".to_string()));
+        assert!(!logged.contains(&"stuck.png: decoded".to_string()));
+    }
+
+    #[test]
+    fn orientation_six_is_turned_upright_unless_ignored() {
+        let bytes = test_util::jpeg_with_exif(
+            &test_util::gradient(60, 40),
+            &[test_util::exif_orientation(6)],
+        );
+        let resize = |respect_exif_orientation| {
+            let preset = ResizePreset {
+                width: 30,
+                height: 30,
+                output_format: OutputFormat::Png,
+                respect_exif_orientation,
+                ..ResizePreset::BASE
+            };
+            let sink = MemorySink::default();
+            ImageResizer::resize_image(
+                Path::new("phone.jpg"),
+                &bytes,
+                Path::new("out/phone.png"),
+                &preset,
+                &sink,
+                &|_| {},
+            )
+            .unwrap();
+            image::load_from_memory(&sink.into_files()[0].1)
+                .unwrap()
+                .to_rgb8()
+        };
+
+        let upright = resize(true);
+        assert_eq!(upright.dimensions(), (20, 30));
+        // A quarter turn clockwise puts the source's top left corner at the top right
+        let [red, green, _] = upright.get_pixel(19, 0).0;
+        assert!(red < 40 && green < 40, "{red} {green}");
+        let [red, green, _] = upright.get_pixel(0, 0).0;
+        assert!(red < 40 && green > 200, "{red} {green}");
+
+        assert_eq!(resize(false).dimensions(), (30, 20));
+    }
+
+    #[test]
+    fn parallel_results_keep_the_input_order() {
+        // Larger images first, so later inputs tend to finish before earlier ones
+        let names: Vec<_> = (0..24)
+            .map(|i| PathBuf::from(format!("img{i:02}.png")))
+            .collect();
+        let inputs: Vec<_> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let side = 400 - i as u32 * 15;
+                let bytes = test_util::encode(&test_util::gradient(side, side), ImageFormat::Png);
+                ReadAhead::new(name, Ok(bytes.into()))
+            })
+            .collect();
+        let preset = ResizePreset {
+            width: 16,
+            height: 16,
+            ..ResizePreset::BASE
+        };
+        let progress = Mutex::new(Vec::new());
+
+        let results = ImageResizer::batch_resize(
+            &inputs,
+            Path::new("out"),
+            &preset,
+            &Naming::default(),
+            &MemorySink::default(),
+            |current, total| {
+                progress.lock().unwrap().push((current, total));
+                ControlFlow::Continue(())
+            },
+            |_| {},
+        )
+        .unwrap();
+
+        assert_eq!(results.len(), names.len());
+        for (name, result) in names.iter().zip(&results) {
+            let written = &result.as_ref().unwrap()[0].path;
+            let expected = format!(
+                "{}_resized_16x16.png",
+                name.file_stem().unwrap().to_string_lossy()
+            );
+            assert_eq!(written, &Path::new("out").join(expected));
+        }
+        let progress = progress.into_inner().unwrap();
+        assert!(progress
+            .iter()
+            .all(|&(current, total)| current <= total && total == 24));
+        assert_eq!(progress.iter().map(|&(current, _)| current).max(), Some(24));
+    }
+
+    #[test]
+    fn region_mask_alters_only_the_white_half() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("photo.png");
+        let source = test_util::gradient(40, 30);
+        let mask =
+            image::GrayImage::from_fn(40, 30, |x, _| image::Luma([if x < 20 { 255 } else { 0 }]));
+        mask.save(crate::region::mask_path(&input)).unwrap();
+        let preset = ResizePreset {
+            width: 10,
+            height: 10,
+            output_format: OutputFormat::Png,
+            grayscale: Some(crate::presets::GrayscaleMode::Luminosity),
+            region_mask: true,
+            ..ResizePreset::BASE
+        };
+        let sink = MemorySink::default();
+
+        ImageResizer::resize_image(
+            &input,
+            &test_util::encode(&source, ImageFormat::Png),
+            &dir.path().join("out.png"),
+            &preset,
+            &sink,
+            &|_| {},
+        )
+        .unwrap();
+
+        let (_, output) = sink.into_files().pop().unwrap();
+        let output = image::load_from_memory(&output).unwrap().to_rgb8();
+        let source = source.to_rgb8();
+        assert_eq!(output.dimensions(), (40, 30));
+        for (x, y, pixel) in output.enumerate_pixels() {
+            if x < 20 {
+                let [r, g, b] = pixel.0;
+                assert!(r == g && g == b, "({x}, {y}) is {:?}", pixel.0);
+            } else {
+                assert_eq!(pixel, source.get_pixel(x, y), "({x}, {y})");
+            }
+        }
+        // The left half has color in the source, so graying it is a visible change
+        assert!((0..20).any(|x| output.get_pixel(x, 15) != source.get_pixel(x, 15)));
+    }
+
+    #[test]
+    fn parallel_inputs_named_alike_are_renamed_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let bytes = test_util::encode(&test_util::gradient(40, 30), ImageFormat::Png);
+        let names: Vec<String> = (0..6).map(|index| format!("in{index}.png")).collect();
+        let inputs: Vec<_> = names
+            .iter()
+            .map(|name| ReadAhead::new(Path::new(name), Ok(bytes.clone().into())))
+            .collect();
+        let preset = ResizePreset {
+            width: 20,
+            height: 20,
+            output_format: OutputFormat::Png,
+            max_parallel_images: Some(6),
+            ..ResizePreset::BASE
+        };
+        let naming = Naming {
+            template: "thumb".to_owned(),
+            overwrite: crate::naming::OverwritePolicy::Rename,
+            ..Naming::default()
+        };
+
+        let results = ImageResizer::batch_resize(
+            &inputs,
+            dir.path(),
+            &preset,
+            &naming,
+            &crate::sink::FileSystemSink,
+            |_, _| ControlFlow::Continue(()),
+            |_| {},
+        )
+        .unwrap();
+
+        let written: std::collections::HashSet<_> = results
+            .into_iter()
+            .flat_map(|result| result.unwrap())
+            .map(|file| file.path)
+            .collect();
+        assert_eq!(written.len(), 6, "{written:?}");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 6);
+    }
+
+    #[test]
+    fn fit_stays_inside_both_target_sides() {
+        let preset = ResizePreset {
+            width: 800,
+            height: 600,
+            ..ResizePreset::BASE
+        };
+        // Landscape wider than the box, landscape narrower than it, portrait and square
+        for (source, expected) in [
+            ((4000, 1000), (800, 200)),
+            ((1000, 900), (667, 600)),
+            ((1200, 3000), (240, 600)),
+            ((2000, 2000), (600, 600)),
+        ] {
+            let (size, _) = ImageResizer::target_size(source, &preset);
+            assert_eq!(size, expected, "{source:?}");
+            assert!(size.0 <= 800 && size.1 <= 600, "{source:?} -> {size:?}");
+        }
+
+        let portrait = test_util::encode(&test_util::gradient(90, 160), ImageFormat::Png);
+        let landscape = test_util::encode(&test_util::gradient(160, 90), ImageFormat::Png);
+        let small_box = ResizePreset {
+            width: 80,
+            height: 60,
+            output_format: OutputFormat::Png,
+            ..ResizePreset::BASE
+        };
+        for (bytes, expected) in [(portrait, (34, 60)), (landscape, (80, 45))] {
+            let sink = MemorySink::default();
+            ImageResizer::resize_image(
+                Path::new("in.png"),
+                &bytes,
+                Path::new("out.png"),
+                &small_box,
+                &sink,
+                &|_| {},
+            )
+            .unwrap();
+            let (_, output) = sink.into_files().pop().unwrap();
+            assert_eq!(test_util::dimensions(&output), expected);
+        }
+    }
+
+    #[test]
+    fn animated_gif_keeps_every_frame() {
+        let bytes = test_util::animated_gif(20, 10, 3);
+        let preset = ResizePreset {
+            width: 10,
+            height: 10,
+            ..ResizePreset::BASE
+        };
+        let sink = MemorySink::default();
+
+        ImageResizer::resize_image(
+            Path::new("anim.gif"),
+            &bytes,
+            Path::new("out/anim.gif"),
+            &preset,
+            &sink,
+            &|_| {},
+        )
+        .unwrap();
+
+        let (_, output) = sink.into_files().pop().unwrap();
+        let frames = GifDecoder::new(Cursor::new(output))
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        assert_eq!(frames.len(), 3);
+        for frame in &frames {
+            assert_eq!(frame.buffer().dimensions(), (10, 5));
+            assert_eq!(frame.delay().numer_denom_ms(), (100, 1));
+        }
+    }
+
+    #[test]
+    fn lossless_webp_decodes_to_the_resized_pixels() {
+        let bytes = test_util::encode(&test_util::photo(120, 90), ImageFormat::Png);
+        let resize = |output_format| {
+            let preset = ResizePreset {
+                width: 60,
+                height: 60,
+                output_format,
+                webp_lossless: true,
+                ..ResizePreset::BASE
+            };
+            let sink = MemorySink::default();
+            ImageResizer::resize_image(
+                Path::new("photo.png"),
+                &bytes,
+                Path::new("out/photo"),
+                &preset,
+                &sink,
+                &|_| {},
+            )
+            .unwrap();
+            let (_, output) = sink.into_files().pop().unwrap();
+            image::load_from_memory(&output).unwrap().to_rgba8()
+        };
+
+        let webp = resize(OutputFormat::Webp);
+        // PNG is lossless too, so both hold exactly what the resize produced
+        let png = resize(OutputFormat::Png);
+        assert_eq!(webp.dimensions(), (60, 45));
+        assert_eq!(webp.as_raw(), png.as_raw());
+    }
+
+    #[test]
+    fn capture_dates_sort_outputs_into_year_and_month() {
+        let img = test_util::gradient(40, 30);
+        let dated = test_util::jpeg_with_exif(&img, &[test_util::exif_date("2023:11:04 18:05:00")]);
+        let inputs = [
+            ReadAhead::new(Path::new("dated.jpg"), Ok(dated.into())),
+            ReadAhead::new(
+                Path::new("undated.jpg"),
+                Ok(test_util::encode(&img, ImageFormat::Jpeg).into()),
+            ),
+        ];
+        let preset = ResizePreset {
+            width: 20,
+            height: 20,
+            ..ResizePreset::BASE
+        };
+        let naming = Naming {
+            subfolders: crate::naming::Subfolders::ByCaptureDate,
+            ..Naming::default()
+        };
+        let sink = MemorySink::default();
+
+        ImageResizer::batch_resize(
+            &inputs,
+            Path::new("out"),
+            &preset,
+            &naming,
+            &sink,
+            |_, _| ControlFlow::Continue(()),
+            |_| {},
+        )
+        .unwrap();
+
+        let mut paths: Vec<_> = sink
+            .into_files()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        paths.sort();
+        // An input without a date or a file to take one from stays at the top
+        assert_eq!(
+            paths,
+            [
+                PathBuf::from("out/2023/11/dated_resized_20x15.jpg"),
+                PathBuf::from("out/undated_resized_20x15.jpg"),
+            ]
+        );
+    }
+
+    #[test]
+    fn separate_readers_and_workers_process_every_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let inputs: Vec<crate::source::FileSource> = (0..10_u32)
+            .map(|i| {
+                let path = dir.path().join(format!("img{i}.png"));
+                // One input is missing, its read fails without stopping the others
+                if i != 4 {
+                    let img = test_util::gradient(40 + i * 10, 30);
+                    std::fs::write(&path, test_util::encode(&img, ImageFormat::Png)).unwrap();
+                }
+                crate::source::FileSource::from(path)
+            })
+            .collect();
+        let preset = ResizePreset {
+            width: 20,
+            height: 20,
+            output_format: OutputFormat::Png,
+            max_parallel_reads: Some(3),
+            max_parallel_images: Some(2),
+            ..ResizePreset::BASE
+        };
+        let sink = MemorySink::default();
+
+        let results = ImageResizer::batch_resize(
+            &inputs,
+            Path::new("out"),
+            &preset,
+            &Naming::default(),
+            &sink,
+            |_, _| ControlFlow::Continue(()),
+            |_| {},
+        )
+        .unwrap();
+
+        assert_eq!(results.len(), 10);
+        for (i, result) in results.iter().enumerate() {
+            if i == 4 {
+                assert!(result.is_err());
+                continue;
+            }
+            let written = &result.as_ref().unwrap()[0].path;
+            assert!(
+                written.starts_with("out")
+                    && written.to_string_lossy().contains(&format!("img{i}_")),
+                "{written:?}"
+            );
+        }
+        let files = sink.into_files();
+        assert_eq!(files.len(), 9);
+        for (path, bytes) in files {
+            let (width, _) = test_util::dimensions(&bytes);
+            assert_eq!(width, 20, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn lqip_is_a_much_smaller_copy() {
+        let bytes = test_util::encode(&test_util::photo(800, 600), ImageFormat::Png);
+        let preset = ResizePreset {
+            width: 400,
+            height: 400,
+            output_format: OutputFormat::Jpeg,
+            lqip: true,
+            ..ResizePreset::BASE
+        };
+        let sink = MemorySink::default();
+
+        let written = ImageResizer::resize_image(
+            Path::new("hero.png"),
+            &bytes,
+            Path::new("out/hero.jpg"),
+            &preset,
+            &sink,
+            &|_| {},
+        )
+        .unwrap();
+
+        assert_eq!(
+            written,
+            [
+                PathBuf::from("out/hero.jpg"),
+                PathBuf::from("out/hero_lqip.jpg")
+            ]
+        );
+        let files = sink.into_files();
+        assert_eq!(test_util::dimensions(&files[0].1), (400, 300));
+        assert_eq!(test_util::dimensions(&files[1].1), (LQIP_SIZE, 24));
+        assert!(files[1].1.len() * 10 < files[0].1.len());
+    }
+
+    #[test]
+    fn stretch_filter_applies_only_when_stretching() {
+        let img = test_util::photo(90, 60);
+        let preset = |mode| ResizePreset {
+            width: 40,
+            height: 40,
+            mode,
+            filter: ResampleFilter::Lanczos3,
+            stretch_filter: Some(ResampleFilter::Nearest),
+            ..ResizePreset::BASE
+        };
+        let scale = |mode| {
+            let preset = preset(mode);
+            ImageResizer::scale_dynamic_image(&img, &preset, preset.filter.filter_type())
+        };
+
+        let stretched = scale(ResizeMode::Stretch);
+        assert_eq!(stretched, img.resize_exact(40, 40, FilterType::Nearest));
+        assert_ne!(stretched, img.resize_exact(40, 40, FilterType::Lanczos3));
+
+        let fitted = scale(ResizeMode::Fit);
+        assert_eq!(fitted, img.resize_exact(40, 27, FilterType::Lanczos3));
+        assert_ne!(fitted, img.resize_exact(40, 27, FilterType::Nearest));
+    }
+
+    #[test]
+    fn prevent_upscale_keeps_sources_inside_the_target() {
+        let preset = ResizePreset {
+            width: 200,
+            height: 300,
+            output_format: OutputFormat::Jpeg,
+            prevent_upscale: true,
+            ..ResizePreset::BASE
+        };
+
+        // Only converted, the 128x128 source stays as it is
+        let bytes = resize_to_bytes(&preset);
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Jpeg);
+        assert_eq!(test_util::dimensions(&bytes), (128, 128));
+    }
+
+    #[test]
+    fn prevent_upscale_still_shrinks_to_one_smaller_side() {
+        for (width, height) in [(200, 64), (64, 200)] {
+            let preset = ResizePreset {
+                width,
+                height,
+                output_format: OutputFormat::Png,
+                prevent_upscale: true,
+                ..ResizePreset::BASE
+            };
+
+            let bytes = resize_to_bytes(&preset);
+            assert_eq!(test_util::dimensions(&bytes), (64, 64), "{width}x{height}");
+        }
+    }
+
+    #[test]
+    fn prevent_upscale_ignores_scales_of_100_percent_and_more() {
+        let scaled = |percent, prevent_upscale| {
+            let preset = ResizePreset {
+                scale_percent: Some(percent),
+                output_format: OutputFormat::Png,
+                prevent_upscale,
+                ..ResizePreset::BASE
+            };
+            test_util::dimensions(&resize_to_bytes(&preset))
+        };
+
+        assert_eq!(scaled(100, true), (128, 128));
+        assert_eq!(scaled(150, true), (128, 128));
+        assert_eq!(scaled(150, false), (192, 192));
+        // Shrinking is unaffected
+        assert_eq!(scaled(50, true), (64, 64));
+    }
+
+    #[test]
+    fn padded_fit_fills_the_bars_with_the_background() {
+        let preset = ResizePreset {
+            width: 100,
+            height: 100,
+            pad_to_target: true,
+            background: [20, 40, 60, 255],
+            output_format: OutputFormat::Png,
+            ..ResizePreset::BASE
+        };
+        let sink = MemorySink::default();
+
+        ImageResizer::resize_image(
+            Path::new("wide.png"),
+            &test_util::encode(&test_util::photo(400, 200), ImageFormat::Png),
+            Path::new("out/wide.png"),
+            &preset,
+            &sink,
+            &|_| {},
+        )
+        .unwrap();
+
+        let padded = image::load_from_memory(&sink.into_files()[0].1)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(padded.dimensions(), (100, 100));
+        // Scaled to 100x50 and centered, leaving 25 rows above and below
+        for (x, y, pixel) in padded.enumerate_pixels() {
+            if !(25..75).contains(&y) {
+                assert_eq!(pixel.0, [20, 40, 60, 255], "{x},{y}");
+            }
+        }
+        assert!((25..75).any(|y| padded.get_pixel(50, y).0 != [20, 40, 60, 255]));
+    }
 }
@@ -0,0 +1,59 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use crate::naming;
+
+/// Output of a headless run. Results go to stdout and messages to stderr as usual, and
+/// both are copied with a UTC timestamp into the log file when there is one.
+pub struct RunLog {
+    file: Option<Mutex<File>>,
+}
+
+impl RunLog {
+    /// Only prints, for runs without a log file.
+    pub const fn console() -> Self {
+        Self { file: None }
+    }
+
+    /// Starts `path` afresh for this run. With `rotate`, the previous run's log is kept
+    /// next to it with `.1` appended to the name instead of being overwritten.
+    pub fn open(path: &Path, rotate: bool) -> Result<Self> {
+        if rotate && path.exists() {
+            let mut rotated = path.as_os_str().to_os_string();
+            rotated.push(".1");
+            std::fs::rename(path, PathBuf::from(rotated))
+                .with_context(|| format!("Failed to rotate log file: {}", path.display()))?;
+        }
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create log file: {}", path.display()))?;
+        Ok(Self {
+            file: Some(Mutex::new(file)),
+        })
+    }
+
+    /// A per-file result or the final summary.
+    pub fn result(&self, line: &str) {
+        println!("{line}");
+        self.write(line);
+    }
+
+    /// A warning or progress note.
+    pub fn message(&self, line: &str) {
+        eprintln!("{line}");
+        self.write(line);
+    }
+
+    fn write(&self, line: &str) {
+        if let Some(file) = &self.file {
+            // Written line by line so an interrupted run keeps everything up to that point
+            let _ = writeln!(
+                file.lock().unwrap_or_else(PoisonError::into_inner),
+                "{} {line}",
+                naming::utc_timestamp()
+            );
+        }
+    }
+}
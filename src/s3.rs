@@ -0,0 +1,343 @@
+use anyhow::{bail, ensure, Context, Result};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use crate::checksum::sha256_hex;
+use crate::naming;
+use crate::sink::OutputSink;
+
+/// SHA-256 hashes input in blocks of this many bytes, the HMAC key is padded to it.
+const HMAC_BLOCK_SIZE: usize = 64;
+
+/// An S3-compatible bucket outputs are uploaded to, such as AWS S3 or `MinIO`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct S3Config {
+    /// Base URL of the service, e.g. `https://s3.eu-central-1.amazonaws.com` or
+    /// `http://localhost:9000`. Buckets are addressed in the path, which every service takes.
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    /// Put in front of every key, e.g. `thumbnails/`.
+    pub prefix: String,
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+impl S3Config {
+    /// `self` with the credentials from `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`, so
+    /// they never have to be typed into or stored by the app.
+    pub fn with_env_credentials(self) -> Result<Self> {
+        let read = |name: &str| {
+            std::env::var(name).with_context(|| format!("{name} must be set to upload to S3"))
+        };
+        Ok(Self {
+            access_key: read("AWS_ACCESS_KEY_ID")?,
+            secret_key: read("AWS_SECRET_ACCESS_KEY")?,
+            ..self
+        })
+    }
+}
+
+/// Uploads each output to the bucket instead of writing it to disk. An output's key is its
+/// path relative to the output folder, after the configured prefix.
+pub struct S3Sink {
+    config: S3Config,
+    root: PathBuf,
+    agent: ureq::Agent,
+}
+
+impl S3Sink {
+    pub fn new(config: S3Config, root: &Path) -> Self {
+        Self {
+            config,
+            root: root.to_path_buf(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_mins(1))
+                .build(),
+        }
+    }
+
+    /// Object key for the output at `path`, which has to be inside the output folder.
+    pub fn key(&self, path: &Path) -> Result<String> {
+        let relative = path.strip_prefix(&self.root).with_context(|| {
+            format!(
+                "{} is outside the output folder {}",
+                path.display(),
+                self.root.display()
+            )
+        })?;
+        let segments = relative
+            .components()
+            .map(|component| match component {
+                Component::Normal(segment) => Ok(segment.to_string_lossy()),
+                _ => bail!("Invalid output path for a key: {}", path.display()),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        ensure!(
+            !segments.is_empty(),
+            "Output path {} has no file name",
+            path.display()
+        );
+        Ok(format!("{}{}", self.config.prefix, segments.join("/")))
+    }
+
+    /// Signed headers for a `PUT` of `payload` to `uri`, at `timestamp` such as
+    /// `20240518T093012Z`, following AWS Signature Version 4.
+    fn authorization(&self, uri: &str, payload_hash: &str, timestamp: &str) -> String {
+        const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+        let config = &self.config;
+        let host = config
+            .endpoint
+            .split_once("://")
+            .map_or(config.endpoint.as_str(), |(_, rest)| rest)
+            .trim_end_matches('/');
+        let canonical_request = format!(
+            "PUT\n{uri}\n\nhost:{host}\nx-amz-content-sha256:{payload_hash}\n\
+             x-amz-date:{timestamp}\n\n{SIGNED_HEADERS}\n{payload_hash}"
+        );
+        let date = &timestamp[..8];
+        let scope = format!("{date}/{}/s3/aws4_request", config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{timestamp}\n{scope}\n{}",
+            sha256_hex(canonical_request.as_bytes())
+        );
+        let key = signing_key(&config.secret_key, date, &config.region, "s3");
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={SIGNED_HEADERS}, \
+             Signature={}",
+            config.access_key,
+            hex(&hmac_sha256(&key, string_to_sign.as_bytes()))
+        )
+    }
+}
+
+impl OutputSink for S3Sink {
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        let key = self.key(path)?;
+        let uri = format!(
+            "/{}/{}",
+            uri_encode(&self.config.bucket),
+            key.split('/').map(uri_encode).collect::<Vec<_>>().join("/")
+        );
+        // `2024-05-18T09:30:12Z` in the compact form signatures use
+        let timestamp = naming::utc_timestamp().replace(['-', ':'], "");
+        let payload_hash = sha256_hex(bytes);
+
+        let url = format!("{}{uri}", self.config.endpoint.trim_end_matches('/'));
+        let result = self
+            .agent
+            .put(&url)
+            .set("x-amz-date", &timestamp)
+            .set("x-amz-content-sha256", &payload_hash)
+            .set(
+                "Authorization",
+                &self.authorization(&uri, &payload_hash, &timestamp),
+            )
+            .send_bytes(bytes);
+        match result {
+            Ok(_) => Ok(()),
+            // The service explains refusals in the body, e.g. `<Code>NoSuchBucket</Code>`
+            Err(ureq::Error::Status(status, response)) => bail!(
+                "Upload of {key} was refused with status {status}: {}",
+                response.into_string().unwrap_or_default().trim()
+            ),
+            Err(e) => Err(e).with_context(|| format!("Failed to upload {key}")),
+        }
+    }
+}
+
+/// Percent-encodes everything but the characters S3 leaves as they are in a path segment.
+fn uri_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0_u8; HMAC_BLOCK_SIZE];
+    if key.len() > HMAC_BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |value: u8| block.map(|byte| byte ^ value);
+
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+/// The key requests of one day, region and service are signed with.
+fn signing_key(secret_key: &str, date: &str, region: &str, service: &str) -> [u8; 32] {
+    [region, service, "aws4_request"].iter().fold(
+        hmac_sha256(format!("AWS4{secret_key}").as_bytes(), date.as_bytes()),
+        |key, part| hmac_sha256(&key, part.as_bytes()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::naming::Naming;
+    use crate::presets::{OutputFormat, ResizePreset};
+    use crate::resizer::ImageResizer;
+    use crate::source::ReadAhead;
+    use crate::test_util;
+    use image::ImageFormat;
+    use std::io::{BufRead, BufReader, Read, Write as _};
+    use std::ops::ControlFlow;
+    use std::sync::mpsc;
+
+    /// A received `PUT`: its path, `Authorization` header and body.
+    type Upload = (String, String, Vec<u8>);
+
+    /// Stands in for the service, answering `requests` uploads with 200. Returns the endpoint
+    /// and what was uploaded.
+    fn mock_s3(requests: usize) -> (String, mpsc::Receiver<Upload>) {
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for _ in 0..requests {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let path = line.split(' ').nth(1).unwrap().to_string();
+                let (mut authorization, mut length) = (String::new(), 0);
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    let Some((name, value)) = line.trim_end().split_once(": ") else {
+                        break;
+                    };
+                    match name.to_ascii_lowercase().as_str() {
+                        "authorization" => authorization = value.to_string(),
+                        "content-length" => length = value.parse().unwrap(),
+                        _ => {}
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .unwrap();
+                sender.send((path, authorization, body)).unwrap();
+            }
+        });
+        (endpoint, receiver)
+    }
+
+    fn config(endpoint: &str) -> S3Config {
+        S3Config {
+            endpoint: endpoint.to_string(),
+            region: "eu-central-1".to_string(),
+            bucket: "photos".to_string(),
+            prefix: "web/".to_string(),
+            access_key: "AKIDEXAMPLE".to_string(),
+            secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+        }
+    }
+
+    #[test]
+    fn batch_outputs_are_uploaded_under_their_keys() {
+        let (endpoint, uploads) = mock_s3(2);
+        let sink = S3Sink::new(config(&endpoint), Path::new("out"));
+        let bytes = test_util::encode(&test_util::gradient(40, 30), ImageFormat::Png);
+        let inputs = ["beach.png", "my photo.png"]
+            .map(|name| ReadAhead::new(Path::new(name), Ok(bytes.clone().into())));
+        let preset = ResizePreset {
+            width: 20,
+            height: 20,
+            output_format: OutputFormat::Png,
+            ..ResizePreset::BASE
+        };
+
+        let results = ImageResizer::batch_resize(
+            &inputs,
+            Path::new("out"),
+            &preset,
+            &Naming::default(),
+            &sink,
+            |_, _| ControlFlow::Continue(()),
+            |_| {},
+        )
+        .unwrap();
+
+        assert!(results.iter().all(Result::is_ok));
+        let mut received: Vec<_> = uploads.iter().take(2).collect();
+        received.sort();
+        let paths: Vec<_> = received.iter().map(|(path, _, _)| path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/photos/web/beach_resized_20x15.png",
+                "/photos/web/my%20photo_resized_20x15.png"
+            ]
+        );
+        for (_, authorization, body) in &received {
+            assert!(
+                authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"),
+                "{authorization}"
+            );
+            assert!(authorization.contains("/eu-central-1/s3/aws4_request"));
+            assert_eq!(test_util::dimensions(body), (20, 15));
+        }
+    }
+
+    #[test]
+    fn keys_follow_the_output_folder() {
+        let sink = S3Sink::new(config("http://localhost:9000"), Path::new("/data/out"));
+
+        assert_eq!(
+            sink.key(Path::new("/data/out/2024/06/a.jpg")).unwrap(),
+            "web/2024/06/a.jpg"
+        );
+        assert!(sink.key(Path::new("/data/other/a.jpg")).is_err());
+        assert!(sink.key(Path::new("/data/out")).is_err());
+    }
+
+    #[test]
+    fn signing_matches_the_published_examples() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // The derived signing key from the AWS Signature Version 4 documentation
+        assert_eq!(
+            hex(&signing_key(
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                "20120215",
+                "us-east-1",
+                "iam"
+            )),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+        assert_eq!(uri_encode("my photo (1).jpg"), "my%20photo%20%281%29.jpg");
+    }
+}
@@ -0,0 +1,200 @@
+use image::{DynamicImage, RgbaImage};
+
+/// Shrinks `img` to `width` by `height` by removing seams, the connected paths of pixels
+/// with the least contrast, so detailed subjects survive a change of aspect ratio.
+///
+/// Each removed seam costs a pass over the whole image; callers should shrink the image
+/// uniformly first so only the aspect ratio change is carved. Neither side may grow.
+pub fn carve(img: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+    debug_assert!(width <= img.width() && height <= img.height());
+    let mut grid = Grid::from_image(&img.to_rgba8());
+
+    grid.carve_columns(width.max(1) as usize);
+    if height < img.height() {
+        // Horizontal seams are vertical seams of the transposed image
+        grid = grid.transposed();
+        grid.carve_columns(height.max(1) as usize);
+        grid = grid.transposed();
+    }
+
+    let carved = grid.into_image();
+    if img.color().has_alpha() {
+        DynamicImage::ImageRgba8(carved)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(carved).to_rgb8())
+    }
+}
+
+/// Row-major pixels that columns can be removed from in place.
+struct Grid {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>,
+}
+
+impl Grid {
+    fn from_image(img: &RgbaImage) -> Self {
+        Self {
+            width: img.width() as usize,
+            height: img.height() as usize,
+            pixels: img.pixels().map(|pixel| pixel.0).collect(),
+        }
+    }
+
+    fn into_image(self) -> RgbaImage {
+        RgbaImage::from_raw(
+            self.width as u32,
+            self.height as u32,
+            self.pixels.into_iter().flatten().collect(),
+        )
+        .expect("grid holds width * height pixels")
+    }
+
+    fn transposed(&self) -> Self {
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for x in 0..self.width {
+            for y in 0..self.height {
+                pixels.push(self.pixels[y * self.width + x]);
+            }
+        }
+        Self {
+            width: self.height,
+            height: self.width,
+            pixels,
+        }
+    }
+
+    fn carve_columns(&mut self, target_width: usize) {
+        while self.width > target_width {
+            let seam = self.lowest_energy_seam();
+            self.remove_seam(&seam);
+        }
+    }
+
+    /// Gradient magnitude of the luma, the sum of the horizontal and vertical differences.
+    fn energy(&self) -> Vec<f32> {
+        let luma: Vec<f32> = self
+            .pixels
+            .iter()
+            .map(|&[r, g, b, _]| {
+                0.114_f32.mul_add(
+                    f32::from(b),
+                    0.299_f32.mul_add(f32::from(r), 0.587 * f32::from(g)),
+                )
+            })
+            .collect();
+        let at = |x: usize, y: usize| luma[y * self.width + x];
+
+        let mut energy = Vec::with_capacity(luma.len());
+        for y in 0..self.height {
+            let (up, down) = (y.saturating_sub(1), (y + 1).min(self.height - 1));
+            for x in 0..self.width {
+                let (left, right) = (x.saturating_sub(1), (x + 1).min(self.width - 1));
+                energy.push((at(right, y) - at(left, y)).abs() + (at(x, down) - at(x, up)).abs());
+            }
+        }
+        energy
+    }
+
+    /// Column of the seam in each row, found by dynamic programming over the energy.
+    fn lowest_energy_seam(&self) -> Vec<usize> {
+        let width = self.width;
+        let mut cost = self.energy();
+        for y in 1..self.height {
+            for x in 0..width {
+                let above = &cost[(y - 1) * width..y * width];
+                let cheapest = above[x.saturating_sub(1)..=(x + 1).min(width - 1)]
+                    .iter()
+                    .copied()
+                    .fold(f32::INFINITY, f32::min);
+                cost[y * width + x] += cheapest;
+            }
+        }
+
+        let last_row = &cost[(self.height - 1) * width..];
+        let mut x = index_of_min(last_row, 0);
+        let mut seam = vec![0; self.height];
+        seam[self.height - 1] = x;
+        for y in (0..self.height - 1).rev() {
+            let start = x.saturating_sub(1);
+            let row = &cost[y * width..(y + 1) * width];
+            x = index_of_min(&row[start..=(x + 1).min(width - 1)], start);
+            seam[y] = x;
+        }
+        seam
+    }
+
+    fn remove_seam(&mut self, seam: &[usize]) {
+        let width = self.width;
+        let mut row = 0;
+        let mut index = 0;
+        self.pixels.retain(|_| {
+            let keep = index % width != seam[row];
+            index += 1;
+            if index % width == 0 {
+                row += 1;
+            }
+            keep
+        });
+        self.width -= 1;
+    }
+}
+
+/// Index of the smallest value plus `offset`, the first one on ties.
+fn index_of_min(values: &[f32], offset: usize) -> usize {
+    values
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map_or(offset, |(index, _)| index + offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    const OBJECT_LEFT: u32 = 40;
+    const OBJECT_TOP: u32 = 10;
+    const OBJECT_SIZE: u32 = 20;
+
+    fn object_pixel(x: u32, y: u32) -> Rgb<u8> {
+        if (x / 2 + y / 2).is_multiple_of(2) {
+            Rgb([0, 0, 0])
+        } else {
+            Rgb([255, 255, 255])
+        }
+    }
+
+    /// A smooth background with a high-contrast checkerboard in the middle.
+    fn scene() -> RgbImage {
+        RgbImage::from_fn(100, 40, |x, y| {
+            let (object_x, object_y) = (x.wrapping_sub(OBJECT_LEFT), y.wrapping_sub(OBJECT_TOP));
+            if object_x < OBJECT_SIZE && object_y < OBJECT_SIZE {
+                object_pixel(object_x, object_y)
+            } else {
+                let shade = 100 + y as u8;
+                Rgb([shade, shade, shade + 20])
+            }
+        })
+    }
+
+    #[test]
+    fn narrowing_keeps_the_centered_object() {
+        let carved = carve(&DynamicImage::ImageRgb8(scene()), 60, 40).to_rgb8();
+        assert_eq!(carved.dimensions(), (60, 40));
+
+        let left = (0..carved.width())
+            .find(|&x| carved.get_pixel(x, OBJECT_TOP) == &object_pixel(0, 0))
+            .expect("object removed");
+        for y in 0..OBJECT_SIZE {
+            for x in 0..OBJECT_SIZE {
+                assert_eq!(
+                    carved.get_pixel(left + x, OBJECT_TOP + y),
+                    &object_pixel(x, y),
+                    "object pixel {x},{y}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use image::DynamicImage;
+
+/// Variance of the Laplacian over the image's luma, higher means sharper.
+///
+/// Blur flattens the second derivative, so out-of-focus and motion-blurred shots score low.
+/// The score depends on content and resolution; a few hundred is typical for sharp photos.
+/// Images smaller than 3x3 have no interior to measure and count as sharp.
+pub fn laplacian_variance(img: &DynamicImage) -> f64 {
+    let luma = img.to_luma8();
+    let (width, height) = luma.dimensions();
+    if width < 3 || height < 3 {
+        return f64::INFINITY;
+    }
+
+    let at = |x: u32, y: u32| f64::from(luma.get_pixel(x, y)[0]);
+    let mut sum = 0.0;
+    let mut sum_squares = 0.0;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let laplacian = 4.0_f64.mul_add(
+                -at(x, y),
+                at(x - 1, y) + at(x + 1, y) + at(x, y - 1) + at(x, y + 1),
+            );
+            sum += laplacian;
+            sum_squares += laplacian * laplacian;
+        }
+    }
+
+    let count = f64::from((width - 2) * (height - 2));
+    let mean = sum / count;
+    mean.mul_add(-mean, sum_squares / count)
+}
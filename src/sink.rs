@@ -0,0 +1,159 @@
+use anyhow::{Context, Result};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Destination for encoded output files.
+///
+/// The resizer only produces bytes and a path, so outputs can go somewhere other than the
+/// local disk by providing another sink.
+/// Shared by every worker of a batch, so implementations must be `Sync`.
+pub trait OutputSink: Sync {
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<()>;
+}
+
+/// Windows rejects longer paths unless they are in the extended-length form.
+#[cfg(windows)]
+const MAX_PATH: usize = 260;
+
+/// Writes outputs to the local filesystem, creating missing directories and replacing
+/// existing files.
+pub struct FileSystemSink;
+
+impl OutputSink for FileSystemSink {
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        let long_path = long_path_safe(path);
+        if let Some(parent) = long_path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        std::fs::write(&long_path, bytes)
+            .with_context(|| format!("Failed to write output file: {}", path.display()))
+    }
+}
+
+/// `path` in the `\\?\` extended-length form when it is too long for the usual Windows
+/// limit, so deep output trees can still be written. Other platforms have no such limit and
+/// get `path` back unchanged.
+#[cfg_attr(not(windows), allow(clippy::missing_const_for_fn))]
+fn long_path_safe(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    if path.as_os_str().len() >= MAX_PATH {
+        // The prefix turns off normalization, so the path has to be absolute and clean already
+        if let Some(absolute) = std::path::absolute(path)
+            .ok()
+            .and_then(|absolute| absolute.to_str().map(str::to_string))
+        {
+            let extended = if absolute.starts_with(r"\\?\") {
+                absolute
+            } else if let Some(share) = absolute.strip_prefix(r"\\") {
+                format!(r"\\?\UNC\{share}")
+            } else {
+                format!(r"\\?\{absolute}")
+            };
+            return Cow::Owned(PathBuf::from(extended));
+        }
+    }
+    Cow::Borrowed(path)
+}
+
+/// Notes the size of every output on its way to another sink.
+pub struct SizeSink<'a> {
+    inner: &'a dyn OutputSink,
+    sizes: Mutex<HashMap<PathBuf, u64>>,
+}
+
+impl<'a> SizeSink<'a> {
+    pub fn new(inner: &'a dyn OutputSink) -> Self {
+        Self {
+            inner,
+            sizes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Bytes last written to `path`, 0 if nothing was.
+    pub fn size(&self, path: &Path) -> u64 {
+        self.sizes
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get(path)
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+impl OutputSink for SizeSink<'_> {
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        self.inner.write(path, bytes)?;
+        self.sizes
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Output size list is poisoned"))?
+            .insert(path.to_path_buf(), bytes.len() as u64);
+        Ok(())
+    }
+}
+
+/// Keeps outputs in memory, for work whose outputs are only written once it has finished.
+#[derive(Default)]
+pub struct MemorySink {
+    files: Mutex<Vec<(PathBuf, Vec<u8>)>>,
+}
+
+impl MemorySink {
+    /// The outputs in the order they were written.
+    pub fn into_files(self) -> Vec<(PathBuf, Vec<u8>)> {
+        self.files
+            .into_inner()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl OutputSink for MemorySink {
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        self.files
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Output buffer is poisoned"))?
+            .push((path.to_path_buf(), bytes.to_vec()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A path under `root` well past the usual Windows limit.
+    fn deep_path(root: &Path) -> PathBuf {
+        let mut path = root.to_path_buf();
+        for level in 0..12 {
+            path.push(format!("mirrored_folder_level_{level:02}_with_a_long_name"));
+        }
+        path.join("photo_resized_1920x1080.jpg")
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn deep_paths_are_written_in_extended_form() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = deep_path(dir.path());
+        assert!(path.as_os_str().len() > MAX_PATH);
+
+        FileSystemSink.write(&path, b"resized").unwrap();
+
+        let extended = long_path_safe(&path);
+        assert!(extended.to_string_lossy().starts_with(r"\\?\"));
+        assert_eq!(std::fs::read(extended).unwrap(), b"resized");
+    }
+
+    #[test]
+    fn short_paths_are_left_alone() {
+        let path = Path::new("out").join("photo.jpg");
+        assert!(matches!(long_path_safe(&path), Cow::Borrowed(p) if p == path));
+        #[cfg(not(windows))]
+        {
+            let deep = deep_path(Path::new("/tmp"));
+            assert!(matches!(long_path_safe(&deep), Cow::Borrowed(p) if p == deep));
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::metadata;
+
+/// Order in which inputs are processed, which also decides sequence numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Selection,
+    Name,
+    CaptureDate,
+    Modified,
+}
+
+impl SortOrder {
+    pub const ALL: [Self; 4] = [
+        Self::Selection,
+        Self::Name,
+        Self::CaptureDate,
+        Self::Modified,
+    ];
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Selection => "As selected",
+            Self::Name => "File name",
+            Self::CaptureDate => "Capture date",
+            Self::Modified => "Modified date",
+        }
+    }
+}
+
+pub fn sort_inputs(files: &mut [PathBuf], order: SortOrder) {
+    let modified = |path: &PathBuf| {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    };
+
+    match order {
+        SortOrder::Selection => {}
+        SortOrder::Name => files.sort_by_cached_key(|path| path.file_name().map(ToOwned::to_owned)),
+        // Images without an EXIF date go last, in the order they were modified
+        SortOrder::CaptureDate => files.sort_by_cached_key(|path| {
+            let date = std::fs::read(path)
+                .ok()
+                .and_then(|bytes| metadata::read_capture_date(&bytes));
+            (date.is_none(), date, modified(path))
+        }),
+        SortOrder::Modified => files.sort_by_cached_key(modified),
+    }
+}
+
+/// The `count` most recently modified files, newest first.
+pub fn take_newest(mut files: Vec<PathBuf>, count: usize) -> Vec<PathBuf> {
+    sort_inputs(&mut files, SortOrder::Modified);
+    files.reverse();
+    files.truncate(count);
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::naming::Naming;
+    use crate::presets::ResizePreset;
+    use crate::resizer::ImageResizer;
+    use crate::test_util;
+    use std::path::Path;
+
+    #[test]
+    fn sequence_numbers_follow_capture_dates() {
+        let dir = tempfile::tempdir().unwrap();
+        let img = test_util::gradient(32, 24);
+        let mut files: Vec<PathBuf> = [
+            ("a_last.jpg", "2023:05:01 08:00:00"),
+            ("b_first.jpg", "2021:01:01 08:00:00"),
+            ("c_middle.jpg", "2022:12:31 23:59:00"),
+        ]
+        .iter()
+        .map(|(name, date)| {
+            let path = dir.path().join(name);
+            std::fs::write(
+                &path,
+                test_util::jpeg_with_exif(&img, &[test_util::exif_date(date)]),
+            )
+            .unwrap();
+            path
+        })
+        .collect();
+
+        sort_inputs(&mut files, SortOrder::CaptureDate);
+        let naming = Naming {
+            sequence_name: "slide".to_string(),
+            ..Naming::default()
+        };
+        let plan = ImageResizer::plan_batch(&files, Path::new("out"), &ResizePreset::BASE, &naming)
+            .unwrap();
+
+        let pairs: Vec<_> = plan
+            .iter()
+            .map(|job| {
+                (
+                    job.input.file_name().unwrap().to_str().unwrap(),
+                    job.output.file_name().unwrap().to_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("b_first.jpg", "slide_001.jpg"),
+                ("c_middle.jpg", "slide_002.jpg"),
+                ("a_last.jpg", "slide_003.jpg"),
+            ]
+        );
+    }
+
+    #[test]
+    fn newest_files_come_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let epoch = SystemTime::UNIX_EPOCH;
+        let files: Vec<PathBuf> = [
+            ("old.jpg", 100),
+            ("newest.jpg", 300),
+            ("mid.jpg", 200),
+            ("older.jpg", 50),
+        ]
+        .iter()
+        .map(|&(name, seconds)| {
+            let path = dir.path().join(name);
+            let file = std::fs::File::create(&path).unwrap();
+            file.set_modified(epoch + std::time::Duration::from_secs(seconds))
+                .unwrap();
+            path
+        })
+        .collect();
+
+        let newest = take_newest(files, 3);
+
+        let names: Vec<_> = newest
+            .iter()
+            .map(|path| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["newest.jpg", "mid.jpg", "old.jpg"]);
+    }
+}
@@ -0,0 +1,368 @@
+use anyhow::{Context, Result};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// Where an input image comes from.
+///
+/// The identifier names the input in messages and is used to derive output file names,
+/// so it should end in a file name with an extension where possible.
+pub trait InputSource: Send + Sync {
+    fn identifier(&self) -> &Path;
+    fn read_bytes(&self) -> Result<InputBytes>;
+}
+
+/// Encoded bytes of an input, either read into memory or mapped from its file.
+pub enum InputBytes {
+    Owned(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl From<Vec<u8>> for InputBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Owned(bytes)
+    }
+}
+
+impl Deref for InputBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Owned(bytes) => bytes,
+            #[cfg(feature = "mmap")]
+            Self::Mapped(map) => map,
+        }
+    }
+}
+
+/// Reads an input from the local filesystem.
+pub struct FileSource {
+    path: PathBuf,
+    #[cfg(feature = "mmap")]
+    memory_map: bool,
+}
+
+#[cfg(feature = "mmap")]
+impl FileSource {
+    /// Files smaller than this are read normally, mapping them saves nothing.
+    const MIN_MAPPED_BYTES: u64 = 16 * 1024 * 1024;
+
+    /// Maps large files into memory instead of copying them, when `memory_map` is set.
+    /// The decoder then reads straight from the page cache.
+    pub const fn with_memory_map(mut self, memory_map: bool) -> Self {
+        self.memory_map = memory_map;
+        self
+    }
+
+    /// Maps the file, `None` when it is small or can't be mapped so it is read instead.
+    fn map(&self) -> Option<memmap2::Mmap> {
+        let file = std::fs::File::open(&self.path).ok()?;
+        if file.metadata().ok()?.len() < Self::MIN_MAPPED_BYTES {
+            return None;
+        }
+        // SAFETY: the mapping is only read while decoding. Another program truncating or
+        // rewriting the file meanwhile is undefined behavior, which is why mapping is opt-in.
+        #[allow(unsafe_code)]
+        unsafe { memmap2::Mmap::map(&file) }.ok()
+    }
+}
+
+impl From<PathBuf> for FileSource {
+    fn from(path: PathBuf) -> Self {
+        Self {
+            path,
+            #[cfg(feature = "mmap")]
+            memory_map: false,
+        }
+    }
+}
+
+impl InputSource for FileSource {
+    fn identifier(&self) -> &Path {
+        &self.path
+    }
+
+    fn read_bytes(&self) -> Result<InputBytes> {
+        #[cfg(feature = "mmap")]
+        if let Some(map) = self.memory_map.then(|| self.map()).flatten() {
+            return Ok(InputBytes::Mapped(map));
+        }
+        std::fs::read(&self.path)
+            .map(InputBytes::from)
+            .with_context(|| format!("Failed to open image: {}", self.path.display()))
+    }
+}
+
+impl<T: InputSource + ?Sized> InputSource for Box<T> {
+    fn identifier(&self) -> &Path {
+        (**self).identifier()
+    }
+
+    fn read_bytes(&self) -> Result<InputBytes> {
+        (**self).read_bytes()
+    }
+}
+
+/// An input whose bytes were already read by someone else, handed out by the first
+/// `read_bytes`.
+pub struct ReadAhead<'a> {
+    identifier: &'a Path,
+    bytes: Mutex<Option<Result<InputBytes>>>,
+}
+
+impl<'a> ReadAhead<'a> {
+    pub const fn new(identifier: &'a Path, bytes: Result<InputBytes>) -> Self {
+        Self {
+            identifier,
+            bytes: Mutex::new(Some(bytes)),
+        }
+    }
+}
+
+impl InputSource for ReadAhead<'_> {
+    fn identifier(&self) -> &Path {
+        self.identifier
+    }
+
+    fn read_bytes(&self) -> Result<InputBytes> {
+        self.bytes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+            .with_context(|| format!("{} was already read", self.identifier.display()))?
+    }
+}
+
+/// Downloads an input over http(s).
+#[cfg(feature = "net")]
+pub struct UrlSource {
+    url: String,
+    identifier: PathBuf,
+    agent: ureq::Agent,
+}
+
+#[cfg(feature = "net")]
+impl UrlSource {
+    /// Refuse to buffer responses larger than this.
+    const MAX_DOWNLOAD_BYTES: u64 = 256 * 1024 * 1024;
+
+    pub fn new(url: &str, agent: ureq::Agent) -> Self {
+        // Name outputs after the last path segment, e.g. https://host/a/photo.jpg?x=1 -> photo.jpg
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let file_name = path
+            .split("://")
+            .nth(1)
+            .and_then(|rest| rest.split_once('/'))
+            .and_then(|(_, path)| path.rsplit('/').next())
+            .filter(|name| !name.is_empty())
+            .unwrap_or("download");
+
+        Self {
+            url: url.to_string(),
+            identifier: PathBuf::from(file_name),
+            agent,
+        }
+    }
+
+    pub fn agent() -> ureq::Agent {
+        ureq::AgentBuilder::new()
+            .timeout(std::time::Duration::from_secs(30))
+            .redirects(5)
+            .build()
+    }
+}
+
+#[cfg(feature = "net")]
+impl InputSource for UrlSource {
+    fn identifier(&self) -> &Path {
+        &self.identifier
+    }
+
+    fn read_bytes(&self) -> Result<InputBytes> {
+        use std::io::Read;
+
+        let response = self
+            .agent
+            .get(&self.url)
+            .call()
+            .with_context(|| format!("Failed to download {}", self.url))?;
+
+        let content_type = response.content_type().to_string();
+        anyhow::ensure!(
+            content_type.starts_with("image/") || content_type == "application/octet-stream",
+            "{} is not an image (content type {content_type})",
+            self.url
+        );
+
+        let mut bytes = Vec::new();
+        response
+            .into_reader()
+            .take(Self::MAX_DOWNLOAD_BYTES + 1)
+            .read_to_end(&mut bytes)
+            .with_context(|| format!("Failed to download {}", self.url))?;
+        anyhow::ensure!(
+            bytes.len() as u64 <= Self::MAX_DOWNLOAD_BYTES,
+            "{} is larger than {} MB",
+            self.url,
+            Self::MAX_DOWNLOAD_BYTES / (1024 * 1024)
+        );
+
+        Ok(bytes.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::ResizePreset;
+    use crate::resizer::ImageResizer;
+    use crate::sink::MemorySink;
+    use crate::test_util;
+    use image::ImageFormat;
+
+    #[test]
+    fn in_memory_input_is_resized() {
+        let bytes = test_util::encode(&test_util::gradient(300, 200), ImageFormat::Png);
+        let source = ReadAhead::new(Path::new("buffer.png"), Ok(bytes.into()));
+        let preset = ResizePreset {
+            width: 150,
+            height: 150,
+            ..ResizePreset::BASE
+        };
+        let sink = MemorySink::default();
+
+        let read = source.read_bytes().unwrap();
+        ImageResizer::resize_image(
+            source.identifier(),
+            &read,
+            Path::new("buffer_small.png"),
+            &preset,
+            &sink,
+            &|_| {},
+        )
+        .unwrap();
+
+        let files = sink.into_files();
+        assert_eq!(files[0].0, Path::new("buffer_small.png"));
+        assert_eq!(test_util::dimensions(&files[0].1), (150, 100));
+        // The bytes are handed out once
+        assert!(source.read_bytes().is_err());
+    }
+
+    /// Answers each of `responses` to one connection on a local port, returns the base URL.
+    #[cfg(feature = "net")]
+    fn serve(responses: Vec<(&'static str, Vec<u8>)>) -> String {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for (content_type, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+                     Connection: close\r\n\r\n",
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+        url
+    }
+
+    #[cfg(feature = "net")]
+    #[test]
+    fn url_input_is_downloaded_and_resized() {
+        let png = test_util::encode(&test_util::gradient(120, 80), ImageFormat::Png);
+        let base = serve(vec![
+            ("image/png", png.clone()),
+            ("text/html", b"<html></html>".to_vec()),
+        ]);
+        let agent = UrlSource::agent();
+
+        let source = UrlSource::new(&format!("{base}/photos/pic.png?size=large"), agent.clone());
+        assert_eq!(source.identifier(), Path::new("pic.png"));
+        let bytes = source.read_bytes().unwrap();
+        assert_eq!(&*bytes, png.as_slice());
+        let sink = MemorySink::default();
+        ImageResizer::resize_image(
+            source.identifier(),
+            &bytes,
+            Path::new("pic_small.png"),
+            &ResizePreset {
+                width: 60,
+                height: 60,
+                ..ResizePreset::BASE
+            },
+            &sink,
+            &|_| {},
+        )
+        .unwrap();
+        assert_eq!(test_util::dimensions(&sink.into_files()[0].1), (60, 40));
+
+        let page = UrlSource::new(&format!("{base}/index.html"), agent);
+        let error = page.read_bytes().err().unwrap().to_string();
+        assert!(error.contains("is not an image"), "{error}");
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mapped_input_resizes_like_a_read_one() {
+        let dir = tempfile::tempdir().unwrap();
+        // An uncompressed BMP past the size where mapping starts
+        let large = dir.path().join("large.bmp");
+        std::fs::write(
+            &large,
+            test_util::encode(&test_util::photo(2400, 2400), ImageFormat::Bmp),
+        )
+        .unwrap();
+        let small = dir.path().join("small.png");
+        std::fs::write(
+            &small,
+            test_util::encode(&test_util::gradient(40, 30), ImageFormat::Png),
+        )
+        .unwrap();
+        let preset = ResizePreset {
+            width: 300,
+            height: 300,
+            output_format: crate::presets::OutputFormat::Png,
+            ..ResizePreset::BASE
+        };
+        let resize = |source: &FileSource| {
+            let sink = MemorySink::default();
+            let bytes = source.read_bytes().unwrap();
+            ImageResizer::resize_image(
+                source.identifier(),
+                &bytes,
+                Path::new("out.png"),
+                &preset,
+                &sink,
+                &|_| {},
+            )
+            .unwrap();
+            sink.into_files().pop().unwrap().1
+        };
+
+        let mapped = FileSource::from(large.clone()).with_memory_map(true);
+        assert!(matches!(
+            mapped.read_bytes().unwrap(),
+            InputBytes::Mapped(_)
+        ));
+        assert_eq!(resize(&mapped), resize(&FileSource::from(large)));
+
+        let unmapped = FileSource::from(small).with_memory_map(true);
+        assert!(matches!(
+            unmapped.read_bytes().unwrap(),
+            InputBytes::Owned(_)
+        ));
+    }
+}
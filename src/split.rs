@@ -0,0 +1,230 @@
+use image::{DynamicImage, RgbImage};
+use std::collections::VecDeque;
+
+/// Detection runs on a copy this large, photo outlines don't need full resolution.
+const ANALYSIS_SIZE: u32 = 800;
+/// Largest per-channel difference from the background that still counts as background.
+const BACKGROUND_TOLERANCE: i16 = 40;
+/// Regions covering less of the scan than this are dust or specks, not photos.
+const MIN_AREA_FRACTION: f64 = 0.01;
+
+/// A region of the scan in source pixels, as `(x, y, width, height)`.
+pub type Region = (u32, u32, u32, u32);
+
+/// Finds the photos on a flatbed scan of several prints lying on a plain background.
+///
+/// The background color is taken from the border of the scan. Connected pixels that differ
+/// from it form a photo, overlapping ones are merged so light areas inside a print don't split
+/// it. Regions come back top to bottom, then left to right.
+pub fn find_photos(img: &DynamicImage) -> Vec<Region> {
+    let small = img.thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE).to_rgb8();
+    let (width, height) = small.dimensions();
+    if width < 3 || height < 3 {
+        return Vec::new();
+    }
+
+    let background = border_color(&small);
+    let foreground: Vec<bool> = small
+        .pixels()
+        .map(|pixel| {
+            pixel
+                .0
+                .iter()
+                .zip(background)
+                .any(|(&channel, background)| {
+                    (i16::from(channel) - i16::from(background)).abs() > BACKGROUND_TOLERANCE
+                })
+        })
+        .collect();
+
+    let min_area = (f64::from(width) * f64::from(height) * MIN_AREA_FRACTION) as u32;
+    let mut boxes = components(&foreground, width as usize, height as usize);
+    merge_overlapping(&mut boxes);
+    boxes.retain(|&(_, _, box_width, box_height)| box_width * box_height >= min_area);
+    boxes.sort_by_key(|&(x, y, _, _)| (y, x));
+
+    // Back to source pixels, rounding outwards so no edge of a photo is lost
+    let (scale_x, scale_y) = (
+        f64::from(img.width()) / f64::from(width),
+        f64::from(img.height()) / f64::from(height),
+    );
+    boxes
+        .into_iter()
+        .map(|(x, y, box_width, box_height)| {
+            let left = (f64::from(x) * scale_x).floor() as u32;
+            let top = (f64::from(y) * scale_y).floor() as u32;
+            let right = ((f64::from(x + box_width) * scale_x).ceil() as u32).min(img.width());
+            let bottom = ((f64::from(y + box_height) * scale_y).ceil() as u32).min(img.height());
+            (left, top, right - left, bottom - top)
+        })
+        .collect()
+}
+
+/// Average color of the outermost rows and columns.
+fn border_color(img: &RgbImage) -> [u8; 3] {
+    let (width, height) = img.dimensions();
+    let mut sum = [0_u64; 3];
+    let mut count = 0_u64;
+    for (x, y, pixel) in img.enumerate_pixels() {
+        if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+            for (total, &channel) in sum.iter_mut().zip(&pixel.0) {
+                *total += u64::from(channel);
+            }
+            count += 1;
+        }
+    }
+    sum.map(|total| (total / count.max(1)) as u8)
+}
+
+/// Bounding boxes of the 4-connected groups of `true` cells in a row-major grid.
+fn components(cells: &[bool], width: usize, height: usize) -> Vec<Region> {
+    let mut seen = vec![false; cells.len()];
+    let mut boxes = Vec::new();
+    let mut queue = VecDeque::new();
+
+    for start in 0..cells.len() {
+        if !cells[start] || seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        queue.push_back(start);
+        let (mut left, mut top, mut right, mut bottom) = (width, height, 0, 0);
+
+        while let Some(index) = queue.pop_front() {
+            let (x, y) = (index % width, index / width);
+            (left, top) = (left.min(x), top.min(y));
+            (right, bottom) = (right.max(x), bottom.max(y));
+
+            let neighbors = [
+                (x > 0).then(|| index - 1),
+                (x + 1 < width).then(|| index + 1),
+                (y > 0).then(|| index - width),
+                (y + 1 < height).then(|| index + width),
+            ];
+            for neighbor in neighbors.into_iter().flatten() {
+                if cells[neighbor] && !seen[neighbor] {
+                    seen[neighbor] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        boxes.push((
+            left as u32,
+            top as u32,
+            (right - left + 1) as u32,
+            (bottom - top + 1) as u32,
+        ));
+    }
+    boxes
+}
+
+/// Replaces boxes that overlap by their union until none do.
+fn merge_overlapping(boxes: &mut Vec<Region>) {
+    let overlaps = |a: Region, b: Region| {
+        a.0 < b.0 + b.2 && b.0 < a.0 + a.2 && a.1 < b.1 + b.3 && b.1 < a.1 + a.3
+    };
+
+    let mut merged = true;
+    while merged {
+        merged = false;
+        'search: for i in 0..boxes.len() {
+            for j in i + 1..boxes.len() {
+                if overlaps(boxes[i], boxes[j]) {
+                    let (a, b) = (boxes[i], boxes.swap_remove(j));
+                    let (left, top) = (a.0.min(b.0), a.1.min(b.1));
+                    let right = (a.0 + a.2).max(b.0 + b.2);
+                    let bottom = (a.1 + a.3).max(b.1 + b.3);
+                    boxes[i] = (left, top, right - left, bottom - top);
+                    merged = true;
+                    break 'search;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::{OutputFormat, ResizePreset};
+    use crate::resizer::ImageResizer;
+    use crate::sink::MemorySink;
+    use crate::test_util;
+    use image::{GenericImage, ImageFormat, Rgb};
+    use std::path::{Path, PathBuf};
+
+    /// A white 1000x700 scan with a 300x200 print at (100, 100) and a 250x350 one at
+    /// (600, 250).
+    fn two_print_scan() -> DynamicImage {
+        let mut scan = RgbImage::from_pixel(1000, 700, Rgb([255, 255, 255]));
+        scan.copy_from(&test_util::photo(300, 200).to_rgb8(), 100, 100)
+            .unwrap();
+        scan.copy_from(&test_util::photo(250, 350).to_rgb8(), 600, 250)
+            .unwrap();
+        DynamicImage::ImageRgb8(scan)
+    }
+
+    #[test]
+    fn two_prints_give_two_regions() {
+        let regions = find_photos(&two_print_scan());
+
+        assert_eq!(regions.len(), 2, "{regions:?}");
+        for (&(x, y, width, height), expected) in regions
+            .iter()
+            .zip([(100, 100, 300, 200), (600, 250, 250, 350)])
+        {
+            let (ex, ey, ew, eh) = expected;
+            assert!(x.abs_diff(ex) <= 4 && y.abs_diff(ey) <= 4, "{regions:?}");
+            assert!(
+                width.abs_diff(ew) <= 8 && height.abs_diff(eh) <= 8,
+                "{regions:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn each_print_is_written_as_its_own_crop() {
+        let bytes = test_util::encode(&two_print_scan(), ImageFormat::Png);
+        let preset = ResizePreset {
+            width: 100,
+            height: 100,
+            output_format: OutputFormat::Png,
+            split_photos: true,
+            ..ResizePreset::BASE
+        };
+        let sink = MemorySink::default();
+
+        let written = ImageResizer::resize_image(
+            Path::new("scan.png"),
+            &bytes,
+            Path::new("out/scan.png"),
+            &preset,
+            &sink,
+            &|_| {},
+        )
+        .unwrap();
+
+        assert_eq!(
+            written,
+            [
+                PathBuf::from("out/scan_001.png"),
+                PathBuf::from("out/scan_002.png")
+            ]
+        );
+        let sizes: Vec<_> = sink
+            .into_files()
+            .iter()
+            .map(|(_, bytes)| test_util::dimensions(bytes))
+            .collect();
+        // Landscape then portrait, each fitted into the box
+        assert!(sizes[0].0 == 100 && sizes[0].1 < 100, "{sizes:?}");
+        assert!(sizes[1].1 == 100 && sizes[1].0 < 100, "{sizes:?}");
+    }
+
+    #[test]
+    fn blank_scan_has_no_photos() {
+        let blank = DynamicImage::ImageRgb8(RgbImage::from_pixel(400, 300, Rgb([250, 250, 250])));
+        assert!(find_photos(&blank).is_empty());
+    }
+}
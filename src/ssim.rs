@@ -0,0 +1,70 @@
+use image::GrayImage;
+
+const WINDOW: u32 = 8;
+const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+/// Mean structural similarity of two same-sized grayscale images, in `0.0..=1.0`.
+///
+/// Uses non-overlapping 8x8 windows, which is close enough to the Gaussian-weighted
+/// reference implementation for choosing an encoder quality.
+pub fn mean_ssim(reference: &GrayImage, candidate: &GrayImage) -> f64 {
+    debug_assert_eq!(reference.dimensions(), candidate.dimensions());
+    let (width, height) = reference.dimensions();
+
+    // Images smaller than one window are compared as a whole
+    let window_width = WINDOW.min(width).max(1);
+    let window_height = WINDOW.min(height).max(1);
+
+    let mut total = 0.0;
+    let mut windows = 0_u32;
+    for top in (0..=height.saturating_sub(window_height)).step_by(window_height as usize) {
+        for left in (0..=width.saturating_sub(window_width)).step_by(window_width as usize) {
+            total += window_ssim(
+                reference,
+                candidate,
+                (left, top),
+                (window_width, window_height),
+            );
+            windows += 1;
+        }
+    }
+
+    if windows == 0 {
+        1.0
+    } else {
+        total / f64::from(windows)
+    }
+}
+
+fn window_ssim(
+    reference: &GrayImage,
+    candidate: &GrayImage,
+    (left, top): (u32, u32),
+    (width, height): (u32, u32),
+) -> f64 {
+    let count = f64::from(width * height);
+    let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) = (0.0, 0.0, 0.0, 0.0, 0.0);
+
+    for y in top..top + height {
+        for x in left..left + width {
+            let a = f64::from(reference.get_pixel(x, y)[0]);
+            let b = f64::from(candidate.get_pixel(x, y)[0]);
+            sum_a += a;
+            sum_b += b;
+            sum_aa += a * a;
+            sum_bb += b * b;
+            sum_ab += a * b;
+        }
+    }
+
+    let mean_a = sum_a / count;
+    let mean_b = sum_b / count;
+    let variance_a = mean_a.mul_add(-mean_a, sum_aa / count);
+    let variance_b = mean_b.mul_add(-mean_b, sum_bb / count);
+    let covariance = mean_a.mul_add(-mean_b, sum_ab / count);
+
+    ((2.0 * mean_a).mul_add(mean_b, C1) * 2.0f64.mul_add(covariance, C2))
+        / (mean_a.mul_add(mean_a, mean_b * mean_b) + C1)
+        / (variance_a + variance_b + C2)
+}
@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+
+use crate::probe::{self, SourceInfo};
+
+/// Counts and extremes of a batch's sources, gathered from their headers before processing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    /// Number of sources per format name, such as `JPEG`.
+    pub formats: BTreeMap<String, usize>,
+    /// Sources whose header couldn't be read.
+    pub unreadable: usize,
+    /// Stored sides of the source with the fewest pixels.
+    pub smallest: Option<(u32, u32)>,
+    /// Stored sides of the source with the most pixels.
+    pub largest: Option<(u32, u32)>,
+    /// Size of all sources on disk, readable or not.
+    pub total_bytes: u64,
+}
+
+impl BatchSummary {
+    /// Counts one source of `bytes` bytes, `None` when its header couldn't be read.
+    pub fn add(&mut self, info: Option<&SourceInfo>, bytes: u64) {
+        self.total_bytes += bytes;
+        let Some(info) = info else {
+            self.unreadable += 1;
+            return;
+        };
+
+        *self
+            .formats
+            .entry(probe::format_name(info.format))
+            .or_default() += 1;
+        let size = (info.width, info.height);
+        let pixels = |(width, height): (u32, u32)| u64::from(width) * u64::from(height);
+        if self
+            .smallest
+            .is_none_or(|smallest| pixels(size) < pixels(smallest))
+        {
+            self.smallest = Some(size);
+        }
+        if self
+            .largest
+            .is_none_or(|largest| pixels(size) > pixels(largest))
+        {
+            self.largest = Some(size);
+        }
+    }
+
+    /// One line such as `230 JPEG, 12 PNG, largest 6000x4000, smallest 640x480, total 1.2 GB`.
+    pub fn describe(&self) -> String {
+        // Most common format first
+        let mut formats: Vec<_> = self.formats.iter().collect();
+        formats.sort_by(|a, b| b.1.cmp(a.1));
+        let mut parts: Vec<String> = formats
+            .into_iter()
+            .map(|(format, count)| format!("{count} {format}"))
+            .collect();
+        if self.unreadable > 0 {
+            parts.push(format!("{} unreadable", self.unreadable));
+        }
+        if let Some((width, height)) = self.largest {
+            parts.push(format!("largest {width}x{height}"));
+        }
+        if let Some((width, height)) = self.smallest.filter(|_| self.smallest != self.largest) {
+            parts.push(format!("smallest {width}x{height}"));
+        }
+        parts.push(format!("total {}", format_bytes(self.total_bytes)));
+        parts.join(", ")
+    }
+}
+
+/// Probes the header of every input. `progress` is called after each one with the number
+/// scanned so far and can stop the scan, which then returns `None`.
+pub fn scan(
+    inputs: &[PathBuf],
+    mut progress: impl FnMut(usize, usize) -> ControlFlow<()>,
+) -> Option<BatchSummary> {
+    let mut summary = BatchSummary::default();
+    for (index, input) in inputs.iter().enumerate() {
+        let bytes = std::fs::metadata(input).map_or(0, |metadata| metadata.len());
+        summary.add(probe::probe(input).ok().as_ref(), bytes);
+        if progress(index + 1, inputs.len()).is_break() {
+            return None;
+        }
+    }
+    Some(summary)
+}
+
+/// Bytes in the largest unit that keeps the number at 1 or above, with one decimal.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} bytes");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ExtendedColorType, ImageFormat};
+
+    fn info(format: ImageFormat, width: u32, height: u32) -> SourceInfo {
+        SourceInfo {
+            format,
+            width,
+            height,
+            color_type: ExtendedColorType::Rgb8,
+            orientation: None,
+        }
+    }
+
+    #[test]
+    fn mixed_batch_is_counted() {
+        let mut summary = BatchSummary::default();
+        summary.add(Some(&info(ImageFormat::Jpeg, 6000, 4000)), 3 * 1024 * 1024);
+        summary.add(Some(&info(ImageFormat::Jpeg, 640, 480)), 100 * 1024);
+        summary.add(Some(&info(ImageFormat::Png, 800, 600)), 512 * 1024);
+        summary.add(Some(&info(ImageFormat::Jpeg, 1920, 1080)), 1024 * 1024);
+        summary.add(None, 412 * 1024);
+
+        assert_eq!(
+            summary.formats,
+            BTreeMap::from([("JPEG".to_string(), 3), ("PNG".to_string(), 1)])
+        );
+        assert_eq!(summary.unreadable, 1);
+        assert_eq!(summary.largest, Some((6000, 4000)));
+        assert_eq!(summary.smallest, Some((640, 480)));
+        assert_eq!(summary.total_bytes, 5 * 1024 * 1024);
+        assert_eq!(
+            summary.describe(),
+            "3 JPEG, 1 PNG, 1 unreadable, largest 6000x4000, smallest 640x480, total 5.0 MB"
+        );
+    }
+
+    #[test]
+    fn scan_reads_headers_and_can_stop() {
+        let dir = tempfile::tempdir().unwrap();
+        let png = crate::test_util::encode(&crate::test_util::gradient(30, 20), ImageFormat::Png);
+        let inputs: Vec<_> = ["a.png", "b.png", "broken.jpg"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        std::fs::write(&inputs[0], &png).unwrap();
+        std::fs::write(&inputs[1], &png).unwrap();
+        std::fs::write(&inputs[2], b"not an image").unwrap();
+
+        let summary = scan(&inputs, |_, _| ControlFlow::Continue(())).unwrap();
+        assert_eq!(summary.formats["PNG"], 2);
+        assert_eq!(summary.unreadable, 1);
+        assert_eq!(summary.total_bytes, 2 * png.len() as u64 + 12);
+
+        assert!(scan(&inputs, |scanned, _| if scanned == 2 {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        })
+        .is_none());
+    }
+
+    #[test]
+    fn bytes_use_the_largest_fitting_unit() {
+        assert_eq!(format_bytes(512), "512 bytes");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(1_288_490_189), "1.2 GB");
+    }
+}
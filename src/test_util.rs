@@ -0,0 +1,101 @@
+//! Images and encodings shared by the unit tests.
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, Frame, ImageFormat, Rgb, RgbImage, Rgba, RgbaImage};
+use std::io::Cursor;
+
+/// A smooth color gradient, detailed enough that filters and encoders behave as on a photo.
+pub fn gradient(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+        Rgb([
+            (x * 255 / width.max(1)) as u8,
+            (y * 255 / height.max(1)) as u8,
+            ((x + y) * 127 / (width + height).max(1)) as u8,
+        ])
+    }))
+}
+
+/// A gradient with deterministic grain and edges, which encoders treat much like a photo.
+pub fn photo(width: u32, height: u32) -> DynamicImage {
+    let mut img = gradient(width, height).to_rgb8();
+    let mut state = 0x2545_f491_u32;
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let grain = (state % 48) as u8;
+        let stripe = if (x / 7 + y / 11) % 2 == 0 { 40 } else { 0 };
+        for channel in &mut pixel.0 {
+            *channel = channel.saturating_add(grain).saturating_sub(stripe);
+        }
+    }
+    DynamicImage::ImageRgb8(img)
+}
+
+/// `img` encoded as `format`.
+pub fn encode(img: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    img.write_to(&mut Cursor::new(&mut bytes), format)
+        .expect("test image encodes");
+    bytes
+}
+
+/// A looping GIF of `frames` solid frames in different colors, 100 ms each.
+pub fn animated_gif(width: u32, height: u32, frames: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut bytes);
+        encoder.set_repeat(Repeat::Infinite).expect("repeat is set");
+        encoder
+            .encode_frames((0..frames).map(|index| {
+                let shade = (index * 255 / frames.max(1)) as u8;
+                Frame::from_parts(
+                    RgbaImage::from_pixel(width, height, Rgba([shade, 255 - shade, 0, 255])),
+                    0,
+                    0,
+                    Delay::from_numer_denom_ms(100, 1),
+                )
+            }))
+            .expect("test animation encodes");
+    }
+    bytes
+}
+
+/// Dimensions of an encoded image.
+pub fn dimensions(bytes: &[u8]) -> (u32, u32) {
+    let img = image::load_from_memory(bytes).expect("output decodes");
+    (img.width(), img.height())
+}
+
+/// `img` as a JPEG carrying an EXIF block with `fields`.
+pub fn jpeg_with_exif(img: &DynamicImage, fields: &[exif::Field]) -> Vec<u8> {
+    let mut writer = exif::experimental::Writer::new();
+    for field in fields {
+        writer.push_field(field);
+    }
+    let mut tiff = Cursor::new(Vec::new());
+    writer.write(&mut tiff, false).expect("EXIF block encodes");
+
+    let mut jpeg = img_parts::jpeg::Jpeg::from_bytes(encode(img, ImageFormat::Jpeg).into())
+        .expect("test JPEG parses");
+    img_parts::ImageEXIF::set_exif(&mut jpeg, Some(tiff.into_inner().into()));
+    img_parts::ImageEncoder::bytes(jpeg.encoder()).to_vec()
+}
+
+/// An EXIF `DateTimeOriginal` field, `date` written like `2024:06:15 10:30:00`.
+pub fn exif_date(date: &str) -> exif::Field {
+    exif::Field {
+        tag: exif::Tag::DateTimeOriginal,
+        ifd_num: exif::In::PRIMARY,
+        value: exif::Value::Ascii(vec![date.as_bytes().to_vec()]),
+    }
+}
+
+/// An EXIF `Orientation` field, 6 meaning the camera was turned a quarter clockwise.
+pub fn exif_orientation(orientation: u16) -> exif::Field {
+    exif::Field {
+        tag: exif::Tag::Orientation,
+        ifd_num: exif::In::PRIMARY,
+        value: exif::Value::Short(vec![orientation]),
+    }
+}
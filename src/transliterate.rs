@@ -0,0 +1,56 @@
+/// ASCII spelling of `text` for file names that survive any file system or archive format.
+///
+/// Accents are dropped and other scripts are spelled out with `deunicode` (`café` becomes
+/// `cafe`, `Москва` becomes `Moskva`, `写真` becomes `XieZhen`). Characters without a spelling,
+/// and spellings that would add a path separator or a character Windows rejects, become `_`.
+/// The tables are built in, so the same name always comes out the same regardless of locale.
+pub fn to_ascii(text: &str) -> String {
+    let mut ascii = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii() {
+            ascii.push(c);
+            continue;
+        }
+        // Spellings are padded with spaces to separate words, which a file name doesn't need
+        match deunicode::deunicode_char(c).map(str::trim) {
+            Some(spelled) if !spelled.is_empty() => {
+                ascii.extend(
+                    spelled
+                        .chars()
+                        .map(|c| if is_reserved(c) { '_' } else { c }),
+                );
+            }
+            _ => ascii.push('_'),
+        }
+    }
+    ascii
+}
+
+const fn is_reserved(c: char) -> bool {
+    matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_ascii_control()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_spelled_in_ascii() {
+        for (name, ascii) in [
+            ("café.jpg", "cafe.jpg"),
+            ("Straße.png", "Strasse.png"),
+            ("Москва.png", "Moskva.png"),
+            ("写真_1.jpg", "XieZhen_1.jpg"),
+            ("a\u{2044}b.png", "a_b.png"),
+            ("plain-name.jpg", "plain-name.jpg"),
+        ] {
+            assert_eq!(to_ascii(name), ascii, "{name}");
+        }
+    }
+
+    #[test]
+    fn distinct_scripts_stay_distinct() {
+        assert_ne!(to_ascii("写真.jpg"), to_ascii("風景.jpg"));
+        assert_ne!(to_ascii("Москва.jpg"), to_ascii("Киев.jpg"));
+    }
+}
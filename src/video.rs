@@ -0,0 +1,180 @@
+use anyhow::{bail, ensure, Context, Result};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Extensions offered when selecting videos, anything ffmpeg decodes works.
+pub const VIDEO_EXTENSIONS: [&str; 6] = ["mp4", "mov", "m4v", "mkv", "webm", "avi"];
+
+/// Frames extracted from one video into a temporary folder, which is removed on drop.
+pub struct Frames {
+    dir: PathBuf,
+    paths: Vec<PathBuf>,
+}
+
+impl Frames {
+    /// The frames in time order, named `{stem}_frame_0001.png`, `_0002`, ...
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+}
+
+impl Drop for Frames {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Extracts one frame per `interval` of `video` as PNGs with the `ffmpeg` command, which
+/// has to be on the `PATH`.
+pub fn extract_frames(video: &Path, interval: Duration) -> Result<Frames> {
+    static EXTRACTIONS: AtomicUsize = AtomicUsize::new(0);
+
+    ensure!(
+        !interval.is_zero(),
+        "The frame interval must be longer than zero"
+    );
+    let stem = video.file_stem().context("Invalid file name")?;
+    let dir = std::env::temp_dir().join(format!(
+        "resize-rs-frames-{}-{}",
+        std::process::id(),
+        EXTRACTIONS.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+    // Removes the folder again on every error below
+    let mut frames = Frames {
+        dir,
+        paths: Vec::new(),
+    };
+
+    let mut pattern = stem.to_os_string();
+    pattern.push("_frame_%04d.png");
+    let output = Command::new("ffmpeg")
+        .args(["-nostdin", "-v", "error", "-i"])
+        .arg(video)
+        .arg("-vf")
+        .arg(format!("fps=1/{}", interval.as_secs_f64()))
+        .arg(frames.dir.join(pattern))
+        .output();
+    let output = match output {
+        Ok(output) => output,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            bail!("ffmpeg was not found, install it to read videos")
+        }
+        Err(e) => return Err(e).context("Failed to run ffmpeg"),
+    };
+    if !output.status.success() {
+        // Missing codecs and broken files are reported on the last line
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "ffmpeg could not read {}: {}",
+            video.display(),
+            stderr.lines().last().unwrap_or("unknown error").trim()
+        );
+    }
+
+    frames.paths = std::fs::read_dir(&frames.dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<_>>()?;
+    frames.paths.sort();
+    ensure!(
+        !frames.paths.is_empty(),
+        "{} has no frames to extract",
+        video.display()
+    );
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::{OutputFormat, ResizePreset};
+    use crate::resizer::ImageResizer;
+    use crate::sink::MemorySink;
+    use crate::source::FileSource;
+    use crate::test_util;
+    use std::ops::ControlFlow;
+
+    /// A 2 s, 64x48 test pattern clip made by ffmpeg, `None` where ffmpeg isn't installed.
+    fn test_clip(dir: &Path) -> Option<PathBuf> {
+        let clip = dir.join("clip.mp4");
+        let status = Command::new("ffmpeg")
+            .args(["-nostdin", "-v", "error", "-f", "lavfi", "-i"])
+            .arg("testsrc=size=64x48:rate=10:duration=2")
+            .args(["-pix_fmt", "yuv420p"])
+            .arg(&clip)
+            .status();
+        match status {
+            Ok(status) if status.success() => Some(clip),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn frames_are_extracted_and_resized() {
+        let dir = tempfile::tempdir().unwrap();
+        let Some(clip) = test_clip(dir.path()) else {
+            eprintln!("ffmpeg is not available, skipping");
+            return;
+        };
+
+        let frames = extract_frames(&clip, Duration::from_millis(500)).unwrap();
+        assert_eq!(frames.paths().len(), 4, "{:?}", frames.paths());
+        assert!(frames.paths()[0].ends_with("clip_frame_0001.png"));
+
+        let inputs: Vec<FileSource> = frames
+            .paths()
+            .iter()
+            .cloned()
+            .map(FileSource::from)
+            .collect();
+        let preset = ResizePreset {
+            width: 32,
+            height: 32,
+            output_format: OutputFormat::Png,
+            ..ResizePreset::BASE
+        };
+        let sink = MemorySink::default();
+        let results = ImageResizer::batch_resize(
+            &inputs,
+            Path::new("out"),
+            &preset,
+            &crate::naming::Naming::default(),
+            &sink,
+            |_, _| ControlFlow::Continue(()),
+            |_| {},
+        )
+        .unwrap();
+
+        assert!(results.iter().all(Result::is_ok));
+        let files = sink.into_files();
+        assert_eq!(files.len(), 4);
+        for (_, bytes) in files {
+            assert_eq!(test_util::dimensions(&bytes), (32, 24));
+        }
+
+        let frames_dir = frames.dir.clone();
+        drop(frames);
+        assert!(!frames_dir.exists());
+    }
+
+    #[test]
+    fn unreadable_videos_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let video = dir.path().join("broken.mp4");
+        std::fs::write(&video, b"not a video").unwrap();
+
+        let Err(error) = extract_frames(&video, Duration::from_secs(1)) else {
+            panic!("a file that isn't a video gave frames");
+        };
+        let message = format!("{error:#}");
+        assert!(
+            message.contains("ffmpeg could not read") || message.contains("ffmpeg was not found"),
+            "{message}"
+        );
+        assert!(extract_frames(&video, Duration::ZERO).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::{Context, Result};
+use image::codecs::webp::WebPDecoder;
+use image::{ImageDecoder, ImageFormat};
+use img_parts::jpeg::{markers, Jpeg, JpegSegment};
+use img_parts::png::{Png, PngChunk};
+use img_parts::riff::{RiffChunk, RiffContent};
+use img_parts::webp::{WebP, CHUNK_VP8X, CHUNK_XMP};
+use img_parts::Bytes;
+use std::fmt::Write as _;
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::sink::OutputSink;
+
+/// Namespace header that marks a JPEG APP1 segment as XMP rather than EXIF.
+const JPEG_XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+/// iTXt keyword, then uncompressed with empty language and translated keyword.
+const PNG_XMP_HEADER: &[u8] = b"XML:com.adobe.xmp\0\0\0\0\0";
+const PNG_CHUNK_ITXT: [u8; 4] = *b"iTXt";
+
+const WEBP_FLAG_XMP: u8 = 0x04;
+const WEBP_FLAG_ALPHA: u8 = 0x10;
+
+/// Builds an XMP packet carrying a copyright notice and an author, either may be empty.
+pub fn rights_packet(copyright: &str, author: &str) -> Vec<u8> {
+    let mut properties = String::new();
+    if !copyright.is_empty() {
+        let _ = write!(
+            properties,
+            "<dc:rights><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:rights>",
+            escape_xml(copyright)
+        );
+    }
+    if !author.is_empty() {
+        let _ = write!(
+            properties,
+            "<dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>",
+            escape_xml(author)
+        );
+    }
+
+    format!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\
+         <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\
+         <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\
+         <rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\
+         {properties}\
+         </rdf:Description></rdf:RDF></x:xmpmeta><?xpacket end=\"w\"?>"
+    )
+    .into_bytes()
+}
+
+/// The XMP packet of a JPEG, PNG or WebP, `None` for other formats or without XMP.
+pub fn read_xmp(bytes: &[u8]) -> Option<Vec<u8>> {
+    let bytes = Bytes::copy_from_slice(bytes);
+    let packet = match image::guess_format(&bytes).ok()? {
+        ImageFormat::Jpeg => Jpeg::from_bytes(bytes)
+            .ok()?
+            .segments_by_marker(markers::APP1)
+            .find_map(|segment| segment.contents().strip_prefix(JPEG_XMP_HEADER))
+            .map(<[u8]>::to_vec),
+        ImageFormat::Png => Png::from_bytes(bytes)
+            .ok()?
+            .chunks_by_type(PNG_CHUNK_ITXT)
+            .find_map(|chunk| chunk.contents().strip_prefix(PNG_XMP_HEADER))
+            .map(<[u8]>::to_vec),
+        ImageFormat::WebP => WebP::from_bytes(bytes)
+            .ok()?
+            .chunk_by_id(CHUNK_XMP)?
+            .content()
+            .data()
+            .map(|data| data.to_vec()),
+        _ => None,
+    };
+    packet
+}
+
+/// Replaces the XMP packet of an encoded JPEG, PNG or WebP. Other formats are returned as-is.
+pub fn embed_xmp(bytes: &[u8], packet: &[u8]) -> Result<Vec<u8>> {
+    let Ok(format) = image::guess_format(bytes) else {
+        return Ok(bytes.to_vec());
+    };
+    let source = Bytes::copy_from_slice(bytes);
+    let packet = Bytes::copy_from_slice(packet);
+
+    let embedded = match format {
+        ImageFormat::Jpeg => {
+            let mut jpeg = Jpeg::from_bytes(source).context("Invalid JPEG")?;
+            let segments = jpeg.segments_mut();
+            segments.retain(|segment| {
+                !(segment.marker() == markers::APP1
+                    && segment.contents().starts_with(JPEG_XMP_HEADER))
+            });
+            // After JFIF and EXIF, which readers expect first
+            let position = segments
+                .iter()
+                .take_while(|segment| matches!(segment.marker(), markers::APP0 | markers::APP1))
+                .count();
+            let contents = [Bytes::from_static(JPEG_XMP_HEADER), packet].concat();
+            segments.insert(
+                position,
+                JpegSegment::new_with_contents(markers::APP1, contents.into()),
+            );
+            jpeg.encoder().bytes()
+        }
+        ImageFormat::Png => {
+            let mut png = Png::from_bytes(source).context("Invalid PNG")?;
+            let chunks = png.chunks_mut();
+            chunks.retain(|chunk| {
+                !(chunk.kind() == PNG_CHUNK_ITXT && chunk.contents().starts_with(PNG_XMP_HEADER))
+            });
+            // Right after IHDR
+            let contents = [Bytes::from_static(PNG_XMP_HEADER), packet].concat();
+            chunks.insert(1, PngChunk::new(PNG_CHUNK_ITXT, contents.into()));
+            png.encoder().bytes()
+        }
+        ImageFormat::WebP => embed_webp_xmp(bytes, source, packet)?,
+        _ => return Ok(bytes.to_vec()),
+    };
+
+    Ok(embedded.to_vec())
+}
+
+/// WebP only allows metadata in the extended format, whose VP8X header flags each kind.
+fn embed_webp_xmp(bytes: &[u8], source: Bytes, packet: Bytes) -> Result<Bytes> {
+    let decoder = WebPDecoder::new(Cursor::new(bytes)).context("Invalid WebP")?;
+    let (width, height) = decoder.dimensions();
+    let has_alpha = decoder.color_type().has_alpha();
+
+    let mut webp = WebP::from_bytes(source).context("Invalid WebP")?;
+    webp.remove_chunks_by_id(CHUNK_XMP);
+    webp.chunks_mut()
+        .push(RiffChunk::new(CHUNK_XMP, RiffContent::Data(packet)));
+
+    let mut header = webp
+        .chunk_by_id(CHUNK_VP8X)
+        .and_then(|chunk| chunk.content().data())
+        .map_or_else(
+            || {
+                let mut header = vec![if has_alpha { WEBP_FLAG_ALPHA } else { 0 }, 0, 0, 0];
+                header.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+                header.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+                header
+            },
+            |existing| existing.to_vec(),
+        );
+    header[0] |= WEBP_FLAG_XMP;
+
+    webp.remove_chunks_by_id(CHUNK_VP8X);
+    webp.chunks_mut().insert(
+        0,
+        RiffChunk::new(CHUNK_VP8X, RiffContent::Data(header.into())),
+    );
+    Ok(webp.encoder().bytes())
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Embeds an XMP packet into every output before passing it on to another sink.
+pub struct XmpSink<'a> {
+    inner: &'a dyn OutputSink,
+    packet: Vec<u8>,
+}
+
+impl<'a> XmpSink<'a> {
+    pub const fn new(inner: &'a dyn OutputSink, packet: Vec<u8>) -> Self {
+        Self { inner, packet }
+    }
+}
+
+impl OutputSink for XmpSink<'_> {
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        let bytes = embed_xmp(bytes, &self.packet)
+            .with_context(|| format!("Failed to embed XMP: {}", path.display()))?;
+        self.inner.write(path, &bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::MemorySink;
+    use crate::test_util;
+
+    #[test]
+    fn copyright_round_trips_through_every_format() {
+        let packet = rights_packet("© 2024 Jane & Co", "Jane");
+        let sink = MemorySink::default();
+        let xmp_sink = XmpSink::new(&sink, packet.clone());
+        let img = test_util::gradient(16, 8);
+        for (name, format) in [
+            ("out.jpg", ImageFormat::Jpeg),
+            ("out.png", ImageFormat::Png),
+            ("out.webp", ImageFormat::WebP),
+        ] {
+            xmp_sink
+                .write(Path::new(name), &test_util::encode(&img, format))
+                .unwrap();
+        }
+
+        for (path, bytes) in sink.into_files() {
+            let read = read_xmp(&bytes).unwrap_or_else(|| panic!("no XMP in {}", path.display()));
+            assert_eq!(read, packet, "{}", path.display());
+            let text = String::from_utf8(read).unwrap();
+            assert!(text.contains("© 2024 Jane &amp; Co"));
+            assert_eq!(image::load_from_memory(&bytes).unwrap().width(), 16);
+        }
+    }
+
+    #[test]
+    fn embedding_replaces_the_previous_packet() {
+        let jpeg = test_util::encode(&test_util::gradient(8, 8), ImageFormat::Jpeg);
+        let first = embed_xmp(&jpeg, &rights_packet("old", "")).unwrap();
+        let second = embed_xmp(&first, &rights_packet("new", "")).unwrap();
+
+        let packet = String::from_utf8(read_xmp(&second).unwrap()).unwrap();
+        assert!(packet.contains("new") && !packet.contains("old"));
+    }
+}